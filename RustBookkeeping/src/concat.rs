@@ -0,0 +1,61 @@
+use crate::error::{TableError, TableResult};
+use crate::events::TableEvent;
+use crate::{Column, OrderedTable, TableChange, UnorderedTable, UnorderedTableMemento};
+
+/// Columns must agree in count, name and kind, position by position.
+pub(crate) fn check_compatible(left: &[Box<dyn Column>], right: &[Box<dyn Column>]) -> TableResult<()> {
+    if left.len() != right.len() {
+        return Err(TableError::SchemaMismatch(format!("{} columns vs {} columns", left.len(), right.len())));
+    }
+    for (l, r) in left.iter().zip(right) {
+        if l.name() != r.name() || l.kind() != r.kind() {
+            return Err(TableError::SchemaMismatch(format!(
+                "column '{}' ({:?}) vs '{}' ({:?})", l.name(), l.kind(), r.name(), r.kind()
+            )));
+        }
+    }
+    Ok(())
+}
+
+impl OrderedTable {
+    /// Append all rows of `other`, which must have the same columns (names and kinds, in order)
+    pub fn concat(&mut self, other: &OrderedTable) -> TableResult<()> {
+        check_compatible(&self.columns, &other.columns)?;
+        let nrows = self.nrows();
+        let indices: Vec<usize> = (0..other.nrows()).collect();
+        for (dst, src) in self.columns.iter_mut().zip(&other.columns) {
+            while dst.len() < nrows { dst.push_empty(); }
+            dst.extend_from(src.as_ref(), &indices);
+        }
+        self.reindex();
+        for row in nrows..self.nrows() { self.observers.emit(TableEvent::RowInserted { row }); }
+        Ok(())
+    }
+}
+
+impl UnorderedTable {
+    /// Append all rows of `other` in its logical order, which must have the same columns.
+    /// The new rows get fresh physical slots at the end rather than recycled ones.
+    #[allow(dead_code)]
+    pub fn concat(&mut self, other: &UnorderedTable) -> TableResult<()> {
+        check_compatible(&self.columns, &other.columns)?;
+        let start = self.next_physical_index;
+        let indices = other.logical_order.in_order();
+        for (dst, src) in self.columns.iter_mut().zip(&other.columns) {
+            // Slots past `next_physical_index` may hold rows whose insert was undone
+            dst.truncate(start);
+            while dst.len() < start { dst.push_empty(); }
+            dst.extend_from(src.as_ref(), &indices);
+        }
+        self.history.record(UnorderedTableMemento {
+            changes: vec![TableChange::LogicalOrder(self.logical_order.in_order()), TableChange::NextIndex(start)],
+        });
+        for phys_idx in start..start + indices.len() {
+            self.logical_order.append(phys_idx);
+        }
+        self.next_physical_index += indices.len();
+        self.reindex();
+        for row in self.nrows() - indices.len()..self.nrows() { self.observers.emit(TableEvent::RowInserted { row }); }
+        Ok(())
+    }
+}
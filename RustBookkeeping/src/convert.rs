@@ -0,0 +1,35 @@
+use crate::events::Observers;
+use crate::history::History;
+use crate::slots::{FreeSlots, SlotPolicy};
+use crate::{Column, OrderedTable, TreeArray, UnorderedTable};
+
+impl UnorderedTable {
+    /// Copy of the rows in logical order as an `OrderedTable` with the same columns
+    pub fn to_ordered(&self) -> OrderedTable {
+        let physical = self.logical_order.in_order();
+        let mut out = OrderedTable::new();
+        for col in &self.columns {
+            let mut dst = col.empty_like();
+            dst.extend_from(col.as_ref(), &physical);
+            out.columns.push(dst);
+        }
+        out
+    }
+}
+
+impl OrderedTable {
+    /// Copy as an `UnorderedTable` whose row `i` sits in physical slot `i`, with no free slots.
+    /// Indexes are not carried over.
+    pub fn to_unordered(&self) -> UnorderedTable {
+        let nrows = self.nrows();
+        let columns: Vec<Box<dyn Column>> = self.columns.iter()
+            .map(|col| {
+                let mut dst = col.empty_like();
+                dst.extend_from(col.as_ref(), &(0..nrows).collect::<Vec<usize>>());
+                dst
+            })
+            .collect();
+        let logical_order = TreeArray::from_slice(&(0..nrows).collect::<Vec<usize>>());
+        UnorderedTable { columns, logical_order, next_physical_index: nrows, free_physical: FreeSlots::new(SlotPolicy::default()), history: History::new(), indexes: Vec::new(), observers: Observers::default() }
+    }
+}
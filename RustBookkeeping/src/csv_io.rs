@@ -0,0 +1,195 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{Column, ColumnKind, OrderedTable, UnorderedTable};
+use crate::error::TableError;
+use crate::format::FormatOptions;
+use crate::value::Value;
+
+// ----------------------------- CSV records -----------------------------
+/// Reads one CSV record (RFC 4180 quoting, LF or CRLF line ends), None at EOF.
+/// Quoted fields may span lines. A quote only starts quoting at the start of a field;
+/// elsewhere it is part of the text (`5" screen`).
+fn read_record<R: BufRead>(reader: &mut R, line_no: &mut usize) -> io::Result<Option<Vec<String>>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 { return Ok(None); }
+    *line_no += 1;
+
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut at_field_start = true;
+    loop {
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if in_quotes && chars.peek() == Some(&'"') => { field.push('"'); chars.next(); }
+                '"' if in_quotes => in_quotes = false,
+                '"' if at_field_start => in_quotes = true,
+                ',' if !in_quotes => { record.push(std::mem::take(&mut field)); at_field_start = true; continue; }
+                '\r' | '\n' if !in_quotes => {}
+                _ => field.push(c),
+            }
+            at_field_start = false;
+        }
+        if !in_quotes { break; }
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid(*line_no, "unterminated quoted field"));
+        }
+        *line_no += 1;
+    }
+    record.push(field);
+    Ok(Some(record))
+}
+
+/// A record of one empty field is written as `""`, so it is not taken for a blank line.
+fn write_record<W: Write, S: AsRef<str>>(writer: &mut W, record: &[S]) -> io::Result<()> {
+    for (i, field) in record.iter().enumerate() {
+        if i > 0 { writer.write_all(b",")?; }
+        let field = field.as_ref();
+        if field.contains([',', '"', '\n', '\r']) || (record.len() == 1 && field.is_empty()) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\n")
+}
+
+fn invalid(line: usize, msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, msg))
+}
+
+/// Text written for a cell. Floats keep full precision and money is written without
+/// grouping (`1234.50 SEK`) so that a file read back is identical.
+fn field_text(val: &Value) -> String {
+    match val {
+        Value::Float(x) => x.to_string(),
+        Value::Double(x) => x.to_string(),
+        Value::Money { .. } => val.format_with(&FormatOptions::default()),
+        other => other.to_string(),
+    }
+}
+
+/// Reads a header line matching `schema` and then every record as a row of parsed values.
+/// Empty fields of non-text columns become the column default. Blank lines are skipped,
+/// unless there is a single column, where they are empty cells.
+fn read_rows<R: BufRead>(mut reader: R, schema: &[(&str, ColumnKind)]) -> io::Result<Vec<Vec<Option<Value>>>> {
+    let mut line_no = 0;
+    let header = read_record(&mut reader, &mut line_no)?.ok_or_else(|| invalid(0, "missing header"))?;
+    let expected: Vec<&str> = schema.iter().map(|(name, _)| *name).collect();
+    if header.iter().map(|h| h.trim()).ne(expected.iter().copied()) {
+        return Err(invalid(line_no, &format!("header {:?} does not match schema {:?}", header, expected)));
+    }
+
+    let mut rows = Vec::new();
+    while let Some(record) = read_record(&mut reader, &mut line_no)? {
+        if schema.len() > 1 && record.len() == 1 && record[0].is_empty() { continue; }
+        if record.len() != schema.len() {
+            return Err(invalid(line_no, &format!("expected {} fields, found {}", schema.len(), record.len())));
+        }
+        let row = record.iter().zip(schema)
+            .map(|(field, (name, kind))| {
+                if field.trim().is_empty() && *kind != ColumnKind::Str { return Ok(None); }
+                kind.parse(field).map(Some).map_err(|source| {
+                    let err = TableError::Parse { column: name.to_string(), source }.at_row(rows.len());
+                    io::Error::new(io::ErrorKind::InvalidData, err)
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn push_row(columns: &mut [Box<dyn Column>], row: Vec<Option<Value>>) {
+    for (val, col) in row.into_iter().zip(columns.iter_mut()) {
+        match val {
+            Some(v) => col.push(v),
+            None => col.push_empty(),
+        }
+    }
+}
+
+impl OrderedTable {
+    /// Write a header line of column names followed by one line per row
+    pub fn to_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let header: Vec<&str> = self.columns.iter().map(|c| c.name()).collect();
+        write_record(&mut writer, &header)?;
+        for r in 0..self.nrows() {
+            let record: Vec<String> = self.columns.iter()
+                .map(|c| if r < c.len() { field_text(&c.get(r)) } else { String::new() })
+                .collect();
+            write_record(&mut writer, &record)?;
+        }
+        writer.flush()
+    }
+
+    /// Build a table from CSV whose header names the columns of `schema` in order
+    #[allow(dead_code)]
+    pub fn from_csv<R: BufRead>(reader: R, schema: &[(&str, ColumnKind)]) -> io::Result<OrderedTable> {
+        let rows = read_rows(reader, schema)?;
+        let mut table = OrderedTable::new();
+        table.columns = schema.iter().map(|(name, kind)| kind.new_column(name)).collect();
+        for row in rows { push_row(&mut table.columns, row); }
+        Ok(table)
+    }
+}
+
+impl UnorderedTable {
+    /// Write a header line of column names followed by one line per row, in logical order
+    #[allow(dead_code)]
+    pub fn to_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let header: Vec<&str> = self.columns.iter().map(|c| c.name()).collect();
+        write_record(&mut writer, &header)?;
+        for phys_idx in self.logical_order.in_order() {
+            let record: Vec<String> = self.columns.iter().map(|c| field_text(&c.get(phys_idx))).collect();
+            write_record(&mut writer, &record)?;
+        }
+        writer.flush()
+    }
+
+    /// Build a table from CSV whose header names the columns of `schema` in order
+    pub fn from_csv<R: BufRead>(reader: R, schema: &[(&str, ColumnKind)]) -> io::Result<UnorderedTable> {
+        let rows = read_rows(reader, schema)?;
+        let mut table = UnorderedTable::new();
+        table.columns = schema.iter().map(|(name, kind)| kind.new_column(name)).collect();
+        for row in rows {
+            let phys_idx = table.next_physical_index;
+            push_row(&mut table.columns, row);
+            table.next_physical_index += 1;
+            table.logical_order.append(phys_idx);
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(text: &str) -> Vec<Vec<String>> {
+        let mut reader = text.as_bytes();
+        let mut line_no = 0;
+        std::iter::from_fn(|| read_record(&mut reader, &mut line_no).unwrap()).collect()
+    }
+
+    #[test]
+    fn quotes_open_only_at_the_start_of_a_field() {
+        assert_eq!(records("5\" screen,x\nnext,\"a,\"\"b\"\"\"\n"), [vec!["5\" screen", "x"], vec!["next", "a,\"b\""]]);
+    }
+
+    #[test]
+    fn single_empty_cells_survive_a_round_trip() {
+        let schema = [("Note", ColumnKind::Str)];
+        let mut table = OrderedTable::new();
+        table.columns = schema.iter().map(|(name, kind)| kind.new_column(name)).collect();
+        for note in ["a", "", "b"] { table.columns[0].push(Value::Str(note.to_string())); }
+        let mut csv = Vec::new();
+        table.to_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv.clone()).unwrap(), "Note\na\n\"\"\nb\n");
+        let read = OrderedTable::from_csv(csv.as_slice(), &schema).unwrap();
+        assert_eq!(read.nrows(), 3);
+        assert_eq!(read.get_cell(1, "Note"), Some(Value::Str(String::new())));
+    }
+}
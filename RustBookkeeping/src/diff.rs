@@ -0,0 +1,119 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use crate::concat::check_compatible;
+use crate::error::{TableError, TableResult};
+use crate::{Column, OrderedTable};
+use crate::value::Value;
+
+// ----------------------------- Change set -----------------------------
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellChange {
+    pub column: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModifiedRow {
+    /// Row index in the old table
+    pub old_index: usize,
+    /// Row index in the new table
+    pub new_index: usize,
+    pub changes: Vec<CellChange>,
+}
+
+/// Differences going from one table to another. Inserted rows carry their index in the
+/// new table, deleted rows their index in the old one.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TableDiff {
+    pub inserted: Vec<(usize, Vec<Value>)>,
+    pub deleted: Vec<(usize, Vec<Value>)>,
+    pub modified: Vec<ModifiedRow>,
+}
+
+impl TableDiff {
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty() && self.deleted.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl fmt::Display for TableDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |vals: &[Value]| vals.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+        for (idx, row) in &self.deleted {
+            writeln!(f, "- [{}] {}", idx, join(row))?;
+        }
+        for (idx, row) in &self.inserted {
+            writeln!(f, "+ [{}] {}", idx, join(row))?;
+        }
+        for m in &self.modified {
+            let changes: Vec<String> = m.changes.iter().map(|c| format!("{}: {} -> {}", c.column, c.before, c.after)).collect();
+            writeln!(f, "~ [{} -> {}] {}", m.old_index, m.new_index, changes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn row_values(columns: &[Box<dyn Column>], r: usize) -> Vec<Value> {
+    columns.iter().map(|c| if r < c.len() { c.get(r) } else { c.default_value() }).collect()
+}
+
+impl OrderedTable {
+    /// Row-by-row differences from `self` to `other`, pairing rows by position.
+    /// Both tables must have the same columns.
+    pub fn diff(&self, other: &OrderedTable) -> TableResult<TableDiff> {
+        check_compatible(&self.columns, &other.columns)?;
+        let (old_n, new_n) = (self.nrows(), other.nrows());
+        let mut diff = TableDiff::default();
+        for r in 0..old_n.min(new_n) {
+            self.diff_row(other, r, r, &mut diff);
+        }
+        diff.deleted = (new_n..old_n).map(|r| (r, row_values(&self.columns, r))).collect();
+        diff.inserted = (old_n..new_n).map(|r| (r, row_values(&other.columns, r))).collect();
+        Ok(diff)
+    }
+
+    /// Differences from `self` to `other`, pairing rows by their value in the `key` column
+    /// (with `Value::normalized_eq`). Repeated keys pair up in order of appearance.
+    #[allow(dead_code)]
+    pub fn diff_by(&self, other: &OrderedTable, key: &str) -> TableResult<TableDiff> {
+        check_compatible(&self.columns, &other.columns)?;
+        let key_idx = self.column_index(key).ok_or_else(|| TableError::UnknownColumn(key.to_string()))?;
+        let key_of = |t: &OrderedTable, r: usize| {
+            let col = &t.columns[key_idx];
+            if r < col.len() { Some(col.get(r).normalized()) } else { None }
+        };
+
+        let mut new_rows: HashMap<Option<Value>, VecDeque<usize>> = HashMap::new();
+        for r in 0..other.nrows() {
+            new_rows.entry(key_of(other, r)).or_default().push_back(r);
+        }
+        let mut diff = TableDiff::default();
+        let mut matched = vec![false; other.nrows()];
+        for r in 0..self.nrows() {
+            match new_rows.get_mut(&key_of(self, r)).and_then(|rows| rows.pop_front()) {
+                Some(n) => {
+                    matched[n] = true;
+                    self.diff_row(other, r, n, &mut diff);
+                }
+                None => diff.deleted.push((r, row_values(&self.columns, r))),
+            }
+        }
+        diff.inserted = (0..other.nrows()).filter(|&n| !matched[n]).map(|n| (n, row_values(&other.columns, n))).collect();
+        Ok(diff)
+    }
+
+    fn diff_row(&self, other: &OrderedTable, old_index: usize, new_index: usize, diff: &mut TableDiff) {
+        let before = row_values(&self.columns, old_index);
+        let after = row_values(&other.columns, new_index);
+        let changes: Vec<CellChange> = self.columns.iter().zip(before).zip(after)
+            .filter(|((_, b), a)| b != a)
+            .map(|((col, before), after)| CellChange { column: col.name().to_string(), before, after })
+            .collect();
+        if !changes.is_empty() {
+            diff.modified.push(ModifiedRow { old_index, new_index, changes });
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::HashSet;
+
+use crate::error::{TableError, TableResult};
+use crate::events::TableEvent;
+use crate::OrderedTable;
+use crate::value::Value;
+
+impl OrderedTable {
+    /// Remove rows identical to an earlier row. Returns the number of rows removed.
+    #[allow(dead_code)]
+    pub fn distinct(&mut self) -> usize {
+        let all: Vec<usize> = (0..self.columns.len()).collect();
+        self.retain_first_by(&all)
+    }
+
+    /// Remove rows whose values in `columns` equal those of an earlier row, keeping the first.
+    /// Returns the number of rows removed.
+    pub fn distinct_by(&mut self, columns: &[&str]) -> TableResult<usize> {
+        let key_cols = columns.iter()
+            .map(|name| self.column_index(name).ok_or_else(|| TableError::UnknownColumn(name.to_string())))
+            .collect::<TableResult<Vec<usize>>>()?;
+        Ok(self.retain_first_by(&key_cols))
+    }
+
+    fn retain_first_by(&mut self, key_cols: &[usize]) -> usize {
+        let nrows = self.nrows();
+        for col in self.columns.iter_mut() {
+            while col.len() < nrows { col.push_empty(); }
+        }
+        let mut seen: HashSet<Vec<Value>> = HashSet::with_capacity(nrows);
+        let keep: Vec<usize> = (0..nrows)
+            .filter(|&r| seen.insert(key_cols.iter().map(|&c| self.columns[c].get(r)).collect()))
+            .collect();
+        for col in self.columns.iter_mut() { col.reorder(&keep); }
+        self.reindex();
+        if keep.len() < nrows { self.observers.emit(TableEvent::RowsChanged); }
+        nrows - keep.len()
+    }
+}
@@ -0,0 +1,25 @@
+use crate::error::{TableError, TableResult};
+use crate::{OrderedTable, UnorderedTable};
+use crate::value::Value;
+
+impl OrderedTable {
+    /// Insert a copy of row `idx` right after it. Returns the index of the copy.
+    pub fn duplicate_row(&mut self, idx: usize) -> TableResult<usize> {
+        if idx >= self.nrows() { return Err(TableError::RowOutOfBounds { index: idx, len: self.nrows() }); }
+        let row: Vec<Value> = self.columns.iter()
+            .map(|col| if idx < col.len() { col.get(idx) } else { col.default_value() })
+            .collect();
+        self.insert_row(idx + 1, row);
+        Ok(idx + 1)
+    }
+}
+
+impl UnorderedTable {
+    /// Insert a copy of the row at user index `idx` right after it, in a slot of its own.
+    /// Returns the user index of the copy.
+    pub fn duplicate_row(&mut self, idx: usize) -> TableResult<usize> {
+        let row = self.get_row(idx)?;
+        self.try_insert_row(idx + 1, row)?;
+        Ok(idx + 1)
+    }
+}
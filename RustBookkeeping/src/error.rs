@@ -0,0 +1,86 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::ColumnKind;
+use crate::value::{ParseValueError, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableError {
+    UnknownColumn(String),
+    ColumnOutOfBounds { index: usize, len: usize },
+    RowOutOfBounds { index: usize, len: usize },
+    /// A value of the wrong kind for its column
+    TypeMismatch { column: String, expected: ColumnKind, value: Value },
+    /// Text that does not parse as the column's kind
+    Parse { column: String, source: ParseValueError },
+    SchemaMismatch(String),
+    InvalidOperation(String),
+    /// A shared table whose writer panicked, so it may be half changed
+    Poisoned,
+    /// `source` happened while handling the row at `row`
+    AtRow { row: usize, source: Box<TableError> },
+}
+
+pub type TableResult<T> = Result<T, TableError>;
+
+impl TableError {
+    /// Locate the error at a row of the table being worked on
+    pub fn at_row(self, row: usize) -> TableError {
+        TableError::AtRow { row, source: Box::new(self) }
+    }
+
+    /// The row the error refers to, if any
+    pub fn row(&self) -> Option<usize> {
+        match self {
+            TableError::RowOutOfBounds { index, .. } => Some(*index),
+            TableError::AtRow { row, .. } => Some(*row),
+            _ => None,
+        }
+    }
+
+    /// The column the error refers to, if any
+    pub fn column(&self) -> Option<&str> {
+        match self {
+            TableError::UnknownColumn(name) => Some(name),
+            TableError::TypeMismatch { column, .. } | TableError::Parse { column, .. } => Some(column),
+            TableError::AtRow { source, .. } => source.column(),
+            _ => None,
+        }
+    }
+
+    /// The offending value, if any
+    #[allow(dead_code)]
+    pub fn value(&self) -> Option<&Value> {
+        match self {
+            TableError::TypeMismatch { value, .. } => Some(value),
+            TableError::AtRow { source, .. } => source.value(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::UnknownColumn(name) => write!(f, "unknown column '{}'", name),
+            TableError::ColumnOutOfBounds { index, len } => write!(f, "column {} out of bounds for table with {} columns", index, len),
+            TableError::RowOutOfBounds { index, len } => write!(f, "row {} out of bounds for table with {} rows", index, len),
+            TableError::TypeMismatch { column, expected, value } => write!(f, "column '{}' is {:?}, got {:?}", column, expected, value),
+            TableError::Parse { column, source } => write!(f, "column '{}': {}", column, source),
+            TableError::SchemaMismatch(detail) => write!(f, "schema mismatch: {}", detail),
+            TableError::InvalidOperation(detail) => write!(f, "invalid operation: {}", detail),
+            TableError::Poisoned => write!(f, "a writer panicked while holding the shared table"),
+            TableError::AtRow { row, source } => write!(f, "row {}: {}", row, source),
+        }
+    }
+}
+
+impl Error for TableError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TableError::Parse { source, .. } => Some(source),
+            TableError::AtRow { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
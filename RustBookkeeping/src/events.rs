@@ -0,0 +1,63 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{OrderedTable, UnorderedTable};
+
+// ----------------------------- Table events -----------------------------
+/// A mutation of a table, as reported to `on_change` callbacks. Rows are
+/// indices as the table shows them (user indices for an `UnorderedTable`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableEvent {
+    RowInserted { row: usize },
+    RowUpdated { row: usize },
+    CellUpdated { row: usize, column: String },
+    RowDeleted { row: usize },
+    ColumnAdded { column: String },
+    ColumnRemoved { column: String },
+    ColumnsReordered,
+    /// Many rows changed at once (sorting, deduplication, truncation, undo/redo);
+    /// the table has to be read again.
+    RowsChanged,
+}
+
+type Callback = Arc<dyn Fn(&TableEvent) + Send + Sync>;
+
+/// The callbacks registered on a table. A cloned table starts without any: it is
+/// a new table, and observers of the original are not interested in it.
+#[derive(Default)]
+pub(crate) struct Observers {
+    callbacks: Vec<Callback>,
+}
+
+impl Observers {
+    pub(crate) fn add(&mut self, callback: Callback) { self.callbacks.push(callback) }
+
+    pub(crate) fn emit(&self, event: TableEvent) {
+        for callback in &self.callbacks { callback(&event); }
+    }
+}
+
+impl Clone for Observers {
+    fn clone(&self) -> Self { Observers::default() }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} observers", self.callbacks.len())
+    }
+}
+
+impl OrderedTable {
+    /// Call `callback` after every mutation of this table
+    pub fn on_change<F: Fn(&TableEvent) + Send + Sync + 'static>(&mut self, callback: F) {
+        self.observers.add(Arc::new(callback))
+    }
+}
+
+impl UnorderedTable {
+    /// Call `callback` after every mutation of this table
+    #[allow(dead_code)]
+    pub fn on_change<F: Fn(&TableEvent) + Send + Sync + 'static>(&mut self, callback: F) {
+        self.observers.add(Arc::new(callback))
+    }
+}
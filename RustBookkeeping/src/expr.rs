@@ -0,0 +1,270 @@
+use std::cmp::Ordering;
+use std::ops;
+
+use crate::error::{TableError, TableResult};
+use crate::query::RowView;
+use crate::value::{Money, Value};
+use crate::OrderedTable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp { Eq, Ne, Lt, Le, Gt, Ge }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithOp { Add, Sub, Mul, Div }
+
+/// A row-level expression, built with `col`/`lit` and combinators and evaluated
+/// against one row at a time:
+/// `col("Amount").gt(lit(1000)).and(col("Account").eq(lit(4010)))`.
+///
+/// Numbers compare across integer/float kinds, and a plain number compared with or
+/// applied to money is taken in major units of that money's currency. Comparing text
+/// with a number is an error, so `lit("4010")` does not silently miss an integer column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Column(String),
+    Literal(Value),
+    Compare(Box<Expr>, CmpOp, Box<Expr>),
+    Arith(Box<Expr>, ArithOp, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+/// Reference to the named column of the current row
+pub fn col(name: &str) -> Expr { Expr::Column(name.to_string()) }
+
+/// A constant
+pub fn lit<V: Into<Value>>(value: V) -> Expr { Expr::Literal(value.into()) }
+
+impl Expr {
+    pub fn eq(self, other: Expr) -> Expr { self.compare(CmpOp::Eq, other) }
+    #[allow(dead_code)]
+    pub fn ne(self, other: Expr) -> Expr { self.compare(CmpOp::Ne, other) }
+    #[allow(dead_code)]
+    pub fn lt(self, other: Expr) -> Expr { self.compare(CmpOp::Lt, other) }
+    #[allow(dead_code)]
+    pub fn le(self, other: Expr) -> Expr { self.compare(CmpOp::Le, other) }
+    pub fn gt(self, other: Expr) -> Expr { self.compare(CmpOp::Gt, other) }
+    #[allow(dead_code)]
+    pub fn ge(self, other: Expr) -> Expr { self.compare(CmpOp::Ge, other) }
+    pub fn and(self, other: Expr) -> Expr { Expr::And(Box::new(self), Box::new(other)) }
+    #[allow(dead_code)]
+    pub fn or(self, other: Expr) -> Expr { Expr::Or(Box::new(self), Box::new(other)) }
+
+    fn compare(self, op: CmpOp, other: Expr) -> Expr { Expr::Compare(Box::new(self), op, Box::new(other)) }
+
+    /// Names of all columns the expression refers to
+    pub fn columns(&self) -> Vec<&str> {
+        let mut out = Vec::new();
+        self.collect_columns(&mut out);
+        out
+    }
+
+    fn collect_columns<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            Expr::Column(name) => out.push(name),
+            Expr::Literal(_) => {}
+            Expr::Compare(l, _, r) | Expr::Arith(l, _, r) | Expr::And(l, r) | Expr::Or(l, r) => {
+                l.collect_columns(out);
+                r.collect_columns(out);
+            }
+            Expr::Not(e) => e.collect_columns(out),
+        }
+    }
+
+    /// Evaluates the expression for one row. Comparisons and logical operators yield `Bool`.
+    pub fn eval(&self, row: &RowView) -> TableResult<Value> {
+        match self {
+            Expr::Column(name) => row.get(name).ok_or_else(|| TableError::UnknownColumn(name.clone())),
+            Expr::Literal(v) => Ok(v.clone()),
+            Expr::Compare(l, op, r) => {
+                let (a, b) = (l.eval(row)?, r.eval(row)?);
+                let is_number = |v: &Value| v.numeric().is_some() || money_of(v).is_some();
+                let is_text = |v: &Value| matches!(v, Value::Str(_));
+                if (is_text(&a) && is_number(&b)) || (is_number(&a) && is_text(&b)) {
+                    return Err(TableError::InvalidOperation(format!("cannot compare {:?} with {:?}", a, b)));
+                }
+                let ord = compare_values(&a, &b);
+                Ok(Value::Bool(match op {
+                    CmpOp::Eq => ord == Ordering::Equal,
+                    CmpOp::Ne => ord != Ordering::Equal,
+                    CmpOp::Lt => ord == Ordering::Less,
+                    CmpOp::Le => ord != Ordering::Greater,
+                    CmpOp::Gt => ord == Ordering::Greater,
+                    CmpOp::Ge => ord != Ordering::Less,
+                }))
+            }
+            Expr::Arith(l, op, r) => arith(&l.eval(row)?, *op, &r.eval(row)?),
+            Expr::And(l, r) => Ok(Value::Bool(l.test(row)? && r.test(row)?)),
+            Expr::Or(l, r) => Ok(Value::Bool(l.test(row)? || r.test(row)?)),
+            Expr::Not(e) => Ok(Value::Bool(!e.test(row)?)),
+        }
+    }
+
+    /// Evaluates a predicate; anything but a `Bool` result is an error.
+    pub fn test(&self, row: &RowView) -> TableResult<bool> {
+        match self.eval(row)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(TableError::InvalidOperation(format!("expected a boolean, got {:?}", other))),
+        }
+    }
+}
+
+impl ops::Not for Expr {
+    type Output = Expr;
+    fn not(self) -> Expr { Expr::Not(Box::new(self)) }
+}
+
+macro_rules! arith_op {
+    ($trait:ident, $method:ident, $op:expr) => {
+        impl ops::$trait for Expr {
+            type Output = Expr;
+            fn $method(self, other: Expr) -> Expr { Expr::Arith(Box::new(self), $op, Box::new(other)) }
+        }
+    };
+}
+
+arith_op!(Add, add, ArithOp::Add);
+arith_op!(Sub, sub, ArithOp::Sub);
+arith_op!(Mul, mul, ArithOp::Mul);
+arith_op!(Div, div, ArithOp::Div);
+
+/// A plain number as money of `like`'s currency, reading it in major units.
+fn as_money(n: Result<i64, f64>, like: &Money) -> Option<Money> {
+    let scale = 10i64.pow(like.currency.minor_digits());
+    let amount = match n {
+        Ok(i) => i.checked_mul(scale)?,
+        Err(f) => (f * scale as f64).round() as i64,
+    };
+    Some(Money::new(amount, like.currency))
+}
+
+fn money_of(v: &Value) -> Option<Money> {
+    match v {
+        Value::Money { amount, currency } => Some(Money::new(*amount, *currency)),
+        _ => None,
+    }
+}
+
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (money_of(a), money_of(b), a.numeric(), b.numeric()) {
+        (Some(m), None, _, Some(n)) => match as_money(n, &m) {
+            Some(n) => m.amount.cmp(&n.amount),
+            None => if n.is_ok_and(|i| i < 0) { Ordering::Greater } else { Ordering::Less },
+        },
+        (None, Some(_), Some(_), _) => compare_values(b, a).reverse(),
+        _ => a.compare(b),
+    }
+}
+
+fn arith(a: &Value, op: ArithOp, b: &Value) -> TableResult<Value> {
+    let invalid = || TableError::InvalidOperation(format!("cannot apply {:?} to {:?} and {:?}", op, a, b));
+    let result = match (op, money_of(a), money_of(b), a.numeric(), b.numeric()) {
+        (ArithOp::Add | ArithOp::Sub, Some(x), Some(y), ..) => add_money(op, &x, &y),
+        (ArithOp::Add | ArithOp::Sub, Some(m), None, _, Some(n)) => as_money(n, &m).and_then(|n| add_money(op, &m, &n)),
+        (ArithOp::Add | ArithOp::Sub, None, Some(m), Some(n), _) => as_money(n, &m).and_then(|n| add_money(op, &n, &m)),
+        (ArithOp::Add | ArithOp::Sub, None, None, ..) => match (a, b) {
+            (Value::Duration(x), Value::Duration(y)) => {
+                let secs = if op == ArithOp::Add { x.as_secs().checked_add(y.as_secs()) } else { x.as_secs().checked_sub(y.as_secs()) };
+                secs.map(|s| Value::Duration(crate::value::Duration::from_secs(s)))
+            }
+            _ => numeric_op(op, a.numeric(), b.numeric()),
+        },
+        (ArithOp::Mul, Some(m), None, _, Some(n)) | (ArithOp::Mul, None, Some(m), Some(n), _) => scale_money(&m, n, false),
+        (ArithOp::Div, Some(m), None, _, Some(n)) => scale_money(&m, n, true),
+        (ArithOp::Mul | ArithOp::Div, None, None, ..) => numeric_op(op, a.numeric(), b.numeric()),
+        _ => None,
+    };
+    result.ok_or_else(invalid)
+}
+
+/// Money plus or minus money of the same currency; None on overflow or mixed currencies.
+fn add_money(op: ArithOp, x: &Money, y: &Money) -> Option<Value> {
+    let sum = if op == ArithOp::Add { x.checked_add(y) } else { x.checked_sub(y) };
+    sum.ok().map(Value::from)
+}
+
+/// Integer operands give `Long`, anything involving a float gives `Double`.
+/// Integer division by zero and overflow give None.
+fn numeric_op(op: ArithOp, a: Option<Result<i64, f64>>, b: Option<Result<i64, f64>>) -> Option<Value> {
+    match (a?, b?) {
+        (Ok(x), Ok(y)) => match op {
+            ArithOp::Add => x.checked_add(y),
+            ArithOp::Sub => x.checked_sub(y),
+            ArithOp::Mul => x.checked_mul(y),
+            ArithOp::Div => x.checked_div(y),
+        }.map(Value::Long),
+        (x, y) => {
+            let as_f64 = |n: Result<i64, f64>| n.map_or_else(|f| f, |i| i as f64);
+            let (x, y) = (as_f64(x), as_f64(y));
+            Some(Value::Double(match op {
+                ArithOp::Add => x + y,
+                ArithOp::Sub => x - y,
+                ArithOp::Mul => x * y,
+                ArithOp::Div => x / y,
+            }))
+        }
+    }
+}
+
+/// Money times or divided by a plain factor, rounded to the nearest minor unit.
+fn scale_money(m: &Money, factor: Result<i64, f64>, divide: bool) -> Option<Value> {
+    let amount = match (factor, divide) {
+        (Ok(n), false) => m.amount.checked_mul(n)?,
+        (Ok(n), true) => m.amount.checked_div(n)?,
+        (Err(f), false) => (m.amount as f64 * f).round() as i64,
+        (Err(f), true) if f != 0.0 => (m.amount as f64 / f).round() as i64,
+        (Err(_), true) => return None,
+    };
+    Some(Value::from(Money::new(amount, m.currency)))
+}
+
+impl OrderedTable {
+    /// New table with the same columns, holding the rows for which `expr` evaluates to true.
+    /// Unknown column names and non-boolean or ill-typed expressions are errors. Rows
+    /// lacking a referenced cell never match.
+    pub fn query(&self, expr: Expr) -> TableResult<OrderedTable> {
+        if let Some(name) = expr.columns().into_iter().find(|name| self.column_index(name).is_none()) {
+            return Err(TableError::UnknownColumn(name.to_string()));
+        }
+        let mut failure = None;
+        let out = self.filter(|row| {
+            if failure.is_some() { return false; }
+            match expr.test(&row) {
+                Ok(keep) => keep,
+                // Every column exists, so this is a row shorter than one of them
+                Err(TableError::UnknownColumn(_)) => false,
+                Err(e) => { failure = Some(e); false }
+            }
+        });
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(out),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Table, TableColumn, TableTrait};
+
+    fn ledger() -> OrderedTable {
+        let mut table = OrderedTable::new();
+        table.add_column(TableColumn::<i32>::new("Account"));
+        table.add_column(TableColumn::<String>::new("Text"));
+        table.append_row(vec![Value::Int(4010), Value::Str("Goods".to_string())]);
+        table.append_row(vec![Value::Int(1930), Value::Str("Bank".to_string())]);
+        table
+    }
+
+    #[test]
+    fn comparing_text_with_a_number_is_an_error() {
+        let table = ledger();
+        assert_eq!(table.query(col("Account").eq(lit(4010))).unwrap().nrows(), 1);
+        assert_eq!(table.query(col("Text").eq(lit("Bank"))).unwrap().nrows(), 1);
+        let err = table.query(col("Account").eq(lit("4010"))).unwrap_err();
+        assert!(matches!(err, TableError::InvalidOperation(_)));
+        assert!(table.query(col("Text").gt(lit(1.5))).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::value::{Money, Value};
+
+// ----------------------------- Format options -----------------------------
+/// How `Value::Date` (seconds since the Unix epoch, UTC) is printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum DateStyle {
+    /// The raw timestamp, as `Display` prints it.
+    Timestamp,
+    /// `2024-03-31`
+    Iso,
+    /// `31.03.2024` with the given separator.
+    DayMonthYear(char),
+    /// `03/31/2024` with the given separator.
+    MonthDayYear(char),
+}
+
+/// Locale settings used when rendering values for people rather than for storage.
+/// The default reproduces `Display`: `.` as decimal separator, no grouping, raw dates,
+/// two decimals for `Float` and four for `Double`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    pub decimal_separator: char,
+    pub thousands_separator: Option<char>,
+    pub date_style: DateStyle,
+    pub float_precision: usize,
+    pub double_precision: usize,
+    /// Per-column overrides of both precisions, looked up by column name when rendering tables.
+    pub column_precision: Vec<(String, usize)>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            decimal_separator: '.',
+            thousands_separator: None,
+            date_style: DateStyle::Timestamp,
+            float_precision: 2,
+            double_precision: 4,
+            column_precision: Vec::new(),
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Swedish books: `12 345,67` and ISO dates.
+    pub fn swedish() -> Self {
+        Self { decimal_separator: ',', thousands_separator: Some(' '), date_style: DateStyle::Iso, ..Self::default() }
+    }
+
+    #[allow(dead_code)]
+    pub fn decimal_separator(mut self, sep: char) -> Self { self.decimal_separator = sep; self }
+    #[allow(dead_code)]
+    pub fn thousands_separator(mut self, sep: Option<char>) -> Self { self.thousands_separator = sep; self }
+    #[allow(dead_code)]
+    pub fn date_style(mut self, style: DateStyle) -> Self { self.date_style = style; self }
+    /// Sets the number of decimals for both `Float` and `Double`.
+    pub fn precision(mut self, digits: usize) -> Self { self.float_precision = digits; self.double_precision = digits; self }
+    #[allow(dead_code)]
+    pub fn float_precision(mut self, digits: usize) -> Self { self.float_precision = digits; self }
+    #[allow(dead_code)]
+    pub fn double_precision(mut self, digits: usize) -> Self { self.double_precision = digits; self }
+    pub fn column_precision(mut self, column: &str, digits: usize) -> Self {
+        self.column_precision.retain(|(name, _)| name != column);
+        self.column_precision.push((column.to_string(), digits));
+        self
+    }
+
+    /// The options to use for cells of `column`, applying any precision override.
+    pub fn for_column(&self, column: &str) -> FormatOptions {
+        match self.column_precision.iter().find(|(name, _)| name == column) {
+            Some((_, digits)) => FormatOptions { column_precision: Vec::new(), ..self.clone() }.precision(*digits),
+            None => self.clone(),
+        }
+    }
+
+    /// Re-punctuates a plain `-1234.5` style number according to these options.
+    fn number(&self, plain: &str) -> String {
+        let (sign, digits) = match plain.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", plain),
+        };
+        let (units, fraction) = match digits.split_once('.') {
+            Some((u, f)) => (u, Some(f)),
+            None => (digits, None),
+        };
+        let units = match self.thousands_separator {
+            Some(sep) => group_thousands(units, sep),
+            None => units.to_string(),
+        };
+        match fraction {
+            Some(f) => format!("{}{}{}{}", sign, units, self.decimal_separator, f),
+            None => format!("{}{}", sign, units),
+        }
+    }
+
+    fn date(&self, timestamp: u64) -> String {
+        let (y, m, d) = civil_from_days((timestamp / 86_400) as i64);
+        match self.date_style {
+            DateStyle::Timestamp => timestamp.to_string(),
+            DateStyle::Iso => format!("{:04}-{:02}-{:02}", y, m, d),
+            DateStyle::DayMonthYear(sep) => format!("{:02}{sep}{:02}{sep}{:04}", d, m, y),
+            DateStyle::MonthDayYear(sep) => format!("{:02}{sep}{:02}{sep}{:04}", m, d, y),
+        }
+    }
+
+    fn money(&self, money: &Money) -> String {
+        let digits = money.currency.minor_digits();
+        let scale = 10i64.pow(digits);
+        let abs = money.amount.unsigned_abs();
+        let sign = if money.amount < 0 { "-" } else { "" };
+        let plain = if digits == 0 {
+            format!("{}{}", sign, abs)
+        } else {
+            format!("{}{}.{:0width$}", sign, abs / scale as u64, abs % scale as u64, width = digits as usize)
+        };
+        format!("{} {}", self.number(&plain), money.currency)
+    }
+}
+
+impl Value {
+    /// Formats the value for display using `opts`. Text-like and clock values are
+    /// unaffected; numbers, money and dates follow the locale settings.
+    pub fn format_with(&self, opts: &FormatOptions) -> String {
+        match self {
+            Value::Int(_) | Value::Long(_) => opts.number(&self.to_string()),
+            Value::Float(x) => opts.number(&format!("{:.*}", opts.float_precision, x)),
+            Value::Double(x) => opts.number(&format!("{:.*}", opts.double_precision, x)),
+            Value::Money { amount, currency } => opts.money(&Money::new(*amount, *currency)),
+            Value::Date(ts) => opts.date(*ts),
+            _ => self.to_string(),
+        }
+    }
+
+    /// `Display` with a custom number of decimals for `Float`/`Double`.
+    #[allow(dead_code)]
+    pub fn format_with_precision(&self, digits: usize) -> String {
+        self.format_with(&FormatOptions::default().precision(digits))
+    }
+}
+
+pub(crate) fn group_thousands(digits: &str, separator: char) -> String {
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) { out.push(separator); }
+        out.push(c);
+    }
+    out
+}
+
+/// Days since 1970-01-01 to a proleptic Gregorian (year, month, day).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
@@ -0,0 +1,50 @@
+/// A target that can apply a memento, returning the memento that reverts it.
+pub trait TargetMementoTrait<T> {
+    fn apply_memento(&mut self, memento: &T) -> T;
+}
+
+/// Undo and redo stacks of mementos.
+#[derive(Debug, Clone)]
+pub struct History<T: Clone> {
+    undo_stack: Vec<T>,
+    redo_stack: Vec<T>,
+}
+
+impl<T: Clone> Default for History<T> {
+    fn default() -> Self { Self::new() }
+}
+
+impl<T: Clone> History<T> {
+    pub fn new() -> Self {
+        Self { undo_stack: Vec::new(), redo_stack: Vec::new() }
+    }
+
+    /// Record the memento that undoes a change just made; forgets anything redoable
+    pub fn record(&mut self, memento: T) {
+        self.undo_stack.push(memento);
+        self.redo_stack.clear();
+    }
+
+    pub fn undoable(&self) -> bool { !self.undo_stack.is_empty() }
+
+    pub fn redoable(&self) -> bool { !self.redo_stack.is_empty() }
+
+    pub fn undo<U: TargetMementoTrait<T>>(&mut self, target: &mut U) -> bool {
+        let Some(memento) = self.undo_stack.pop() else { return false };
+        let inverse = target.apply_memento(&memento);
+        self.redo_stack.push(inverse);
+        true
+    }
+
+    pub fn redo<U: TargetMementoTrait<T>>(&mut self, target: &mut U) -> bool {
+        let Some(memento) = self.redo_stack.pop() else { return false };
+        let inverse = target.apply_memento(&memento);
+        self.undo_stack.push(inverse);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
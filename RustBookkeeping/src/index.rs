@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use crate::error::{TableError, TableResult};
+use crate::{OrderedTable, UnorderedTable};
+use crate::value::Value;
+
+/// Rows of an `OrderedTable` grouped by the normalized value of one column.
+/// Kept in step with the table by its row mutators; bulk reorderings rebuild it.
+#[derive(Debug, Clone)]
+pub(crate) struct HashIndex {
+    column: String,
+    rows: HashMap<Value, Vec<usize>>,
+}
+
+impl HashIndex {
+    fn add(&mut self, key: Value, row: usize) {
+        let rows = self.rows.entry(key).or_default();
+        let pos = rows.partition_point(|&r| r < row);
+        rows.insert(pos, row);
+    }
+
+    fn remove(&mut self, key: &Value, row: usize) {
+        if let Some(rows) = self.rows.get_mut(key) {
+            rows.retain(|&r| r != row);
+            if rows.is_empty() { self.rows.remove(key); }
+        }
+    }
+
+    /// Renumbers rows at or after `from` by `delta` (+1 for an insert, -1 for a delete)
+    fn shift(&mut self, from: usize, delta: isize) {
+        for rows in self.rows.values_mut() {
+            for r in rows.iter_mut().filter(|r| **r >= from) { *r = r.wrapping_add_signed(delta); }
+        }
+    }
+}
+
+impl OrderedTable {
+    /// Build a hash index on `column`, making `find_by` on it O(1). Creating an index
+    /// that already exists rebuilds it.
+    pub fn create_index(&mut self, column: &str) -> TableResult<()> {
+        if self.column_index(column).is_none() { return Err(TableError::UnknownColumn(column.to_string())); }
+        self.indexes.retain(|ix| ix.column != column);
+        let mut index = HashIndex { column: column.to_string(), rows: HashMap::new() };
+        for r in 0..self.nrows() {
+            if let Some(key) = self.index_key(column, r) { index.add(key, r); }
+        }
+        self.indexes.push(index);
+        Ok(())
+    }
+
+    /// Drop the index on `column`. Returns false if there was none.
+    pub fn drop_index(&mut self, column: &str) -> bool {
+        let before = self.indexes.len();
+        self.indexes.retain(|ix| ix.column != column);
+        self.indexes.len() != before
+    }
+
+    #[allow(dead_code)]
+    pub fn has_index(&self, column: &str) -> bool { self.indexes.iter().any(|ix| ix.column == column) }
+
+    /// Indices of the rows whose `column` equals `value` (after normalization, so
+    /// `Int(7)` finds `Long(7)`), ascending. Uses the index on `column` if there is one,
+    /// otherwise scans the column.
+    pub fn find_by(&self, column: &str, value: &Value) -> TableResult<Vec<usize>> {
+        if self.column_index(column).is_none() { return Err(TableError::UnknownColumn(column.to_string())); }
+        let key = value.normalized();
+        match self.indexes.iter().find(|ix| ix.column == column) {
+            Some(index) => Ok(index.rows.get(&key).cloned().unwrap_or_default()),
+            None => Ok((0..self.nrows()).filter(|&r| self.index_key(column, r).as_ref() == Some(&key)).collect()),
+        }
+    }
+
+    fn index_key(&self, column: &str, row: usize) -> Option<Value> {
+        let col = self.column(column)?;
+        Some(if row < col.len() { col.get(row) } else { col.default_value() }.normalized())
+    }
+
+    /// Record a row that now sits at `row`, after any rows from there on moved down.
+    pub(crate) fn index_inserted(&mut self, row: usize) {
+        if self.indexes.is_empty() { return; }
+        let last = row + 1 == self.nrows();
+        let keys: Vec<Option<Value>> = self.indexes.iter().map(|ix| self.index_key(&ix.column, row)).collect();
+        for (index, key) in self.indexes.iter_mut().zip(keys) {
+            if !last { index.shift(row, 1); }
+            if let Some(key) = key { index.add(key, row); }
+        }
+    }
+
+    /// Forget the row that was at `row` and held `removed` (in column order).
+    pub(crate) fn index_removed(&mut self, row: usize, removed: &[Value]) {
+        if self.indexes.is_empty() { return; }
+        let positions: Vec<Option<usize>> = self.indexes.iter().map(|ix| self.column_index(&ix.column)).collect();
+        for (index, pos) in self.indexes.iter_mut().zip(positions) {
+            if let Some(old) = pos.and_then(|c| removed.get(c)) { index.remove(&old.normalized(), row); }
+            index.shift(row + 1, -1);
+        }
+    }
+
+    /// Move `row` from the key `old` to its current value in the index on `column`, if any.
+    pub(crate) fn index_changed(&mut self, row: usize, column: &str, old: &Value) {
+        let Some(new) = self.index_key(column, row) else { return };
+        if let Some(index) = self.indexes.iter_mut().find(|ix| ix.column == column) {
+            index.remove(&old.normalized(), row);
+            index.add(new, row);
+        }
+    }
+
+    /// Rebuild every index, after an operation that moved rows around wholesale.
+    pub(crate) fn reindex(&mut self) {
+        let columns: Vec<String> = self.indexes.iter().map(|ix| ix.column.clone()).collect();
+        self.indexes.clear();
+        for column in columns {
+            // Indexes on columns that have since been removed are dropped
+            let _ = self.create_index(&column);
+        }
+    }
+}
+
+impl UnorderedTable {
+    /// Build a hash index from `column`'s values to physical slots. It follows cell
+    /// writes only, so reordering rows never touches it. Creating an index that already
+    /// exists rebuilds it.
+    pub fn create_index(&mut self, column: &str) -> TableResult<()> {
+        let col = self.columns.iter().find(|c| c.name() == column)
+            .ok_or_else(|| TableError::UnknownColumn(column.to_string()))?;
+        let mut index = HashIndex { column: column.to_string(), rows: HashMap::new() };
+        for phys in 0..self.next_physical_index {
+            let val = if phys < col.len() { col.get(phys) } else { col.default_value() };
+            index.add(val.normalized(), phys);
+        }
+        self.indexes.retain(|ix| ix.column != column);
+        self.indexes.push(index);
+        Ok(())
+    }
+
+    /// Drop the index on `column`. Returns false if there was none.
+    pub fn drop_index(&mut self, column: &str) -> bool {
+        let before = self.indexes.len();
+        self.indexes.retain(|ix| ix.column != column);
+        self.indexes.len() != before
+    }
+
+    #[allow(dead_code)]
+    pub fn has_index(&self, column: &str) -> bool { self.indexes.iter().any(|ix| ix.column == column) }
+
+    /// Logical indices of the rows whose `column` equals `value` (after normalization),
+    /// ascending. With an index each matching slot's position is looked up in the logical
+    /// order, O(k log n) for k matches; without one the column is scanned.
+    pub fn find(&self, column: &str, value: &Value) -> TableResult<Vec<usize>> {
+        let col = self.columns.iter().find(|c| c.name() == column)
+            .ok_or_else(|| TableError::UnknownColumn(column.to_string()))?;
+        let key = value.normalized();
+        match self.indexes.iter().find(|ix| ix.column == column) {
+            Some(index) => {
+                let Some(slots) = index.rows.get(&key) else { return Ok(Vec::new()) };
+                // Slots of deleted rows are in no position
+                let mut found: Vec<usize> = slots.iter().filter_map(|&p| self.logical_order.position(p)).collect();
+                found.sort_unstable();
+                Ok(found)
+            }
+            None => Ok(self.logical_order.in_order().iter().enumerate()
+                .filter(|&(_, &p)| (if p < col.len() { col.get(p) } else { col.default_value() }).normalized() == key)
+                .map(|(i, _)| i)
+                .collect()),
+        }
+    }
+
+    /// Move physical slot `phys` from the key `old` to the current value of the column at
+    /// position `c`, in the index on that column if there is one.
+    pub(crate) fn index_slot_changed(&mut self, phys: usize, c: usize, old: &Value) {
+        let col = &self.columns[c];
+        let Some(index) = self.indexes.iter_mut().find(|ix| ix.column == col.name()) else { return };
+        let new = if phys < col.len() { col.get(phys) } else { col.default_value() };
+        index.remove(&old.normalized(), phys);
+        index.add(new.normalized(), phys);
+    }
+
+    /// Rebuild every index, after cells were written in bulk.
+    pub(crate) fn reindex(&mut self) {
+        let columns: Vec<String> = self.indexes.iter().map(|ix| ix.column.clone()).collect();
+        self.indexes.clear();
+        for column in columns {
+            // Indexes on columns that have since been removed are dropped
+            let _ = self.create_index(&column);
+        }
+    }
+}
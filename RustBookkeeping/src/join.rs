@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use crate::error::{TableError, TableResult};
+use crate::OrderedTable;
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    /// Only rows with a match on both sides
+    Inner,
+    /// Every left row; right columns are left empty (default values) when nothing matches
+    Left,
+}
+
+impl OrderedTable {
+    /// Combine rows of `self` and `other` whose `on_left` / `on_right` key values are equal
+    /// (compared with `Value::normalized_eq`, so an `Int` key matches a `Long` key).
+    /// The result has all columns of `self` followed by those of `other` except its key column;
+    /// right-hand names that clash with a name before them get a `_right` suffix, and a number
+    /// after that if it clashes too (`_right2`, `_right3`...).
+    /// Left rows keep their order; several matches produce one row each, in right-hand order.
+    pub fn join(&self, other: &OrderedTable, on_left: &str, on_right: &str, kind: JoinKind) -> TableResult<OrderedTable> {
+        let left_key = self.column(on_left).ok_or_else(|| TableError::UnknownColumn(on_left.to_string()))?;
+        let right_key_idx = other.column_index(on_right).ok_or_else(|| TableError::UnknownColumn(on_right.to_string()))?;
+        let right_key = other.columns[right_key_idx].as_ref();
+
+        let mut matches: HashMap<Value, Vec<usize>> = HashMap::new();
+        for r in 0..right_key.len() {
+            matches.entry(right_key.get(r).normalized()).or_default().push(r);
+        }
+
+        let mut out = self.empty_like();
+        let right_cols: Vec<usize> = (0..other.columns.len()).filter(|&i| i != right_key_idx).collect();
+        for &i in &right_cols {
+            let mut col = other.columns[i].empty_like();
+            if out.column_index(col.name()).is_some() {
+                let base = format!("{}_right", col.name());
+                let mut renamed = base.clone();
+                let mut n = 2;
+                while out.column_index(&renamed).is_some() {
+                    renamed = format!("{}{}", base, n);
+                    n += 1;
+                }
+                col.set_name(&renamed);
+            }
+            out.columns.push(col);
+        }
+
+        let left_width = self.columns.len();
+        for l in 0..self.nrows() {
+            let found = if l < left_key.len() { matches.get(&left_key.get(l).normalized()) } else { None };
+            let right_rows: Vec<Option<usize>> = match (found, kind) {
+                (Some(rows), _) => rows.iter().map(|&r| Some(r)).collect(),
+                (None, JoinKind::Left) => vec![None],
+                (None, JoinKind::Inner) => continue,
+            };
+            for right_row in right_rows {
+                for (src, dst) in self.columns.iter().zip(out.columns.iter_mut()) {
+                    if l < src.len() { dst.push(src.get(l)) } else { dst.push_empty() }
+                }
+                for (&i, dst) in right_cols.iter().zip(out.columns[left_width..].iter_mut()) {
+                    let src = &other.columns[i];
+                    match right_row {
+                        Some(r) if r < src.len() => dst.push(src.get(r)),
+                        _ => dst.push_empty(),
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Table, TableColumn, TableTrait};
+
+    fn table(columns: &[&str], row: Vec<Value>) -> OrderedTable {
+        let mut table = OrderedTable::new();
+        for name in columns { table.add_column(TableColumn::<i32>::new(name)); }
+        table.append_row(row);
+        table
+    }
+
+    #[test]
+    fn join_unknown_key_is_an_error() {
+        let left = table(&["Id"], vec![Value::Int(1)]);
+        let right = table(&["Key"], vec![Value::Int(1)]);
+        assert_eq!(left.join(&right, "Key", "Key", JoinKind::Inner).unwrap_err(), TableError::UnknownColumn("Key".to_string()));
+        assert_eq!(left.join(&right, "Id", "Id", JoinKind::Inner).unwrap_err(), TableError::UnknownColumn("Id".to_string()));
+    }
+
+    #[test]
+    fn join_renames_clashing_columns_uniquely() {
+        let left = table(&["Id", "x", "x_right"], vec![Value::Int(1), Value::Int(2), Value::Int(3)]);
+        let right = table(&["Id", "x", "x_right"], vec![Value::Int(1), Value::Int(4), Value::Int(5)]);
+        let joined = left.join(&right, "Id", "Id", JoinKind::Inner).unwrap();
+        let names: Vec<&str> = joined.schema().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["Id", "x", "x_right", "x_right2", "x_right_right"]);
+        assert_eq!(joined.get_cell(0, "x_right2"), Some(Value::Int(4)));
+        assert_eq!(joined.get_cell(0, "x_right_right"), Some(Value::Int(5)));
+    }
+}
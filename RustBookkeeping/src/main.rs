@@ -1,223 +1,485 @@
+use std::any::Any;
+use std::collections::HashMap;
 use std::fmt::Debug;
-use std::collections::{HashSet};
+use std::hash::Hash;
+use std::io::{self, Write};
+use std::mem;
+
+mod concat;
+mod convert;
+mod csv_io;
+mod diff;
+mod duplicate;
+mod distinct;
+mod error;
+mod events;
+mod expr;
+mod format;
+mod history;
+mod index;
+mod join;
+mod pivot;
+mod query;
+mod render;
+mod reorder;
+mod schema;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod shared;
+mod slots;
+mod sort;
+mod stats;
+mod table_row;
+mod value;
+use expr::{col, lit};
+use error::{TableError, TableResult};
+use format::FormatOptions;
+use events::{Observers, TableEvent};
+use history::{History, TargetMementoTrait};
+use index::HashIndex;
+use render::{write_grid, RenderOptions, TableStyle};
+use join::JoinKind;
+use query::RowView;
+use pivot::Agg;
+use shared::SharedTable;
+use slots::{FreeSlots, SlotPolicy};
+use sort::SortOrder;
+use table_row::TableRow;
+pub use table_row_derive::TableRow;
+use value::{Currency, Duration, Money, ParseValueError, TimeOfDay, Value};
 
 // ----------------------------- AVL Node & TreeArray -----------------------------
-#[derive(Debug)]
+/// Nodes live in the tree's arena and refer to each other by index, so that a node can
+/// be walked up from as well as down to.
+#[derive(Debug, Clone)]
 struct Node<T> {
     value: T,
     size: usize,      // subtree size
     height: usize,    // height of subtree
-    left: Option<Box<Node<T>>>,
-    right: Option<Box<Node<T>>> ,
+    left: Option<usize>,
+    right: Option<usize>,
+    parent: Option<usize>,
 }
 
 impl<T> Node<T> {
     fn new(value: T) -> Self {
-        Self { value, size: 1, height: 1, left: None, right: None }
-    }
-
-    fn update(&mut self) {
-        let lh = self.left.as_ref().map_or(0, |l| l.height);
-        let rh = self.right.as_ref().map_or(0, |r| r.height);
-        self.height = 1 + lh.max(rh);
-
-        let ls = self.left.as_ref().map_or(0, |l| l.size);
-        let rs = self.right.as_ref().map_or(0, |r| r.size);
-        self.size = 1 + ls + rs;
-    }
-
-    fn balance_factor(&self) -> isize {
-        let lh = self.left.as_ref().map_or(0, |l| l.height as isize);
-        let rh = self.right.as_ref().map_or(0, |r| r.height as isize);
-        lh - rh
+        Self { value, size: 1, height: 1, left: None, right: None, parent: None }
     }
 }
 
-#[derive(Debug)]
+/// Implicit AVL tree: an array with O(log n) insert and delete at any index. Its values
+/// are distinct (physical slots), which lets `position` find where a value is.
+#[derive(Debug, Clone)]
 struct TreeArray<T> {
-    root: Option<Box<Node<T>>>,
+    nodes: Vec<Node<T>>,
+    free: Vec<usize>,          // arena entries of deleted nodes, reused first
+    root: Option<usize>,
+    nodes_by_value: HashMap<T, usize>,
 }
 
-impl<T: Copy + Debug> TreeArray<T> {
-    fn new() -> Self { Self { root: None } }
+impl<T: Copy + Debug + Eq + Hash> TreeArray<T> {
+    fn new() -> Self { Self { nodes: Vec::new(), free: Vec::new(), root: None, nodes_by_value: HashMap::new() } }
 
-    fn len(&self) -> usize { self.root.as_ref().map_or(0, |n| n.size) }
+    /// Perfectly balanced tree holding `values` in order
+    fn from_slice(values: &[T]) -> Self {
+        let mut tree = Self::new();
+        tree.root = tree.build_balanced(values);
+        tree
+    }
+
+    fn len(&self) -> usize { self.size(self.root) }
+
+    /// Bytes held by the arena, its free list and the value lookup
+    fn heap_bytes(&self) -> usize {
+        self.nodes.capacity() * mem::size_of::<Node<T>>()
+            + self.free.capacity() * mem::size_of::<usize>()
+            + self.nodes_by_value.capacity() * mem::size_of::<(T, usize)>()
+    }
 
     // Public interface
     fn get(&self, idx: usize) -> Option<T> { self.get_ref(idx).cloned() }
-    fn get_ref(&self, idx: usize) -> Option<&T> { Self::get_node_ref(&self.root, idx)}
+    fn get_ref(&self, idx: usize) -> Option<&T> { self.node_at(idx).map(|n| &self.nodes[n].value) }
     fn append(&mut self, value: T) { self.insert(self.len(), value); }
-    fn pop(&mut self) -> Option<T> {
-        let idx = self.len().checked_sub(1)?;
-        let val = self.get(idx)?;
-        self.delete(idx);
-        Some(val)
+    fn insert(&mut self, idx: usize, value: T) {
+        let node = self.alloc(value);
+        let root = self.insert_node(self.root, idx, node);
+        self.set_root(Some(root));
+    }
+    fn delete(&mut self, idx: usize) {
+        let root = self.delete_node(self.root, idx);
+        self.set_root(root);
+    }
+
+    /// Index of `value`, walking up from its node: O(log n)
+    fn position(&self, value: T) -> Option<usize> {
+        let mut node = *self.nodes_by_value.get(&value)?;
+        let mut pos = self.size(self.nodes[node].left);
+        while let Some(parent) = self.nodes[node].parent {
+            if self.nodes[parent].right == Some(node) {
+                pos += self.size(self.nodes[parent].left) + 1;
+            }
+            node = parent;
+        }
+        Some(pos)
+    }
+
+    /// Append many values at once, rebuilding the tree perfectly balanced in a single pass
+    /// instead of rebalancing after every insert.
+    fn extend_from_slice(&mut self, values: &[T]) {
+        let mut all = self.in_order();
+        all.extend_from_slice(values);
+        *self = Self::from_slice(&all);
+    }
+
+    fn build_balanced(&mut self, values: &[T]) -> Option<usize> {
+        if values.is_empty() { return None; }
+        let mid = values.len() / 2;
+        let node = self.alloc(values[mid]);
+        let left = self.build_balanced(&values[..mid]);
+        let right = self.build_balanced(&values[mid + 1..]);
+        self.set_left(node, left);
+        self.set_right(node, right);
+        self.update(node);
+        Some(node)
     }
-    fn insert(&mut self, idx: usize, value: T) { self.root = Self::insert_node(self.root.take(), idx, value); }
-    fn delete(&mut self, idx: usize) { self.root = Self::delete_node(self.root.take(), idx); }
 
     /// Set a value at logical index idx (overwrite). Returns true if succeeded.
     fn set(&mut self, idx: usize, value: T) -> bool {
-        Self::set_node_mut(&mut self.root, idx, value)
+        let Some(node) = self.node_at(idx) else { return false };
+        let old = mem::replace(&mut self.nodes[node].value, value);
+        // While two values are swapped, the second `set` has already moved `old` away
+        if self.nodes_by_value.get(&old) == Some(&node) { self.nodes_by_value.remove(&old); }
+        self.nodes_by_value.insert(value, node);
+        true
     }
 
-    // ------------------ AVL helpers ------------------
-    fn get_node_ref(node: &Option<Box<Node<T>>>, idx: usize) -> Option<&T> {
-        let node = node.as_ref()?;
-        let left_size = node.left.as_ref().map_or(0, |l| l.size);
-        if idx < left_size { Self::get_node_ref(&node.left, idx) }
-        else if idx == left_size { Some(&node.value) }
-        else { Self::get_node_ref(&node.right, idx - left_size - 1) }
-    }
-
-    fn set_node_mut(node: &mut Option<Box<Node<T>>>, idx: usize, value: T) -> bool {
-        let nd = match node {
-            Some(n) => n,
-            None => return false,
+    // ------------------ Arena helpers ------------------
+    fn alloc(&mut self, value: T) -> usize {
+        let node = match self.free.pop() {
+            Some(n) => { self.nodes[n] = Node::new(value); n }
+            None => { self.nodes.push(Node::new(value)); self.nodes.len() - 1 }
         };
-        let left_size = nd.left.as_ref().map_or(0, |l| l.size);
-        if idx < left_size {
-            Self::set_node_mut(&mut nd.left, idx, value)
-        } else if idx == left_size {
-            nd.value = value;
-            true
-        } else {
-            Self::set_node_mut(&mut nd.right, idx - left_size - 1, value)
+        self.nodes_by_value.insert(value, node);
+        node
+    }
+
+    fn release(&mut self, node: usize) {
+        let value = self.nodes[node].value;
+        if self.nodes_by_value.get(&value) == Some(&node) { self.nodes_by_value.remove(&value); }
+        self.free.push(node);
+    }
+
+    fn size(&self, node: Option<usize>) -> usize { node.map_or(0, |n| self.nodes[n].size) }
+    fn height(&self, node: Option<usize>) -> usize { node.map_or(0, |n| self.nodes[n].height) }
+
+    fn set_root(&mut self, root: Option<usize>) {
+        if let Some(r) = root { self.nodes[r].parent = None; }
+        self.root = root;
+    }
+
+    fn set_left(&mut self, node: usize, child: Option<usize>) {
+        self.nodes[node].left = child;
+        if let Some(c) = child { self.nodes[c].parent = Some(node); }
+    }
+
+    fn set_right(&mut self, node: usize, child: Option<usize>) {
+        self.nodes[node].right = child;
+        if let Some(c) = child { self.nodes[c].parent = Some(node); }
+    }
+
+    fn update(&mut self, node: usize) {
+        let (left, right) = (self.nodes[node].left, self.nodes[node].right);
+        self.nodes[node].height = 1 + self.height(left).max(self.height(right));
+        self.nodes[node].size = 1 + self.size(left) + self.size(right);
+    }
+
+    fn balance_factor(&self, node: usize) -> isize {
+        self.height(self.nodes[node].left) as isize - self.height(self.nodes[node].right) as isize
+    }
+
+    // ------------------ AVL helpers ------------------
+    fn node_at(&self, mut idx: usize) -> Option<usize> {
+        let mut node = self.root?;
+        loop {
+            let left_size = self.size(self.nodes[node].left);
+            if idx < left_size { node = self.nodes[node].left?; }
+            else if idx == left_size { return Some(node); }
+            else {
+                idx -= left_size + 1;
+                node = self.nodes[node].right?;
+            }
         }
     }
 
-    fn rotate_right(mut y: Box<Node<T>>) -> Box<Node<T>> {
-        let mut x = y.left.take().unwrap();
-        y.left = x.right.take();
-        y.update();
-        x.right = Some(y);
-        x.update();
+    fn rotate_right(&mut self, y: usize) -> usize {
+        let x = self.nodes[y].left.unwrap();
+        self.set_left(y, self.nodes[x].right);
+        self.set_right(x, Some(y));
+        self.update(y);
+        self.update(x);
         x
     }
 
-    fn rotate_left(mut x: Box<Node<T>>) -> Box<Node<T>> {
-        let mut y = x.right.take().unwrap();
-        x.right = y.left.take();
-        x.update();
-        y.left = Some(x);
-        y.update();
+    fn rotate_left(&mut self, x: usize) -> usize {
+        let y = self.nodes[x].right.unwrap();
+        self.set_right(x, self.nodes[y].left);
+        self.set_left(y, Some(x));
+        self.update(x);
+        self.update(y);
         y
     }
 
-    fn balance(mut node: Box<Node<T>>) -> Box<Node<T>> {
-        node.update();
-        let bf = node.balance_factor();
+    fn balance(&mut self, node: usize) -> usize {
+        self.update(node);
+        let bf = self.balance_factor(node);
         if bf > 1 {
             // Left heavy
-            if node.left.as_ref().unwrap().balance_factor() < 0 {
-                node.left = Some(Self::rotate_left(node.left.take().unwrap()));
+            let left = self.nodes[node].left.unwrap();
+            if self.balance_factor(left) < 0 {
+                let rotated = self.rotate_left(left);
+                self.set_left(node, Some(rotated));
             }
-            return Self::rotate_right(node);
+            return self.rotate_right(node);
         } else if bf < -1 {
             // Right heavy
-            if node.right.as_ref().unwrap().balance_factor() > 0 {
-                node.right = Some(Self::rotate_right(node.right.take().unwrap()));
+            let right = self.nodes[node].right.unwrap();
+            if self.balance_factor(right) > 0 {
+                let rotated = self.rotate_right(right);
+                self.set_right(node, Some(rotated));
             }
-            return Self::rotate_left(node);
+            return self.rotate_left(node);
         }
         node
     }
 
-    fn insert_node(node: Option<Box<Node<T>>>, idx: usize, value: T) -> Option<Box<Node<T>>> {
-        let mut node = match node {
-            Some(n) => n,
-            None => return Some(Box::new(Node::new(value))),
-        };
-        let left_size = node.left.as_ref().map_or(0, |l| l.size);
+    fn insert_node(&mut self, node: Option<usize>, idx: usize, new: usize) -> usize {
+        let Some(node) = node else { return new };
+        let left_size = self.size(self.nodes[node].left);
         if idx <= left_size {
-            node.left = Self::insert_node(node.left.take(), idx, value);
+            let left = self.insert_node(self.nodes[node].left, idx, new);
+            self.set_left(node, Some(left));
         } else {
-            node.right = Self::insert_node(node.right.take(), idx - left_size - 1, value);
+            let right = self.insert_node(self.nodes[node].right, idx - left_size - 1, new);
+            self.set_right(node, Some(right));
         }
-        Some(Self::balance(node))
+        self.balance(node)
     }
 
-    fn delete_node(node: Option<Box<Node<T>>>, idx: usize) -> Option<Box<Node<T>>> {
-        let mut node = node?;
-        let left_size = node.left.as_ref().map_or(0, |l| l.size);
+    fn delete_node(&mut self, node: Option<usize>, idx: usize) -> Option<usize> {
+        let node = node?;
+        let left_size = self.size(self.nodes[node].left);
         if idx < left_size {
-            node.left = Self::delete_node(node.left.take(), idx);
+            let left = self.delete_node(self.nodes[node].left, idx);
+            self.set_left(node, left);
         } else if idx > left_size {
-            node.right = Self::delete_node(node.right.take(), idx - left_size - 1);
+            let right = self.delete_node(self.nodes[node].right, idx - left_size - 1);
+            self.set_right(node, right);
         } else {
-            // Node to remove
-            if node.left.is_none() { return node.right; }
-            if node.right.is_none() { return node.left; }
-            let (min_val, new_right) = Self::take_min(node.right.take().unwrap());
-            node.value = min_val;
-            node.right = new_right;
+            // Node to remove: its successor takes its place, keeping its own value
+            let (left, right) = (self.nodes[node].left, self.nodes[node].right);
+            self.release(node);
+            let (Some(_), Some(right)) = (left, right) else { return left.or(right) };
+            let (successor, new_right) = self.take_min(right);
+            self.set_left(successor, left);
+            self.set_right(successor, new_right);
+            return Some(self.balance(successor));
         }
-        Some(Self::balance(node))
+        Some(self.balance(node))
     }
 
-    fn take_min(mut node: Box<Node<T>>) -> (T, Option<Box<Node<T>>>) {
-        if node.left.is_none() {
-            return (node.value, node.right.take());
-        } else {
-            let (min_val, new_left) = Self::take_min(node.left.take().unwrap());
-            node.left = new_left;
-            (min_val, Some(Self::balance(node)))
+    /// Detach the leftmost node under `node`; returns it and what is left of the subtree
+    fn take_min(&mut self, node: usize) -> (usize, Option<usize>) {
+        match self.nodes[node].left {
+            None => (node, self.nodes[node].right),
+            Some(left) => {
+                let (min, new_left) = self.take_min(left);
+                self.set_left(node, new_left);
+                (min, Some(self.balance(node)))
+            }
         }
     }
 
-    fn in_order(&self) -> Vec<T> {
-        let mut result = Vec::with_capacity(self.len());
-        fn recurse<T: Clone>(node: &Option<Box<Node<T>>>, result: &mut Vec<T>) {
-            if let Some(n) = node {
-                recurse(&n.left, result);
-                result.push(n.value.clone());
-                recurse(&n.right, result);
-            }
+    /// Values at indices `start..end`, visiting only the nodes on the way to them
+    fn range(&self, start: usize, end: usize) -> Vec<T> {
+        fn recurse<T: Copy + Debug + Eq + Hash>(tree: &TreeArray<T>, node: Option<usize>, offset: usize, start: usize, end: usize, result: &mut Vec<T>) {
+            let Some(n) = node else { return };
+            let size = tree.nodes[n].size;
+            if offset >= end || offset + size <= start { return; }
+            let left_size = tree.size(tree.nodes[n].left);
+            recurse(tree, tree.nodes[n].left, offset, start, end, result);
+            let here = offset + left_size;
+            if here >= start && here < end { result.push(tree.nodes[n].value); }
+            recurse(tree, tree.nodes[n].right, here + 1, start, end, result);
         }
-        recurse(&self.root, &mut result);
+        let mut result = Vec::with_capacity(end.saturating_sub(start));
+        recurse(self, self.root, 0, start, end, &mut result);
         result
     }
 
-    // ------------------ Pretty print ------------------
-    fn pretty_print(&self) {
-        fn recurse<T: Debug>(node: &Option<Box<Node<T>>>, prefix: String, is_left: bool) {
-            if let Some(n) = node {
-                println!("{}{}- [{:?}] size:{} height:{}", prefix, if is_left { "L" } else { "R" }, n.value, n.size, n.height);
-                let new_prefix = prefix.clone() + if is_left { "|  " } else { "   " };
-                recurse(&n.left, new_prefix.clone(), true);
-                recurse(&n.right, new_prefix, false);
-            }
-        }
-        println!("TreeArray structure:");
-        recurse(&self.root, "".to_string(), false);
-    }
+    fn in_order(&self) -> Vec<T> { self.range(0, self.len()) }
+
 }
 
-// ----------------------------- Value enum & Column traits -----------------------------
-#[derive(Debug, Clone)]
-enum Value {
-    Int(i32),
-    Float(f32),
-    Str(String),
-    Bool(bool),
-    Byte(u8),
-    Double(f64),
-    Char(char),
-    UInt(u32),
-    Long(i64),
-    Date(u64),
-}
-
-trait Column: Debug {
+// ----------------------------- Column traits -----------------------------
+trait Column: Debug + Send + Sync {
     fn name(&self) -> &str;
+    fn set_name(&mut self, name: &str);
     fn len(&self) -> usize;
     fn push(&mut self, val: Value);
     fn push_empty(&mut self);
     fn update(&mut self, idx: usize, val: Value);
+    fn insert(&mut self, idx: usize, val: Value);
+    fn remove(&mut self, idx: usize) -> Value;
+    /// Drop every row from `len` on; no-op if the column is not longer than that
+    fn truncate(&mut self, len: usize);
+    /// Rearrange rows so that new row `i` is old row `order[i]`
+    fn reorder(&mut self, order: &[usize]);
+    /// New column with the same name and type but no rows
+    fn empty_like(&self) -> Box<dyn Column>;
+    /// Deep copy, rows included
+    fn clone_box(&self) -> Box<dyn Column>;
+    /// New column with copies of rows `start..end` (clamped to the column length)
+    fn slice(&self, start: usize, end: usize) -> Box<dyn Column>;
+    /// Append copies of `other`'s rows at `indices` (defaults past its end).
+    /// Returns false, appending nothing, if `other` has a different type.
+    fn extend_from(&mut self, other: &dyn Column, indices: &[usize]) -> bool;
+    fn as_any(&self) -> &dyn Any;
     fn get_value(&self, idx: usize) -> String;
+    fn get(&self, idx: usize) -> Value;
+    /// The value `push_empty` stores
+    fn default_value(&self) -> Value;
+    fn kind(&self) -> ColumnKind;
+    /// Approximate memory held by the column: its allocated cells plus any heap data they own
+    fn estimated_bytes(&self) -> usize;
+}
+
+/// The value kinds a `TableColumn` can hold, used to describe a schema without data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum ColumnKind {
+    Int,
+    Str,
+    Float,
+    Double,
+    Duration,
+    Time,
+    Money,
+    #[cfg(feature = "uuid")]
+    Uuid,
+}
+
+impl ColumnKind {
+    pub fn new_column(&self, name: &str) -> Box<dyn Column> {
+        match self {
+            ColumnKind::Int => Box::new(TableColumn::<i32>::new(name)),
+            ColumnKind::Str => Box::new(TableColumn::<String>::new(name)),
+            ColumnKind::Float => Box::new(TableColumn::<f32>::new(name)),
+            ColumnKind::Double => Box::new(TableColumn::<f64>::new(name)),
+            ColumnKind::Duration => Box::new(TableColumn::<Duration>::new(name)),
+            ColumnKind::Time => Box::new(TableColumn::<TimeOfDay>::new(name)),
+            ColumnKind::Money => Box::new(TableColumn::<Money>::new(name)),
+            #[cfg(feature = "uuid")]
+            ColumnKind::Uuid => Box::new(TableColumn::<uuid::Uuid>::new(name)),
+        }
+    }
+
+    /// Whether `val` can be stored in a column of this kind
+    pub fn accepts(&self, val: &Value) -> bool {
+        match (self, val) {
+            (ColumnKind::Int, Value::Int(_)) | (ColumnKind::Str, Value::Str(_)) | (ColumnKind::Float, Value::Float(_))
+            | (ColumnKind::Double, Value::Double(_)) | (ColumnKind::Duration, Value::Duration(_))
+            | (ColumnKind::Time, Value::Time(_)) | (ColumnKind::Money, Value::Money { .. }) => true,
+            #[cfg(feature = "uuid")]
+            (ColumnKind::Uuid, Value::Uuid(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Parse text into a value of this kind
+    pub fn parse(&self, text: &str) -> Result<Value, ParseValueError> {
+        let t = text.trim();
+        match self {
+            ColumnKind::Int => t.parse().map(Value::Int).map_err(|_| ParseValueError::new("int", text)),
+            ColumnKind::Str => Ok(Value::Str(text.to_string())),
+            ColumnKind::Float => t.parse().map(Value::Float).map_err(|_| ParseValueError::new("float", text)),
+            ColumnKind::Double => t.parse().map(Value::Double).map_err(|_| ParseValueError::new("double", text)),
+            ColumnKind::Duration => t.parse().map(Value::Duration),
+            ColumnKind::Time => t.parse().map(Value::Time),
+            ColumnKind::Money => t.parse::<Money>().map(Value::from),
+            #[cfg(feature = "uuid")]
+            ColumnKind::Uuid => Value::parse_uuid(t),
+        }
+    }
 }
 
-#[derive(Debug)]
+/// Storage type of a `TableColumn`, convertible to and from the matching `Value` variant.
+trait CellType: Clone + Debug + Default + Send + Sync {
+    const KIND: ColumnKind;
+    fn from_value(val: Value) -> Option<Self>;
+    fn to_value(&self) -> Value;
+    /// Heap memory owned by the value beyond its inline size
+    fn heap_bytes(&self) -> usize { 0 }
+}
+
+impl CellType for i32 {
+    const KIND: ColumnKind = ColumnKind::Int;
+    fn from_value(val: Value) -> Option<Self> { if let Value::Int(x) = val { Some(x) } else { None } }
+    fn to_value(&self) -> Value { Value::Int(*self) }
+}
+impl CellType for String {
+    const KIND: ColumnKind = ColumnKind::Str;
+    fn from_value(val: Value) -> Option<Self> { if let Value::Str(x) = val { Some(x) } else { None } }
+    fn to_value(&self) -> Value { Value::Str(self.clone()) }
+    fn heap_bytes(&self) -> usize { self.capacity() }
+}
+impl CellType for f32 {
+    const KIND: ColumnKind = ColumnKind::Float;
+    fn from_value(val: Value) -> Option<Self> { if let Value::Float(x) = val { Some(x) } else { None } }
+    fn to_value(&self) -> Value { Value::Float(*self) }
+}
+impl CellType for f64 {
+    const KIND: ColumnKind = ColumnKind::Double;
+    fn from_value(val: Value) -> Option<Self> { if let Value::Double(x) = val { Some(x) } else { None } }
+    fn to_value(&self) -> Value { Value::Double(*self) }
+}
+impl CellType for Duration {
+    const KIND: ColumnKind = ColumnKind::Duration;
+    fn from_value(val: Value) -> Option<Self> { if let Value::Duration(x) = val { Some(x) } else { None } }
+    fn to_value(&self) -> Value { Value::Duration(*self) }
+}
+impl CellType for TimeOfDay {
+    const KIND: ColumnKind = ColumnKind::Time;
+    fn from_value(val: Value) -> Option<Self> { if let Value::Time(x) = val { Some(x) } else { None } }
+    fn to_value(&self) -> Value { Value::Time(*self) }
+}
+impl CellType for Money {
+    const KIND: ColumnKind = ColumnKind::Money;
+    fn from_value(val: Value) -> Option<Self> { if let Value::Money { amount, currency } = val { Some(Money::new(amount, currency)) } else { None } }
+    fn to_value(&self) -> Value { Value::from(*self) }
+}
+#[cfg(feature = "uuid")]
+impl CellType for uuid::Uuid {
+    const KIND: ColumnKind = ColumnKind::Uuid;
+    fn from_value(val: Value) -> Option<Self> { if let Value::Uuid(x) = val { Some(x) } else { None } }
+    fn to_value(&self) -> Value { Value::Uuid(*self) }
+}
+
+impl Clone for Box<dyn Column> {
+    fn clone(&self) -> Self { self.clone_box() }
+}
+
+/// Checks that `row` has one value per column, each of the column's kind.
+fn check_row(columns: &[Box<dyn Column>], row: &[Value]) -> TableResult<()> {
+    if row.len() != columns.len() {
+        return Err(TableError::SchemaMismatch(format!("row has {} values, table has {} columns", row.len(), columns.len())));
+    }
+    match columns.iter().zip(row).find(|(col, val)| !col.kind().accepts(val)) {
+        Some((col, val)) => Err(TableError::TypeMismatch { column: col.name().to_string(), expected: col.kind(), value: val.clone() }),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone)]
 struct TableColumn<T> {
     name: String,
     rows: Vec<T>,
@@ -232,106 +494,280 @@ impl<T> TableColumn<T> {
     }
 }
 
-impl Column for TableColumn<i32> {
-    fn name(&self) -> &str { &self.name }
-    fn len(&self) -> usize { self.rows.len() }
-    fn push(&mut self, val: Value) { if let Value::Int(x) = val { self.rows.push(x) } else { panic!("Type mismatch") } }
-    fn push_empty(&mut self) { self.rows.push(0) }
-    fn update(&mut self, idx: usize, val: Value) { if let Value::Int(x) = val { self.rows[idx] = x } else { panic!("Type mismatch") } }
-    fn get_value(&self, idx: usize) -> String { self.rows[idx].to_string() }
-}
-impl Column for TableColumn<String> {
+impl<T: CellType + 'static> Column for TableColumn<T> {
     fn name(&self) -> &str { &self.name }
+    fn set_name(&mut self, name: &str) { self.name = name.to_string() }
     fn len(&self) -> usize { self.rows.len() }
-    fn push(&mut self, val: Value) { if let Value::Str(x) = val { self.rows.push(x) } else { panic!("Type mismatch") } }
-    fn push_empty(&mut self) { self.rows.push(String::new()) }
-    fn update(&mut self, idx: usize, val: Value) { if let Value::Str(x) = val { self.rows[idx] = x } else { panic!("Type mismatch") } }
-    fn get_value(&self, idx: usize) -> String { self.rows[idx].clone() }
-}
-impl Column for TableColumn<f32> {
-    fn name(&self) -> &str { &self.name }
-    fn len(&self) -> usize { self.rows.len() }
-    fn push(&mut self, val: Value) { if let Value::Float(x) = val { self.rows.push(x) } else { panic!("Type mismatch") } }
-    fn push_empty(&mut self) { self.rows.push(0.0) }
-    fn update(&mut self, idx: usize, val: Value) { if let Value::Float(x) = val { self.rows[idx] = x } else { panic!("Type mismatch") } }
-    fn get_value(&self, idx: usize) -> String { format!("{:.2}", self.rows[idx]) }
+    fn push(&mut self, val: Value) { self.rows.push(T::from_value(val).expect("Type mismatch")) }
+    fn push_empty(&mut self) { self.rows.push(T::default()) }
+    fn update(&mut self, idx: usize, val: Value) { self.rows[idx] = T::from_value(val).expect("Type mismatch") }
+    fn insert(&mut self, idx: usize, val: Value) { self.rows.insert(idx, T::from_value(val).expect("Type mismatch")) }
+    fn remove(&mut self, idx: usize) -> Value { self.rows.remove(idx).to_value() }
+    fn truncate(&mut self, len: usize) { self.rows.truncate(len) }
+    fn reorder(&mut self, order: &[usize]) { self.rows = order.iter().map(|&i| self.rows[i].clone()).collect() }
+    fn empty_like(&self) -> Box<dyn Column> { Box::new(TableColumn::<T>::new(&self.name)) }
+    fn clone_box(&self) -> Box<dyn Column> { Box::new(self.clone()) }
+    fn slice(&self, start: usize, end: usize) -> Box<dyn Column> {
+        let end = end.min(self.rows.len());
+        let start = start.min(end);
+        Box::new(TableColumn { name: self.name.clone(), rows: self.rows[start..end].to_vec() })
+    }
+    fn extend_from(&mut self, other: &dyn Column, indices: &[usize]) -> bool {
+        let Some(other) = other.as_any().downcast_ref::<TableColumn<T>>() else { return false };
+        self.rows.extend(indices.iter().map(|&i| other.rows.get(i).cloned().unwrap_or_default()));
+        true
+    }
+    fn as_any(&self) -> &dyn Any { self }
+    fn get_value(&self, idx: usize) -> String { self.rows[idx].to_value().to_string() }
+    fn get(&self, idx: usize) -> Value { self.rows[idx].to_value() }
+    fn default_value(&self) -> Value { T::default().to_value() }
+    fn kind(&self) -> ColumnKind { T::KIND }
+    fn estimated_bytes(&self) -> usize {
+        self.name.capacity() + self.rows.capacity() * mem::size_of::<T>() + self.rows.iter().map(|v| v.heap_bytes()).sum::<usize>()
+    }
 }
 
 // ----------------------------- Table traits & OrderedTable (unchanged) -----------------------------
-trait TableTrait: Debug {
-    fn add_column<C: Column + 'static>(&mut self, col: C);
+/// The object-safe part of a table, shared by both table kinds so report and import
+/// code can work through `&dyn Table` / `&mut dyn Table`.
+trait Table: Debug {
+    fn columns(&self) -> &[Box<dyn Column>];
+    fn row_count(&self) -> usize;
     fn append_row(&mut self, row: Vec<Value>);
     fn update_row(&mut self, idx: usize, row: Vec<Value>);
-    fn print_table(&self);
+    /// Values of the row at `idx`, in column order
+    fn get_row(&self, idx: usize) -> TableResult<Vec<Value>>;
+    /// Write the table to `w` row by row, laid out according to `opts`
+    fn render_with_to(&self, w: &mut dyn Write, opts: &RenderOptions) -> io::Result<()>;
+
+    fn render_with(&self, opts: &RenderOptions) -> String {
+        let mut out = Vec::new();
+        self.render_with_to(&mut out, opts).expect("writing to a Vec cannot fail");
+        String::from_utf8(out).expect("rendered cells are UTF-8")
+    }
+
+    /// `render_with` in the default layout
+    fn render(&self, opts: &FormatOptions) -> String { self.render_with(&RenderOptions::from(opts.clone())) }
 }
 
-#[derive(Debug)]
+trait TableTrait: Table {
+    fn add_column<C: Column + 'static>(&mut self, col: C);
+
+    /// Stream the table to `w` as `print_table` shows it, with values in their `Display` form
+    fn render_to<W: Write>(&self, w: W) -> io::Result<()>;
+
+    fn print_table(&self) { self.render_to(io::stdout().lock()).expect("failed printing to stdout") }
+}
+
+#[derive(Debug, Clone)]
 struct OrderedTable {
     columns: Vec<Box<dyn Column>>,
+    indexes: Vec<HashIndex>,
+    observers: Observers,
 }
 
 impl OrderedTable {
-    pub fn new() -> Self { OrderedTable { columns: Vec::new() } }
+    pub fn new() -> Self { OrderedTable { columns: Vec::new(), indexes: Vec::new(), observers: Observers::default() } }
+
+    /// Table with the same columns but no rows (and no indexes)
+    pub fn empty_like(&self) -> OrderedTable {
+        OrderedTable { columns: self.columns.iter().map(|c| c.empty_like()).collect(), indexes: Vec::new(), observers: Observers::default() }
+    }
+
+    /// Column names and kinds, in column order (the shape `from_csv` expects)
+    pub fn schema(&self) -> Vec<(&str, ColumnKind)> {
+        self.columns.iter().map(|c| (c.name(), c.kind())).collect()
+    }
+
+    /// Number of rows (the longest column, as columns may be ragged after `update_row`)
+    pub fn nrows(&self) -> usize { self.columns.iter().map(|c| c.len()).max().unwrap_or(0) }
+
+    /// Insert a row at index, shifting subsequent rows down
+    pub fn insert_row(&mut self, idx: usize, row: Vec<Value>) {
+        if let Err(e) = check_row(&self.columns, &row) { panic!("{}", e.at_row(idx)) }
+        assert!(idx <= self.nrows(), "Row index out of bounds");
+        for (val, col) in row.into_iter().zip(self.columns.iter_mut()) {
+            while idx > col.len() { col.push_empty(); }
+            col.insert(idx, val);
+        }
+        self.index_inserted(idx);
+        self.observers.emit(TableEvent::RowInserted { row: idx });
+    }
+
+    /// Delete the row at index, shifting subsequent rows up. Returns the removed values.
+    pub fn delete_row(&mut self, idx: usize) -> Vec<Value> {
+        assert!(idx < self.nrows(), "Row index out of bounds");
+        let mut removed = Vec::with_capacity(self.columns.len());
+        for col in self.columns.iter_mut() {
+            while idx >= col.len() { col.push_empty(); }
+            removed.push(col.remove(idx));
+        }
+        self.index_removed(idx, &removed);
+        self.observers.emit(TableEvent::RowDeleted { row: idx });
+        removed
+    }
+
+    /// Drop all rows, keeping the columns (and any indexes, now empty)
+    pub fn clear(&mut self) { self.truncate(0) }
+
+    /// Keep only the first `n` rows; no-op if the table has no more than that
+    pub fn truncate(&mut self, n: usize) {
+        if n >= self.nrows() { return; }
+        for col in self.columns.iter_mut() { col.truncate(n); }
+        self.reindex();
+        self.observers.emit(TableEvent::RowsChanged);
+    }
+
+    /// Position of the named column
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.name() == name)
+    }
+
+    pub fn column(&self, name: &str) -> Option<&dyn Column> {
+        self.column_index(name).map(|idx| self.columns[idx].as_ref())
+    }
+
+    /// Drop the named column and its data, returning it
+    pub fn remove_column(&mut self, name: &str) -> Option<Box<dyn Column>> {
+        let idx = self.column_index(name)?;
+        self.drop_index(name);
+        let removed = self.columns.remove(idx);
+        self.observers.emit(TableEvent::ColumnRemoved { column: name.to_string() });
+        Some(removed)
+    }
+
+    /// Read a single cell by column name. None if the column or row does not exist.
+    pub fn get_cell(&self, row: usize, column: &str) -> Option<Value> {
+        let col = self.column(column)?;
+        if row < col.len() { Some(col.get(row)) } else { None }
+    }
+
+    /// Overwrite a single cell by column name
+    pub fn set_cell(&mut self, row: usize, column: &str, val: Value) -> TableResult<()> {
+        if row >= self.nrows() { return Err(TableError::RowOutOfBounds { index: row, len: self.nrows() }); }
+        let idx = self.column_index(column).ok_or_else(|| TableError::UnknownColumn(column.to_string()))?;
+        let col = &mut self.columns[idx];
+        if !col.kind().accepts(&val) {
+            return Err(TableError::TypeMismatch { column: column.to_string(), expected: col.kind(), value: val }.at_row(row));
+        }
+        while row >= col.len() { col.push_empty(); }
+        let old = col.get(row);
+        col.update(row, val);
+        self.index_changed(row, column, &old);
+        self.observers.emit(TableEvent::CellUpdated { row, column: column.to_string() });
+        Ok(())
+    }
 }
 
-impl TableTrait for OrderedTable {
-    fn add_column<C: Column + 'static>(&mut self, col: C) { self.columns.push(Box::new(col)) }
+impl Table for OrderedTable {
+    fn columns(&self) -> &[Box<dyn Column>] { &self.columns }
+
+    fn row_count(&self) -> usize { self.nrows() }
 
     fn append_row(&mut self, row: Vec<Value>) {
-        assert_eq!(row.len(), self.columns.len(), "Row length mismatch");
+        if let Err(e) = check_row(&self.columns, &row) { panic!("{}", e.at_row(self.nrows())) }
         for (val, col) in row.into_iter().zip(self.columns.iter_mut()) {
             col.push(val);
         }
+        self.index_inserted(self.nrows() - 1);
+        self.observers.emit(TableEvent::RowInserted { row: self.nrows() - 1 });
     }
 
     fn update_row(&mut self, idx: usize, row: Vec<Value>) {
-        assert_eq!(row.len(), self.columns.len(), "Row length mismatch");
+        if let Err(e) = check_row(&self.columns, &row) { panic!("{}", e.at_row(idx)) }
+        let mut old = Vec::with_capacity(row.len());
         for (val, col) in row.into_iter().zip(self.columns.iter_mut()) {
             while idx >= col.len() { col.push_empty(); }
+            old.push((col.name().to_string(), col.get(idx)));
             col.update(idx, val);
         }
+        for (name, val) in old { self.index_changed(idx, &name, &val); }
+        self.observers.emit(TableEvent::RowUpdated { row: idx });
     }
 
-    fn print_table(&self) {
-        if self.columns.is_empty() { println!("(empty table)"); return; }
-        let nrows = self.columns.iter().map(|c| c.len()).max().unwrap_or(0);
-        let mut widths = Vec::new();
-        for col in &self.columns {
-            let mut max_width = col.name().len();
-            for r in 0..nrows { let val = col.get_value(r); if val.len() > max_width { max_width = val.len(); } }
-            widths.push(max_width);
-        }
-        for (i, (col, w)) in self.columns.iter().zip(&widths).enumerate() { if i>0 {print!(" ")}; print!("{:<width$}", col.name(), width=w); }
-        println!();
-        for (i, w) in widths.iter().enumerate() { if i>0 {print!(" ")}; print!("{}", "-".repeat(*w)); }
-        println!();
-        for r in 0..nrows {
-            for (i, (col, w)) in self.columns.iter().zip(&widths).enumerate() {
-                if i>0 { print!(" "); }
-                let val = if r < col.len() { col.get_value(r) } else { "".to_string() };
-                print!("{:<width$}", val, width=w);
-            }
-            println!();
-        }
+    fn get_row(&self, idx: usize) -> TableResult<Vec<Value>> {
+        if idx >= self.nrows() { return Err(TableError::RowOutOfBounds { index: idx, len: self.nrows() }); }
+        Ok(self.columns.iter().map(|col| if idx < col.len() { col.get(idx) } else { col.default_value() }).collect())
+    }
+
+    fn render_with_to(&self, w: &mut dyn Write, opts: &RenderOptions) -> io::Result<()> {
+        if self.columns.is_empty() { return writeln!(w, "(empty table)"); }
+        let col_opts: Vec<FormatOptions> = self.columns.iter().map(|c| opts.format.for_column(c.name())).collect();
+        let cell = |r: usize, c: usize| {
+            let col = &self.columns[c];
+            if r < col.len() { col.get(r).format_with(&col_opts[c]) } else { "".to_string() }
+        };
+        write_grid(w, &self.columns, 0..self.nrows(), cell, opts)
+    }
+}
+
+impl TableTrait for OrderedTable {
+    fn add_column<C: Column + 'static>(&mut self, col: C) {
+        let column = col.name().to_string();
+        self.columns.push(Box::new(col));
+        self.observers.emit(TableEvent::ColumnAdded { column });
+    }
+
+    fn render_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        if self.columns.is_empty() { return writeln!(w, "(empty table)"); }
+        let cell = |r: usize, c: usize| {
+            let col = &self.columns[c];
+            if r < col.len() { col.get_value(r) } else { "".to_string() }
+        };
+        write_grid(w, &self.columns, 0..self.nrows(), cell, &RenderOptions::default())
     }
 }
 
 // ----------------------------- UnorderedTable with TreeArray + recycling -----------------------------
-#[derive(Debug)]
+/// One reversible step of an `UnorderedTable` mutation, described by what applying it does.
+#[derive(Debug, Clone)]
+enum TableChange {
+    /// Overwrite (physical row, column position) with the value
+    CellEdit(usize, usize, Value),
+    /// Insert physical slot at user index
+    RowInserted(usize, usize),
+    /// Remove user index, which maps to the physical slot
+    RowDeleted(usize, usize),
+    /// Point user index at physical slot
+    LogicalSet(usize, usize),
+    /// Replace the whole logical order
+    LogicalOrder(Vec<usize>),
+    SlotFreed(usize),
+    SlotTaken(usize),
+    NextIndex(usize),
+}
+
+#[derive(Debug, Clone)]
+struct UnorderedTableMemento {
+    changes: Vec<TableChange>,
+}
+
+#[derive(Debug, Clone)]
 struct UnorderedTable {
     columns: Vec<Box<dyn Column>>,
     logical_order: TreeArray<usize>, // user_index -> physical_index
     next_physical_index: usize,
-    free_physical: HashSet<usize>, // recycling of freed physical indices
+    free_physical: FreeSlots, // recycling of freed physical indices
+    history: History<UnorderedTableMemento>,
+    indexes: Vec<HashIndex>, // keyed by cell value, pointing at physical slots
+    observers: Observers,
 }
 
 impl UnorderedTable {
-    pub fn new() -> Self {
+    pub fn new() -> Self { Self::with_slot_policy(SlotPolicy::default()) }
+
+    pub fn slot_policy(&self) -> SlotPolicy { self.free_physical.policy() }
+
+    /// Empty table recycling freed physical slots according to `policy`
+    pub fn with_slot_policy(policy: SlotPolicy) -> Self {
         Self {
             columns: Vec::new(),
             logical_order: TreeArray::new(),
             next_physical_index: 0,
-            free_physical: HashSet::new(),
+            free_physical: FreeSlots::new(policy),
+            history: History::new(),
+            indexes: Vec::new(),
+            observers: Observers::default(),
         }
     }
 
@@ -342,33 +778,109 @@ impl UnorderedTable {
             self.logical_order.delete(user_idx);
             // add to free set for reuse
             self.free_physical.insert(phys);
+            self.history.record(UnorderedTableMemento {
+                changes: vec![TableChange::SlotTaken(phys), TableChange::RowInserted(user_idx, phys)],
+            });
+            self.observers.emit(TableEvent::RowDeleted { row: user_idx });
+        }
+    }
+
+    /// Delete every row for which `pred` returns true, freeing their physical slots.
+    /// Returns the number of rows deleted.
+    pub fn delete_rows_where<F: FnMut(RowView) -> bool>(&mut self, mut pred: F) -> usize {
+        let before = self.logical_order.in_order();
+        let mut kept = Vec::with_capacity(before.len());
+        let mut changes = Vec::new();
+        for &phys in &before {
+            if pred(RowView::new(&self.columns, phys)) {
+                self.free_physical.insert(phys);
+                changes.push(TableChange::SlotTaken(phys));
+            } else {
+                kept.push(phys);
+            }
+        }
+        let deleted = changes.len();
+        if deleted > 0 {
+            self.logical_order = TreeArray::from_slice(&kept);
+            changes.push(TableChange::LogicalOrder(before));
+            self.history.record(UnorderedTableMemento { changes });
+            self.observers.emit(TableEvent::RowsChanged);
         }
+        deleted
     }
 
-    /// Insert a row at user index (shifts subsequent)
+    /// Insert a row at user index (shifts subsequent). Panics if the row does not fit.
     pub fn insert_row(&mut self, user_idx: usize, row: Vec<Value>) {
-        assert_eq!(row.len(), self.columns.len(), "Row length mismatch");
+        if let Err(e) = self.try_insert_row(user_idx, row) { panic!("{}", e) }
+    }
+
+    /// Insert a row at user index (shifts subsequent). The whole row is checked against
+    /// the columns before anything is touched, so a failed insert leaves the table as it was.
+    pub fn try_insert_row(&mut self, user_idx: usize, row: Vec<Value>) -> TableResult<()> {
+        check_row(&self.columns, &row).map_err(|e| e.at_row(user_idx))?;
+        if user_idx > self.nrows() { return Err(TableError::RowOutOfBounds { index: user_idx, len: self.nrows() }); }
+        let mut changes = Vec::with_capacity(row.len() + 2);
         // choose physical index: recycle or append
-        let phys_idx = if let Some(&p) = self.free_physical.iter().next() {
-            // take an arbitrary element from the set
-            self.free_physical.take(&p);
+        let phys_idx = if let Some(p) = self.free_physical.take() {
+            changes.push(TableChange::SlotFreed(p));
             p
         } else {
             let p = self.next_physical_index;
+            changes.push(TableChange::NextIndex(p));
             self.next_physical_index += 1;
             p
         };
 
         // ensure each column has space for phys_idx and set the value at phys_idx
-        for (val, col) in row.into_iter().zip(self.columns.iter_mut()) {
+        for (c, (val, col)) in row.into_iter().zip(self.columns.iter_mut()).enumerate() {
             while phys_idx >= col.len() {
                 col.push_empty();
             }
+            changes.push(TableChange::CellEdit(phys_idx, c, col.get(phys_idx)));
             col.update(phys_idx, val);
         }
 
+        for change in &changes {
+            if let TableChange::CellEdit(phys, c, old) = change { self.index_slot_changed(*phys, *c, old); }
+        }
+
         // insert into logical array at user_idx
         self.logical_order.insert(user_idx, phys_idx);
+        changes.push(TableChange::RowDeleted(user_idx, phys_idx));
+        changes.reverse();
+        self.history.record(UnorderedTableMemento { changes });
+        self.observers.emit(TableEvent::RowInserted { row: user_idx });
+        Ok(())
+    }
+
+    /// Append many rows at once: they get a contiguous block of fresh physical slots
+    /// (free slots are not recycled), values are pushed column by column and the logical
+    /// order is rebuilt once. Returns the number of rows appended.
+    pub fn extend_rows<I: IntoIterator<Item = Vec<Value>>>(&mut self, rows: I) -> usize {
+        let ncols = self.columns.len();
+        let mut by_column: Vec<Vec<Value>> = vec![Vec::new(); ncols];
+        for (i, row) in rows.into_iter().enumerate() {
+            if let Err(e) = check_row(&self.columns, &row) { panic!("{}", e.at_row(self.nrows() + i)) }
+            for (vals, val) in by_column.iter_mut().zip(row) { vals.push(val); }
+        }
+        let count = by_column.first().map_or(0, |vals| vals.len());
+        if count == 0 { return 0; }
+        let start = self.next_physical_index;
+        for (col, vals) in self.columns.iter_mut().zip(by_column) {
+            // Slots past `next_physical_index` may hold rows whose insert was undone
+            col.truncate(start);
+            while col.len() < start { col.push_empty(); }
+            for val in vals { col.push(val); }
+        }
+        self.history.record(UnorderedTableMemento {
+            changes: vec![TableChange::LogicalOrder(self.logical_order.in_order()), TableChange::NextIndex(start)],
+        });
+        self.next_physical_index += count;
+        let slots: Vec<usize> = (start..start + count).collect();
+        self.logical_order.extend_from_slice(&slots);
+        self.reindex();
+        for row in self.nrows() - count..self.nrows() { self.observers.emit(TableEvent::RowInserted { row }); }
+        count
     }
 
     /// Rearrange user indices: swap two rows (swap physical indices)
@@ -377,15 +889,79 @@ impl UnorderedTable {
         if let (Some(p1), Some(p2)) = (self.logical_order.get(idx1), self.logical_order.get(idx2)) {
             self.logical_order.set(idx1, p2);
             self.logical_order.set(idx2, p1);
+            self.history.record(UnorderedTableMemento {
+                changes: vec![TableChange::LogicalSet(idx1, p1), TableChange::LogicalSet(idx2, p2)],
+            });
+            self.observers.emit(TableEvent::RowUpdated { row: idx1 });
+            self.observers.emit(TableEvent::RowUpdated { row: idx2 });
         }
     }
 
+    /// Revert the most recent recorded change. Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let mut history = mem::take(&mut self.history);
+        let done = history.undo(self);
+        self.history = history;
+        if done { self.observers.emit(TableEvent::RowsChanged); }
+        done
+    }
+
+    /// Reapply the most recently undone change. Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let mut history = mem::take(&mut self.history);
+        let done = history.redo(self);
+        self.history = history;
+        if done { self.observers.emit(TableEvent::RowsChanged); }
+        done
+    }
+
+    pub fn undoable(&self) -> bool { self.history.undoable() }
+
+    pub fn redoable(&self) -> bool { self.history.redoable() }
+
     /// Get number of logical rows
     pub fn nrows(&self) -> usize { self.logical_order.len() }
+
+    /// Physical slot holding the row at user index
+    fn physical_index(&self, user_idx: usize) -> TableResult<usize> {
+        self.logical_order.get(user_idx).ok_or(TableError::RowOutOfBounds { index: user_idx, len: self.nrows() })
+    }
+
+    /// Values of the row at user index, in column order
+    pub fn get_row(&self, user_idx: usize) -> TableResult<Vec<Value>> {
+        let phys = self.physical_index(user_idx)?;
+        Ok(self.columns.iter().map(|col| if phys < col.len() { col.get(phys) } else { col.default_value() }).collect())
+    }
+
+    /// Value of one cell, by user index and column name
+    pub fn get_cell(&self, user_idx: usize, column: &str) -> TableResult<Value> {
+        let phys = self.physical_index(user_idx)?;
+        let col = self.columns.iter().find(|c| c.name() == column).ok_or_else(|| TableError::UnknownColumn(column.to_string()))?;
+        Ok(if phys < col.len() { col.get(phys) } else { col.default_value() })
+    }
+
+    /// Overwrite one cell, by user index and column name
+    pub fn set_cell(&mut self, user_idx: usize, column: &str, val: Value) -> TableResult<()> {
+        let phys = self.physical_index(user_idx)?;
+        let c = self.columns.iter().position(|c| c.name() == column).ok_or_else(|| TableError::UnknownColumn(column.to_string()))?;
+        let col = &mut self.columns[c];
+        if !col.kind().accepts(&val) {
+            return Err(TableError::TypeMismatch { column: column.to_string(), expected: col.kind(), value: val }.at_row(user_idx));
+        }
+        while phys >= col.len() { col.push_empty(); }
+        let old = col.get(phys);
+        col.update(phys, val);
+        self.index_slot_changed(phys, c, &old);
+        self.history.record(UnorderedTableMemento { changes: vec![TableChange::CellEdit(phys, c, old)] });
+        self.observers.emit(TableEvent::CellUpdated { row: user_idx, column: column.to_string() });
+        Ok(())
+    }
 }
 
-impl TableTrait for UnorderedTable {
-    fn add_column<C: Column + 'static>(&mut self, col: C) { self.columns.push(Box::new(col)) }
+impl Table for UnorderedTable {
+    fn columns(&self) -> &[Box<dyn Column>] { &self.columns }
+
+    fn row_count(&self) -> usize { self.nrows() }
 
     fn append_row(&mut self, row: Vec<Value>) {
         let idx = self.logical_order.len();
@@ -393,45 +969,117 @@ impl TableTrait for UnorderedTable {
     }
 
     fn update_row(&mut self, idx: usize, row: Vec<Value>) {
-        assert_eq!(row.len(), self.columns.len(), "Row length mismatch");
+        if let Err(e) = check_row(&self.columns, &row) { panic!("{}", e.at_row(idx)) }
         if let Some(phys_idx) = self.logical_order.get(idx) {
-            for (val, col) in row.into_iter().zip(self.columns.iter_mut()) {
+            let mut changes = Vec::with_capacity(row.len());
+            for (c, (val, col)) in row.into_iter().zip(self.columns.iter_mut()).enumerate() {
                 while phys_idx >= col.len() { col.push_empty(); }
+                changes.push(TableChange::CellEdit(phys_idx, c, col.get(phys_idx)));
                 col.update(phys_idx, val);
             }
+            for change in &changes {
+                if let TableChange::CellEdit(phys, c, old) = change { self.index_slot_changed(*phys, *c, old); }
+            }
+            self.history.record(UnorderedTableMemento { changes });
+            self.observers.emit(TableEvent::RowUpdated { row: idx });
         }
     }
 
-    fn print_table(&self) {
-        if self.columns.is_empty() || self.logical_order.len() == 0 { println!("(empty table)"); return; }
-        let nrows = self.logical_order.len();
-        let mut widths = Vec::new();
-        for col in &self.columns {
-            let mut max_width = col.name().len();
-            for user_idx in 0..nrows {
-                if let Some(phys_idx) = self.logical_order.get(user_idx) {
-                    let val = col.get_value(phys_idx);
-                    if val.len() > max_width { max_width = val.len(); }
+    fn get_row(&self, idx: usize) -> TableResult<Vec<Value>> { UnorderedTable::get_row(self, idx) }
+
+    fn render_with_to(&self, w: &mut dyn Write, opts: &RenderOptions) -> io::Result<()> {
+        if self.columns.is_empty() || self.logical_order.len() == 0 { return writeln!(w, "(empty table)"); }
+        let col_opts: Vec<FormatOptions> = self.columns.iter().map(|c| opts.format.for_column(c.name())).collect();
+        let physical = self.logical_order.in_order();
+        let cell = |r: usize, c: usize| self.columns[c].get(physical[r]).format_with(&col_opts[c]);
+        write_grid(w, &self.columns, 0..physical.len(), cell, opts)
+    }
+}
+
+impl TableTrait for UnorderedTable {
+    /// Existing rows get the column's default value
+    fn add_column<C: Column + 'static>(&mut self, col: C) {
+        let mut col: Box<dyn Column> = Box::new(col);
+        while col.len() < self.next_physical_index { col.push_empty(); }
+        let column = col.name().to_string();
+        self.columns.push(col);
+        self.observers.emit(TableEvent::ColumnAdded { column });
+    }
+
+    fn render_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        if self.columns.is_empty() || self.logical_order.len() == 0 { return writeln!(w, "(empty table)"); }
+        let physical = self.logical_order.in_order();
+        let cell = |r: usize, c: usize| self.columns[c].get_value(physical[r]);
+        write_grid(w, &self.columns, 0..physical.len(), cell, &RenderOptions::default())
+    }
+}
+
+impl TargetMementoTrait<UnorderedTableMemento> for UnorderedTable {
+    fn apply_memento(&mut self, memento: &UnorderedTableMemento) -> UnorderedTableMemento {
+        let mut inverse = Vec::with_capacity(memento.changes.len());
+        for change in &memento.changes {
+            inverse.push(match change {
+                TableChange::CellEdit(phys, c, val) => {
+                    let col = &mut self.columns[*c];
+                    while *phys >= col.len() { col.push_empty(); }
+                    let previous = col.get(*phys);
+                    col.update(*phys, val.clone());
+                    self.index_slot_changed(*phys, *c, &previous);
+                    TableChange::CellEdit(*phys, *c, previous)
                 }
-            }
-            widths.push(max_width);
-        }
-        // header
-        for (i, (col, w)) in self.columns.iter().zip(&widths).enumerate() { if i>0 {print!(" ")}; print!("{:<width$}", col.name(), width=w); }
-        println!();
-        for (i, w) in widths.iter().enumerate() { if i>0 {print!(" ")}; print!("{}", "-".repeat(*w)); }
-        println!();
-        // rows
-        for user_idx in 0..nrows {
-            if let Some(phys_idx) = self.logical_order.get(user_idx) {
-                for (i, (col, w)) in self.columns.iter().zip(&widths).enumerate() {
-                    if i>0 { print!(" "); }
-                    print!("{:<width$}", col.get_value(phys_idx), width=w);
+                TableChange::RowInserted(logical, phys) => {
+                    self.logical_order.insert(*logical, *phys);
+                    TableChange::RowDeleted(*logical, *phys)
                 }
-                println!();
-            }
+                TableChange::RowDeleted(logical, phys) => {
+                    self.logical_order.delete(*logical);
+                    TableChange::RowInserted(*logical, *phys)
+                }
+                TableChange::LogicalSet(logical, phys) => {
+                    let previous = self.logical_order.get(*logical).expect("Logical index recorded in history");
+                    self.logical_order.set(*logical, *phys);
+                    TableChange::LogicalSet(*logical, previous)
+                }
+                TableChange::LogicalOrder(order) => {
+                    let previous = self.logical_order.in_order();
+                    self.logical_order = TreeArray::from_slice(order);
+                    TableChange::LogicalOrder(previous)
+                }
+                TableChange::SlotFreed(phys) => {
+                    self.free_physical.insert(*phys);
+                    TableChange::SlotTaken(*phys)
+                }
+                TableChange::SlotTaken(phys) => {
+                    self.free_physical.remove(phys);
+                    TableChange::SlotFreed(*phys)
+                }
+                TableChange::NextIndex(next) => {
+                    let previous = mem::replace(&mut self.next_physical_index, *next);
+                    TableChange::NextIndex(previous)
+                }
+            });
         }
+        inverse.reverse();
+        UnorderedTableMemento { changes: inverse }
+    }
+}
+
+#[derive(Debug, TableRow)]
+struct Transaction {
+    #[column("Account")]
+    account: i32,
+    #[column("Text")]
+    text: String,
+    #[column("Amount")]
+    amount: Money,
+}
+
+/// Copies every row of `source` onto the end of `target`, whichever kind of table either is
+fn copy_rows(source: &dyn Table, target: &mut dyn Table) -> TableResult<usize> {
+    for idx in 0..source.row_count() {
+        target.append_row(source.get_row(idx)?);
     }
+    Ok(source.row_count())
 }
 
 // ----------------------------- Demonstration in main -----------------------------
@@ -445,6 +1093,23 @@ fn main() {
     ord.append_row(vec![Value::Int(30), Value::Str("Bob".to_string()), Value::Float(60000.0)]);
     println!("OrderedTable:");
     ord.print_table();
+    println!("\nOrderedTable (Swedish format):");
+    print!("{}", ord.render(&FormatOptions::swedish()));
+    ord.insert_row(1, vec![Value::Int(41), Value::Str("Carol".to_string()), Value::Float(72000.0)]);
+    let removed = ord.delete_row(0);
+    println!("\nOrderedTable after insert at 1 and delete of {:?}:", removed);
+    ord.print_table();
+    ord.set_cell(1, "Salary", Value::Float(61500.0)).unwrap();
+    println!("Bob's salary is now {}", ord.get_cell(1, "Salary").unwrap());
+    ord.sort_by(&[("Salary", SortOrder::Desc), ("Name", SortOrder::Asc)]).unwrap();
+    println!("Highest paid first:");
+    ord.print_table();
+    let well_paid = ord.filter_by("Salary", |v| v.compare(&Value::Float(65000.0)).is_gt());
+    println!("Earning over 65000:");
+    well_paid.select(&["Name", "Salary"]).unwrap().print_table();
+    if let Some(age) = ord.remove_column("Age") {
+        println!("Dropped column '{}' with {} values, {} columns left", age.name(), age.len(), ord.columns.len());
+    }
 
     // Unordered example using TreeArray + recycling
     let mut unord = UnorderedTable::new();
@@ -486,9 +1151,326 @@ fn main() {
     unord.update_row(1, vec![Value::Int(99), Value::Str("Updated".to_string()), Value::Float(12345.0)]);
     println!("\nAfter update logical row 1:");
     unord.print_table();
+    println!("Row 1: {:?}", unord.get_row(1).unwrap());
+    unord.set_cell(2, "Salary", Value::Float(61000.0)).unwrap();
+    println!("Name of row 2: {}, salary now {}", unord.get_cell(2, "Name").unwrap(), unord.get_cell(2, "Salary").unwrap());
+    if let Err(e) = unord.get_row(10) { println!("Row 10: {}", e); }
+    let names: Vec<Value> = unord.iter_rows().filter_map(|row| row.get("Name")).collect();
+    println!("Names in order: {:?}", names);
 
     // show internal mapping & recycling info
     println!("\nInternal logical->physical (in-order): {:?}", unord.logical_order.in_order());
     println!("Next physical index: {}", unord.next_physical_index);
     println!("Free physical set: {:?}", unord.free_physical);
+    let purged = unord.delete_rows_where(|row| row.get("Age").is_some_and(|age| age.compare(&Value::Int(90)).is_ge()));
+    println!("Purged {} placeholder rows, free physical set: {:?}", purged, unord.free_physical);
+    if let Err(e) = unord.try_insert_row(0, vec![Value::Str("forty".to_string()), Value::Str("Bad".to_string()), Value::Float(0.0)]) {
+        println!("Rejected insert: {} (still {} rows)", e, unord.nrows());
+    }
+    if unord.undoable() { unord.undo(); }
+    println!("Undo purge: {} rows, free physical set: {:?}", unord.nrows(), unord.free_physical);
+    if unord.redoable() { unord.redo(); }
+    println!("Redo purge: {} rows, free physical set: {:?}", unord.nrows(), unord.free_physical);
+    unord.sort_by("Name", true).unwrap();
+    println!("\nSorted by name (logical->physical {:?}):", unord.logical_order.in_order());
+    unord.print_table();
+    let loaded = unord.extend_rows((0..10_000).map(|i| vec![Value::Int(20 + i % 40), Value::Str(format!("Temp {}", i)), Value::Float(30000.0)]));
+    println!("Bulk loaded {} rows, now {} rows", loaded, unord.nrows());
+    unord.delete_rows_where(|row| row.get("Salary") == Some(Value::Float(30000.0)));
+    let bonuses = unord.iter_rows().map(|row| Value::Float(if row.get("Age") == Some(Value::Int(30)) { 500.0 } else { 0.0 })).collect::<Vec<_>>();
+    unord.add_column_with(TableColumn::<f32>::new("Bonus"), bonuses).unwrap();
+    unord.remove_column("Salary");
+    println!("\nBonus added, salary removed:");
+    unord.print_table();
+    unord.reverse();
+    println!("Reversed names: {:?}", unord.iter_rows().filter_map(|row| row.get("Name")).collect::<Vec<_>>());
+    unord.undo();
+    unord.create_index("Name").unwrap();
+    println!("Rows named Elina: {:?}", unord.find("Name", &Value::Str("Elina".to_string())).unwrap());
+    print!("{}", unord.render_range(1.., &RenderOptions::default().row_numbers(true)));
+    println!("Storage: {}", unord.storage_stats());
+    let mut stable_ids = UnorderedTable::with_slot_policy(SlotPolicy::NoReuse);
+    stable_ids.add_column(TableColumn::<String>::new("Name"));
+    for name in ["a", "b", "c"] { stable_ids.append_row(vec![Value::Str(name.to_string())]); }
+    stable_ids.delete_row(1);
+    stable_ids.append_row(vec![Value::Str("d".to_string())]);
+    println!("{:?} policy: logical->physical {:?}, free {:?}", stable_ids.slot_policy(), stable_ids.logical_order.in_order(), stable_ids.free_physical);
+    let mut what_if = unord.clone();
+    what_if.delete_row(0);
+    what_if.duplicate_row(0).unwrap();
+    println!("What-if copy: {} rows, original: {} rows", what_if.nrows(), unord.nrows());
+    let snapshot = unord.to_ordered();
+    println!("As ordered table: {} rows, back to unordered: {} rows", snapshot.nrows(), snapshot.to_unordered().nrows());
+
+    // Time tracking example with Duration / Time cells
+    let mut sheet = OrderedTable::new();
+    sheet.add_column(TableColumn::<String>::new("Name"));
+    sheet.add_column(TableColumn::<TimeOfDay>::new("Start"));
+    sheet.add_column(TableColumn::<TimeOfDay>::new("End"));
+    sheet.add_column(TableColumn::<Duration>::new("Worked"));
+    for (name, start, end) in [("Alice", "08:00", "16:30"), ("Bob", "22:00", "06:15")] {
+        let start: TimeOfDay = start.parse().unwrap();
+        let end: TimeOfDay = end.parse().unwrap();
+        sheet.append_row(vec![Value::Str(name.to_string()), Value::Time(start), Value::Time(end), Value::Duration(start.until(end))]);
+    }
+    println!("\nTimesheet:");
+    sheet.print_table();
+    let overtime: Duration = "1h45m".parse().unwrap();
+    println!("Overtime {} = {:.2} h", overtime, overtime.as_hours());
+
+    // Multi-currency ledger with Money cells
+    let mut ledger = OrderedTable::new();
+    ledger.add_column(TableColumn::<String>::new("Text"));
+    ledger.add_column(TableColumn::<Money>::new("Amount"));
+    let entries: Vec<Money> = ["1234,56 SEK", "-99.50 SEK", "250 EUR"].iter().map(|s| s.parse().unwrap()).collect();
+    for (text, amount) in ["Invoice 17", "Bank fee", "Conference"].iter().zip(&entries) {
+        ledger.append_row(vec![Value::Str(text.to_string()), Value::from(*amount)]);
+    }
+    println!("\nLedger:");
+    ledger.print_table();
+    println!("\nLedger (Swedish format):");
+    print!("{}", ledger.render(&FormatOptions::swedish()));
+    println!("\nLedger (markdown report):");
+    let report = RenderOptions::from(FormatOptions::swedish()).style(TableStyle::Markdown).align_numbers(true).row_numbers(true).max_cell_width(Some(16));
+    print!("{}", ledger.render_with(&report));
+    ledger.render_with_to(&mut io::stdout().lock(), &report.style(TableStyle::AsciiBox)).unwrap();
+    println!("SEK total: {}", Money::sum(Currency::SEK, &entries[..2]).unwrap());
+    match Money::sum(Currency::SEK, &entries) {
+        Ok(total) => println!("All total: {}", total),
+        Err(e) => println!("All total refused: {}", e),
+    }
+
+    // Exchange rates need more decimals than the default four
+    let mut rates = OrderedTable::new();
+    rates.add_column(TableColumn::<String>::new("Pair"));
+    rates.add_column(TableColumn::<f64>::new("Rate"));
+    rates.add_column(TableColumn::<f32>::new("Qty"));
+    rates.append_row(vec![Value::Str("EUR/SEK".to_string()), Value::Double(11.2834571), Value::Float(3.0)]);
+    rates.append_row(vec![Value::Str("USD/SEK".to_string()), Value::Double(10.4109263), Value::Float(12.5)]);
+    println!("\nRates:");
+    print!("{}", rates.render(&FormatOptions::swedish().column_precision("Rate", 6).column_precision("Qty", 1)));
+
+
+    // Join transactions against the chart of accounts
+    let mut accounts = OrderedTable::new();
+    accounts.add_column(TableColumn::<i32>::new("Account"));
+    accounts.add_column(TableColumn::<String>::new("Name"));
+    accounts.append_row(vec![Value::Int(1930), Value::Str("Bank".to_string())]);
+    accounts.append_row(vec![Value::Int(4010), Value::Str("Purchases".to_string())]);
+    let mut transactions = OrderedTable::new();
+    transactions.add_column(TableColumn::<i32>::new("Account"));
+    transactions.add_column(TableColumn::<Money>::new("Amount"));
+    transactions.append_row(vec![Value::Int(4010), Value::from(Money::new(125_000, Currency::SEK))]);
+    transactions.append_row(vec![Value::Int(1930), Value::from(Money::new(-125_000, Currency::SEK))]);
+    transactions.append_row(vec![Value::Int(2640), Value::from(Money::new(31_250, Currency::SEK))]);
+    println!("\nTransactions with account names (left join):");
+    let mut named = transactions.join(&accounts, "Account", "Account", JoinKind::Left).unwrap();
+    named.move_column(2, 1).unwrap();
+    named.print_table();
+    println!("Inner join keeps {} of {} rows", transactions.join(&accounts, "Account", "Account", JoinKind::Inner).unwrap().nrows(), transactions.nrows());
+
+    // CSV round trip through the typed schema
+    let mut csv = Vec::new();
+    transactions.to_csv(&mut csv).unwrap();
+    println!("\nTransactions as CSV:\n{}", String::from_utf8_lossy(&csv));
+    let reloaded = UnorderedTable::from_csv(csv.as_slice(), &transactions.schema()).unwrap();
+    println!("Reloaded into an UnorderedTable:");
+    reloaded.print_table();
+    let damaged = b"Account,Amount\n4010,1250.00 SEK\n1930,twelve SEK\n";
+    if let Err(e) = UnorderedTable::from_csv(&damaged[..], &transactions.schema()) {
+        println!("Import failed: {}", e);
+        if let Some(err) = e.get_ref().and_then(|inner| inner.downcast_ref::<TableError>()) {
+            println!("Fix row {} column {}", err.row().unwrap(), err.column().unwrap());
+        }
+    }
+
+    // Typed rows through #[derive(TableRow)]
+    let mut journal = OrderedTable::with_schema::<Transaction>();
+    journal.append(Transaction { account: 1930, text: "Customer payment".to_string(), amount: Money::new(50_000, Currency::SEK) });
+    journal.append(Transaction { account: 1510, text: "Customer payment".to_string(), amount: Money::new(-50_000, Currency::SEK) });
+    println!("\nJournal:");
+    journal.print_table();
+    println!("First entry read back: {:?}", journal.row_as::<Transaction>(0).unwrap());
+    println!("Last entry:");
+    journal.tail(1).print_table();
+    let mut year = OrderedTable::with_schema::<Transaction>();
+    for month in [&journal, &journal] {
+        year.concat(month).unwrap();
+    }
+    println!("Two months merged: {} rows", year.nrows());
+    println!("Overlapping import removed {} duplicate rows", year.distinct_by(&["Account", "Amount"]).unwrap());
+    if let Err(e) = year.concat(&ledger) {
+        println!("Refused to merge the ledger: {}", e);
+    }
+    let mut revised = journal.clone();
+    let audit = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = audit.clone();
+    revised.on_change(move |event| log.lock().unwrap().push(event.clone()));
+    let copy = revised.duplicate_row(0).unwrap();
+    revised.delete_row(copy);
+    revised.set_cell(1, "Text", Value::Str("Customer payment, invoice 17".to_string())).unwrap();
+    revised.append(Transaction { account: 6570, text: "Bank fee".to_string(), amount: Money::new(-2_500, Currency::SEK) });
+    print!("Changes since last save:\n{}", journal.diff(&revised).unwrap());
+    println!("Audit log: {:?}", audit.lock().unwrap());
+    let shared = SharedTable::new(journal.empty_like());
+    let importer = {
+        let shared = shared.clone();
+        std::thread::spawn(move || {
+            for i in 0..100 {
+                shared.with_write(|t| t.append(Transaction { account: 1930, text: format!("Import {}", i), amount: Money::new(100, Currency::SEK) })).unwrap();
+            }
+        })
+    };
+    let _mid_import_view = shared.snapshot().unwrap();
+    importer.join().unwrap();
+    println!("Shared journal after import: {} rows", shared.read().unwrap().nrows());
+    year.clear();
+    println!("Year closed: {} rows, {} columns kept", year.nrows(), year.schema().len());
+
+    // Account x month summary
+    let mut postings = OrderedTable::new();
+    postings.add_column(TableColumn::<i32>::new("Account"));
+    postings.add_column(TableColumn::<String>::new("Month"));
+    postings.add_column(TableColumn::<Money>::new("Amount"));
+    for (account, month, amount) in [(4010, "2024-01", 120_000), (4010, "2024-02", 80_000), (6570, "2024-01", 2_500), (4010, "2024-01", 30_000)] {
+        postings.append_row(vec![Value::Int(account), Value::Str(month.to_string()), Value::from(Money::new(amount, Currency::SEK))]);
+    }
+    let summary = postings.pivot("Account", "Month", "Amount", Agg::Sum).unwrap();
+    println!("\nAccount x month:");
+    summary.print_table();
+    println!("Melted back: {} rows", summary.melt(&["Account"], "Month", "Amount").unwrap().nrows());
+    postings.create_index("Account").unwrap();
+    println!("Postings on 4010: {:?}", postings.find_by("Account", &Value::Int(4010)).unwrap());
+    println!("\nSales postings over 1 000:");
+    postings.query(col("Amount").gt(lit(1000)).and(col("Account").eq(lit(4010)))).unwrap().print_table();
+    let mut archive = postings.empty_like().to_unordered();
+    let copied = copy_rows(&postings, &mut archive).unwrap();
+    println!("Archived {} postings into {} columns", copied, archive.columns().len());
+    postings.add_column_with(TableColumn::<String>::new("Voucher"), ["V1", "V2", "V3", "V4"].map(Value::from)).unwrap();
+    if let Err(e) = postings.add_column_with(TableColumn::<i32>::new("Project"), [Value::Int(7)]) {
+        println!("Project column refused: {}", e);
+    }
+    #[cfg(feature = "uuid")]
+    {
+        let mut docs = OrderedTable::new();
+        docs.add_column(TableColumn::<uuid::Uuid>::new("Id"));
+        docs.add_column(TableColumn::<String>::new("Document"));
+        docs.append_row(vec![Value::parse_uuid("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap(), Value::Str("Invoice 17".to_string())]);
+        println!("\nDocuments:");
+        docs.print_table();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn people() -> OrderedTable {
+        let mut table = OrderedTable::new();
+        table.add_column(TableColumn::<i32>::new("Age"));
+        table.add_column(TableColumn::<String>::new("Name"));
+        table.append_row(vec![Value::Int(25), Value::Str("Alice".to_string())]);
+        table.append_row(vec![Value::Int(30), Value::Str("Bob".to_string())]);
+        table
+    }
+
+    #[test]
+    fn ordered_set_cell_checks_row_column_and_kind() {
+        let mut table = people();
+        assert_eq!(table.set_cell(1, "Age", Value::Int(31)), Ok(()));
+        assert_eq!(table.get_cell(1, "Age"), Some(Value::Int(31)));
+        assert_eq!(table.set_cell(2, "Age", Value::Int(1)), Err(TableError::RowOutOfBounds { index: 2, len: 2 }));
+        assert_eq!(table.set_cell(0, "Salary", Value::Int(1)), Err(TableError::UnknownColumn("Salary".to_string())));
+        let err = table.set_cell(0, "Age", Value::Str("old".to_string())).unwrap_err();
+        assert!(matches!(err, TableError::AtRow { row: 0, ref source } if matches!(**source, TableError::TypeMismatch { .. })));
+        assert_eq!(table.get_cell(0, "Age"), Some(Value::Int(25)));
+    }
+
+    /// Checks `tree` against `model`: order, sizes, parent links, balance and `position`
+    fn assert_matches(tree: &TreeArray<usize>, model: &[usize]) {
+        assert_eq!(tree.in_order(), model);
+        assert_eq!(tree.len(), model.len());
+        for (idx, &value) in model.iter().enumerate() {
+            assert_eq!(tree.get(idx), Some(value));
+            assert_eq!(tree.position(value), Some(idx), "position of {}", value);
+        }
+        assert_eq!(tree.get(model.len()), None);
+        let mut stack: Vec<usize> = tree.root.into_iter().collect();
+        while let Some(node) = stack.pop() {
+            assert!(tree.balance_factor(node).abs() <= 1);
+            for child in [tree.nodes[node].left, tree.nodes[node].right].into_iter().flatten() {
+                assert_eq!(tree.nodes[child].parent, Some(node));
+                stack.push(child);
+            }
+        }
+    }
+
+    #[test]
+    fn tree_array_inserts_and_deletes_like_a_vec() {
+        let mut tree = TreeArray::new();
+        let mut model = Vec::new();
+        let mut seed = 7usize;
+        for value in 0..300 {
+            seed = (seed * 1103515245 + 12345) % (1 << 31);
+            let idx = seed % (model.len() + 1);
+            tree.insert(idx, value);
+            model.insert(idx, value);
+            if value % 3 == 2 {
+                let idx = seed / 7 % model.len();
+                tree.delete(idx);
+                model.remove(idx);
+            }
+        }
+        assert_matches(&tree, &model);
+        // Deleted nodes are reused rather than the arena growing
+        let arena = tree.nodes.len();
+        tree.append(1000);
+        model.push(1000);
+        assert_eq!(tree.nodes.len(), arena);
+        assert_matches(&tree, &model);
+        assert_eq!(tree.position(9999), None);
+    }
+
+    #[test]
+    fn tree_array_set_and_extend_keep_positions() {
+        let mut tree = TreeArray::from_slice(&[10, 20, 30]);
+        // Swapping two values passes through a moment where both nodes hold one of them
+        assert!(tree.set(0, 30));
+        assert!(tree.set(2, 10));
+        assert_matches(&tree, &[30, 20, 10]);
+        assert!(tree.set(1, 25));
+        assert_eq!(tree.position(20), None);
+        assert!(!tree.set(3, 40));
+
+        tree.extend_from_slice(&[40, 50, 60, 70]);
+        assert_matches(&tree, &[30, 25, 10, 40, 50, 60, 70]);
+        assert_eq!(tree.range(2, 5), [10, 40, 50]);
+    }
+
+    #[test]
+    fn unordered_undo_and_redo_keep_the_index_in_step() {
+        let mut table = UnorderedTable::new();
+        table.add_column(TableColumn::<String>::new("Name"));
+        for name in ["Alice", "Bob", "Carol"] { table.append_row(vec![Value::Str(name.to_string())]); }
+        table.create_index("Name").unwrap();
+        let find = |table: &UnorderedTable, name: &str| table.find("Name", &Value::Str(name.to_string())).unwrap();
+
+        table.set_cell(1, "Name", Value::Str("Bert".to_string())).unwrap();
+        assert_eq!((find(&table, "Bob"), find(&table, "Bert")), (vec![], vec![1]));
+        assert!(table.undo());
+        assert_eq!((find(&table, "Bob"), find(&table, "Bert")), (vec![1], vec![]));
+        assert!(table.redo());
+        assert_eq!(find(&table, "Bert"), [1]);
+
+        table.delete_row(0);
+        assert_eq!((find(&table, "Alice"), find(&table, "Carol")), (vec![], vec![1]));
+        assert!(table.undo());
+        assert_eq!((find(&table, "Alice"), find(&table, "Carol")), (vec![0], vec![2]));
+
+        table.swap_rows(0, 2);
+        assert_eq!(find(&table, "Alice"), [2]);
+        assert!(table.undo());
+        assert_eq!(find(&table, "Alice"), [0]);
+        assert_eq!(table.get_cell(1, "Name"), Ok(Value::Str("Bert".to_string())));
+    }
 }
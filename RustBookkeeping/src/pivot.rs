@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use crate::error::{TableError, TableResult};
+use crate::{ColumnKind, OrderedTable};
+use crate::value::Value;
+
+/// How the values that land in one pivot cell are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum Agg {
+    /// Sum of the values; money must share one currency
+    Sum,
+    /// Number of values, as `Int`
+    Count,
+    Min,
+    Max,
+    First,
+    Last,
+}
+
+impl Agg {
+    fn combine(self, acc: Value, val: Value) -> TableResult<Value> {
+        match self {
+            Agg::Sum => acc.checked_add(&val).ok_or_else(|| {
+                TableError::InvalidOperation(format!("cannot add {:?} and {:?}", acc, val))
+            }),
+            Agg::Count => Ok(Value::Int(if let Value::Int(n) = acc { n + 1 } else { 1 })),
+            Agg::Min => Ok(if val.compare(&acc).is_lt() { val } else { acc }),
+            Agg::Max => Ok(if val.compare(&acc).is_gt() { val } else { acc }),
+            Agg::First => Ok(acc),
+            Agg::Last => Ok(val),
+        }
+    }
+
+    fn start(self, val: Value) -> Value {
+        match self {
+            Agg::Count => Value::Int(1),
+            _ => val,
+        }
+    }
+}
+
+fn lookup(table: &OrderedTable, name: &str) -> TableResult<usize> {
+    table.column_index(name).ok_or_else(|| TableError::UnknownColumn(name.to_string()))
+}
+
+impl OrderedTable {
+    /// Long-to-wide reshaping: one row per distinct `index_col` value (in order of first
+    /// appearance), one column per distinct `key_col` value (sorted, named by its text),
+    /// and each cell the `agg` of the `value_col` values of the matching rows.
+    /// Cells without any matching row hold the value column's default.
+    pub fn pivot(&self, index_col: &str, key_col: &str, value_col: &str, agg: Agg) -> TableResult<OrderedTable> {
+        let (i, k, v) = (lookup(self, index_col)?, lookup(self, key_col)?, lookup(self, value_col)?);
+        let nrows = self.nrows();
+        let cell = |c: usize, r: usize| {
+            let col = &self.columns[c];
+            if r < col.len() { col.get(r) } else { col.default_value() }
+        };
+
+        let mut index_values: Vec<Value> = Vec::new();
+        let mut index_pos: HashMap<Value, usize> = HashMap::new();
+        let mut key_values: Vec<Value> = Vec::new();
+        let mut cells: HashMap<(usize, Value), Value> = HashMap::new();
+        for r in 0..nrows {
+            let idx_val = cell(i, r);
+            let row = *index_pos.entry(idx_val.normalized()).or_insert_with(|| {
+                index_values.push(idx_val);
+                index_values.len() - 1
+            });
+            let key = cell(k, r);
+            let key_norm = key.normalized();
+            if !key_values.iter().any(|kv| kv.normalized() == key_norm) { key_values.push(key); }
+            let val = cell(v, r);
+            let combined = match cells.remove(&(row, key_norm.clone())) {
+                Some(acc) => agg.combine(acc, val)?,
+                None => agg.start(val),
+            };
+            cells.insert((row, key_norm), combined);
+        }
+        key_values.sort_by(|a, b| a.compare(b));
+
+        let value_kind = if agg == Agg::Count { ColumnKind::Int } else { self.columns[v].kind() };
+        let mut out = OrderedTable::new();
+        out.columns.push(self.columns[i].empty_like());
+        for key in &key_values { out.columns.push(value_kind.new_column(&key.to_string())); }
+        for (row, idx_val) in index_values.into_iter().enumerate() {
+            out.columns[0].push(idx_val);
+            for (col, key) in out.columns[1..].iter_mut().zip(&key_values) {
+                match cells.remove(&(row, key.normalized())) {
+                    Some(val) => col.push(val),
+                    None => col.push_empty(),
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Wide-to-long reshaping (the inverse of `pivot`): every non-`id_cols` column becomes
+    /// rows of (id values..., `var_name` = column name, `value_name` = cell value).
+    /// The melted columns must all have the same kind.
+    pub fn melt(&self, id_cols: &[&str], var_name: &str, value_name: &str) -> TableResult<OrderedTable> {
+        let ids = id_cols.iter().map(|name| lookup(self, name)).collect::<TableResult<Vec<usize>>>()?;
+        let melted: Vec<usize> = (0..self.columns.len()).filter(|c| !ids.contains(c)).collect();
+        let Some(&first) = melted.first() else {
+            return Err(TableError::InvalidOperation("no columns left to melt".to_string()));
+        };
+        let kind = self.columns[first].kind();
+        if let Some(&c) = melted.iter().find(|&&c| self.columns[c].kind() != kind) {
+            return Err(TableError::SchemaMismatch(format!(
+                "column '{}' is {:?}, expected {:?}", self.columns[c].name(), self.columns[c].kind(), kind
+            )));
+        }
+
+        let mut out = OrderedTable::new();
+        for &c in &ids { out.columns.push(self.columns[c].empty_like()); }
+        out.columns.push(ColumnKind::Str.new_column(var_name));
+        out.columns.push(kind.new_column(value_name));
+        let width = ids.len();
+        for r in 0..self.nrows() {
+            for &m in &melted {
+                for (dst, &c) in out.columns.iter_mut().zip(&ids) {
+                    let src = &self.columns[c];
+                    dst.push(if r < src.len() { src.get(r) } else { src.default_value() });
+                }
+                let src = &self.columns[m];
+                out.columns[width].push(Value::Str(src.name().to_string()));
+                out.columns[width + 1].push(if r < src.len() { src.get(r) } else { src.default_value() });
+            }
+        }
+        Ok(out)
+    }
+}
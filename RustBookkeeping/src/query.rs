@@ -0,0 +1,145 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::error::{TableError, TableResult};
+use crate::events::Observers;
+use crate::{Column, OrderedTable, UnorderedTable};
+use crate::value::Value;
+
+/// Read-only view of one row, handed to filter predicates.
+#[derive(Clone, Copy)]
+pub struct RowView<'a> {
+    columns: &'a [Box<dyn Column>],
+    row: usize,
+}
+
+impl<'a> RowView<'a> {
+    pub(crate) fn new(columns: &'a [Box<dyn Column>], row: usize) -> Self { Self { columns, row } }
+
+    /// Value of the named column, None if there is no such column
+    pub fn get(&self, column: &str) -> Option<Value> {
+        let col = self.columns.iter().find(|c| c.name() == column)?;
+        self.cell(col.as_ref())
+    }
+
+    /// Value of the column at position `idx`
+    #[allow(dead_code)]
+    pub fn get_at(&self, idx: usize) -> Option<Value> {
+        self.cell(self.columns.get(idx)?.as_ref())
+    }
+
+    #[allow(dead_code)]
+    pub fn values(&self) -> Vec<Value> {
+        self.columns.iter().filter_map(|c| self.cell(c.as_ref())).collect()
+    }
+
+    /// Storage index of the row
+    #[allow(dead_code)]
+    pub fn index(&self) -> usize { self.row }
+
+    fn cell(&self, col: &dyn Column) -> Option<Value> {
+        if self.row < col.len() { Some(col.get(self.row)) } else { None }
+    }
+}
+
+impl OrderedTable {
+    /// New table with the same columns, holding the rows for which `pred` returns true
+    pub fn filter<F: FnMut(RowView) -> bool>(&self, mut pred: F) -> OrderedTable {
+        let mut out = self.empty_like();
+        for r in 0..self.nrows() {
+            if pred(RowView::new(&self.columns, r)) {
+                for (src, dst) in self.columns.iter().zip(out.columns.iter_mut()) {
+                    if r < src.len() { dst.push(src.get(r)) } else { dst.push_empty() }
+                }
+            }
+        }
+        out
+    }
+
+    /// `filter` on a single column's value. Rows lacking the column never match.
+    pub fn filter_by<F: FnMut(&Value) -> bool>(&self, column: &str, mut pred: F) -> OrderedTable {
+        self.filter(|row| row.get(column).is_some_and(|v| pred(&v)))
+    }
+
+    /// New table holding copies of the named columns, in the given order
+    pub fn select(&self, columns: &[&str]) -> TableResult<OrderedTable> {
+        let mut out = OrderedTable::new();
+        for name in columns {
+            let src = self.column(name).ok_or_else(|| TableError::UnknownColumn(name.to_string()))?;
+            let mut dst = src.empty_like();
+            for r in 0..src.len() { dst.push(src.get(r)); }
+            out.columns.push(dst);
+        }
+        Ok(out)
+    }
+
+    /// New table with copies of the rows in `range` (clamped to the table)
+    pub fn rows<B: RangeBounds<usize>>(&self, range: B) -> OrderedTable {
+        let (start, end) = self.clamp_range(range);
+        OrderedTable { columns: self.columns.iter().map(|c| c.slice(start, end)).collect(), indexes: Vec::new(), observers: Observers::default() }
+    }
+
+    /// First `n` rows
+    #[allow(dead_code)]
+    pub fn head(&self, n: usize) -> OrderedTable { self.rows(..n) }
+
+    /// Last `n` rows
+    pub fn tail(&self, n: usize) -> OrderedTable { self.rows(self.nrows().saturating_sub(n)..) }
+
+    /// Page number `page` (0-based) of `page_size` rows; empty past the end
+    #[allow(dead_code)]
+    pub fn page(&self, page: usize, page_size: usize) -> OrderedTable {
+        let start = page.saturating_mul(page_size);
+        self.rows(start..start.saturating_add(page_size))
+    }
+
+    /// Views of the rows in `range` without copying any values
+    #[allow(dead_code)]
+    pub fn row_views<B: RangeBounds<usize>>(&self, range: B) -> impl Iterator<Item = RowView<'_>> {
+        let (start, end) = self.clamp_range(range);
+        (start..end).map(move |r| RowView::new(&self.columns, r))
+    }
+
+    fn clamp_range<B: RangeBounds<usize>>(&self, range: B) -> (usize, usize) { clamp_range(range, self.nrows()) }
+}
+
+/// `range` as (start, end) within `0..nrows`
+pub(crate) fn clamp_range<B: RangeBounds<usize>>(range: B, nrows: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&s) => s,
+        Bound::Excluded(&s) => s.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&e) => e.saturating_add(1),
+        Bound::Excluded(&e) => e,
+        Bound::Unbounded => nrows,
+    };
+    let end = end.min(nrows);
+    (start.min(end), end)
+}
+
+impl UnorderedTable {
+    /// Views of all rows in logical order. `RowView::index` is the row's physical slot.
+    pub fn iter_rows(&self) -> impl Iterator<Item = RowView<'_>> {
+        self.logical_order.in_order().into_iter().map(move |phys| RowView::new(&self.columns, phys))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::TableError;
+    use crate::value::Value;
+    use crate::{OrderedTable, Table, TableColumn, TableTrait};
+
+    #[test]
+    fn select_unknown_column_is_an_error() {
+        let mut table = OrderedTable::new();
+        table.add_column(TableColumn::<i32>::new("Age"));
+        table.add_column(TableColumn::<String>::new("Name"));
+        table.append_row(vec![Value::Int(25), Value::Str("Alice".to_string())]);
+        let names = table.select(&["Name"]).unwrap();
+        assert_eq!(names.schema().len(), 1);
+        assert_eq!(names.get_cell(0, "Name"), Some(Value::Str("Alice".to_string())));
+        assert_eq!(table.select(&["Name", "Salary"]).unwrap_err(), TableError::UnknownColumn("Salary".to_string()));
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::{self, Write};
+use std::ops::{Range, RangeBounds};
+
+use crate::format::FormatOptions;
+use crate::query::clamp_range;
+use crate::{Column, ColumnKind, Table, UnorderedTable};
+
+// ----------------------------- Render options -----------------------------
+/// Outline drawn around the rendered cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableStyle {
+    /// Space-separated columns under a dashed rule
+    Plain,
+    /// GitHub-flavoured markdown table
+    Markdown,
+    /// `+---+` borders around every row and column
+    AsciiBox,
+}
+
+/// Layout settings for `Table::render_with`, on top of the value formatting.
+/// The default reproduces `print_table`: plain style, everything left-aligned,
+/// no row numbers and no truncation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    pub format: FormatOptions,
+    pub style: TableStyle,
+    /// Right-align numeric, money and duration columns
+    pub align_numbers: bool,
+    /// Prefix each row with its row index in a `#` column
+    pub row_numbers: bool,
+    /// Cut cells (and headers) longer than this many characters, ending them with `…`
+    pub max_cell_width: Option<usize>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self { format: FormatOptions::default(), style: TableStyle::Plain, align_numbers: false, row_numbers: false, max_cell_width: None }
+    }
+}
+
+impl From<FormatOptions> for RenderOptions {
+    fn from(format: FormatOptions) -> Self { Self { format, ..Self::default() } }
+}
+
+impl RenderOptions {
+    #[allow(dead_code)]
+    pub fn format(mut self, format: FormatOptions) -> Self { self.format = format; self }
+    pub fn style(mut self, style: TableStyle) -> Self { self.style = style; self }
+    pub fn align_numbers(mut self, on: bool) -> Self { self.align_numbers = on; self }
+    pub fn row_numbers(mut self, on: bool) -> Self { self.row_numbers = on; self }
+    pub fn max_cell_width(mut self, width: Option<usize>) -> Self { self.max_cell_width = width; self }
+
+    fn clip(&self, text: &str) -> String {
+        match self.max_cell_width {
+            Some(max) if text.chars().count() > max => {
+                let mut cut: String = text.chars().take(max.saturating_sub(1)).collect();
+                cut.push('…');
+                cut
+            }
+            _ => text.to_string(),
+        }
+    }
+}
+
+fn is_numeric(kind: ColumnKind) -> bool {
+    matches!(kind, ColumnKind::Int | ColumnKind::Float | ColumnKind::Double | ColumnKind::Money | ColumnKind::Duration)
+}
+
+/// Writes the column headers and the rows in `rows` according to `opts`, where `cell(r, c)`
+/// formats row `r` of column `c`; row numbers show `r`. Column widths are measured in a first pass over the
+/// cells, so rows are formatted twice but never held in memory all at once.
+pub(crate) fn write_grid<W, F>(mut w: W, columns: &[Box<dyn Column>], rows: Range<usize>, cell: F, opts: &RenderOptions) -> io::Result<()>
+where
+    W: Write,
+    F: Fn(usize, usize) -> String,
+{
+    let prepare = |text: &str| {
+        let text = opts.clip(text);
+        if opts.style == TableStyle::Markdown { text.replace('|', "\\|") } else { text }
+    };
+    let row = |r: usize| -> Vec<String> {
+        let numbers = opts.row_numbers.then(|| r.to_string());
+        numbers.into_iter().chain((0..columns.len()).map(|c| prepare(&cell(r, c)))).collect()
+    };
+
+    let mut header: Vec<String> = columns.iter().map(|c| prepare(c.name())).collect();
+    let mut right: Vec<bool> = columns.iter().map(|c| opts.align_numbers && is_numeric(c.kind())).collect();
+    if opts.row_numbers {
+        header.insert(0, "#".to_string());
+        right.insert(0, true);
+    }
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    if opts.style == TableStyle::Markdown {
+        // The separator row needs room for `---:`
+        for w in widths.iter_mut() { *w = (*w).max(3); }
+    }
+    for r in rows.clone() {
+        for (w, val) in widths.iter_mut().zip(row(r)) { *w = (*w).max(val.chars().count()); }
+    }
+
+    let line = |w: &mut W, vals: &[String], delims: (&str, &str, &str)| write_grid_line(w, vals, &widths, &right, delims);
+    let dashes: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    match opts.style {
+        TableStyle::Plain => {
+            line(&mut w, &header, ("", " ", ""))?;
+            line(&mut w, &dashes, ("", " ", ""))?;
+            for r in rows.clone() { line(&mut w, &row(r), ("", " ", ""))?; }
+        }
+        TableStyle::Markdown => {
+            line(&mut w, &header, ("| ", " | ", " |"))?;
+            let rule: Vec<String> = widths.iter().zip(&right)
+                .map(|(w, r)| if *r { format!("{}:", "-".repeat(w - 1)) } else { "-".repeat(*w) })
+                .collect();
+            line(&mut w, &rule, ("| ", " | ", " |"))?;
+            for r in rows.clone() { line(&mut w, &row(r), ("| ", " | ", " |"))?; }
+        }
+        TableStyle::AsciiBox => {
+            line(&mut w, &dashes, ("+-", "-+-", "-+"))?;
+            line(&mut w, &header, ("| ", " | ", " |"))?;
+            line(&mut w, &dashes, ("+-", "-+-", "-+"))?;
+            for r in rows.clone() { line(&mut w, &row(r), ("| ", " | ", " |"))?; }
+            line(&mut w, &dashes, ("+-", "-+-", "-+"))?;
+        }
+    }
+    w.flush()
+}
+
+/// One line of cells padded to `widths`, framed by the (left, between, right) delimiters.
+fn write_grid_line<W: Write>(w: &mut W, vals: &[String], widths: &[usize], right: &[bool], delims: (&str, &str, &str)) -> io::Result<()> {
+    w.write_all(delims.0.as_bytes())?;
+    for (i, ((val, width), r)) in vals.iter().zip(widths).zip(right).enumerate() {
+        if i > 0 { w.write_all(delims.1.as_bytes())?; }
+        if *r { write!(w, "{:>width$}", val, width = width)?; } else { write!(w, "{:<width$}", val, width = width)?; }
+    }
+    writeln!(w, "{}", delims.2)
+}
+
+impl UnorderedTable {
+    /// Render only the logical rows in `range` (clamped to the table), visiting just
+    /// those rows of the mapping. Row numbers show logical indices.
+    pub fn render_range<B: RangeBounds<usize>>(&self, range: B, opts: &RenderOptions) -> String {
+        if self.columns.is_empty() || self.nrows() == 0 { return self.render_with(opts); }
+        let (start, end) = clamp_range(range, self.nrows());
+        let physical = self.logical_order.range(start, end);
+        let col_opts: Vec<_> = self.columns.iter().map(|c| opts.format.for_column(c.name())).collect();
+        let cell = |r: usize, c: usize| {
+            let col = &self.columns[c];
+            let phys = physical[r - start];
+            if phys < col.len() { col.get(phys).format_with(&col_opts[c]) } else { "".to_string() }
+        };
+        let mut out = Vec::new();
+        write_grid(&mut out, &self.columns, start..end, cell, opts).expect("writing to a Vec cannot fail");
+        String::from_utf8(out).expect("rendered cells are UTF-8")
+    }
+}
@@ -0,0 +1,70 @@
+use crate::error::{TableError, TableResult};
+use crate::events::TableEvent;
+use crate::{Column, OrderedTable, UnorderedTable};
+
+fn move_column(columns: &mut Vec<Box<dyn Column>>, from: usize, to: usize) -> TableResult<()> {
+    let len = columns.len();
+    if from >= len || to >= len {
+        return Err(TableError::ColumnOutOfBounds { index: from.max(to), len });
+    }
+    let col = columns.remove(from);
+    columns.insert(to, col);
+    Ok(())
+}
+
+/// Puts the named columns first, in the given order; unnamed columns follow in their current order.
+fn reorder_columns(columns: &mut Vec<Box<dyn Column>>, names: &[&str]) -> TableResult<()> {
+    let mut order = Vec::with_capacity(columns.len());
+    for name in names {
+        let idx = columns.iter().position(|c| c.name() == *name).ok_or_else(|| TableError::UnknownColumn(name.to_string()))?;
+        if order.contains(&idx) {
+            return Err(TableError::SchemaMismatch(format!("column '{}' listed twice", name)));
+        }
+        order.push(idx);
+    }
+    let rest: Vec<usize> = (0..columns.len()).filter(|i| !order.contains(i)).collect();
+    order.extend(rest);
+
+    let mut slots: Vec<Option<Box<dyn Column>>> = columns.drain(..).map(Some).collect();
+    columns.extend(order.into_iter().map(|i| slots[i].take().unwrap()));
+    Ok(())
+}
+
+impl OrderedTable {
+    /// Move the column at position `from` to position `to`, shifting the ones in between
+    pub fn move_column(&mut self, from: usize, to: usize) -> TableResult<()> {
+        move_column(&mut self.columns, from, to)?;
+        self.observers.emit(TableEvent::ColumnsReordered);
+        Ok(())
+    }
+
+    /// Put the named columns first, in the given order; the rest keep their relative order
+    #[allow(dead_code)]
+    pub fn reorder_columns(&mut self, names: &[&str]) -> TableResult<()> {
+        reorder_columns(&mut self.columns, names)?;
+        self.observers.emit(TableEvent::ColumnsReordered);
+        Ok(())
+    }
+}
+
+impl UnorderedTable {
+    /// Move the column at position `from` to position `to`, shifting the ones in between.
+    /// Clears the undo history, which refers to columns by position.
+    #[allow(dead_code)]
+    pub fn move_column(&mut self, from: usize, to: usize) -> TableResult<()> {
+        move_column(&mut self.columns, from, to)?;
+        self.history.clear();
+        self.observers.emit(TableEvent::ColumnsReordered);
+        Ok(())
+    }
+
+    /// Put the named columns first, in the given order; the rest keep their relative order.
+    /// Clears the undo history, which refers to columns by position.
+    #[allow(dead_code)]
+    pub fn reorder_columns(&mut self, names: &[&str]) -> TableResult<()> {
+        reorder_columns(&mut self.columns, names)?;
+        self.history.clear();
+        self.observers.emit(TableEvent::ColumnsReordered);
+        Ok(())
+    }
+}
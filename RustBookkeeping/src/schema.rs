@@ -0,0 +1,71 @@
+use crate::error::{TableError, TableResult};
+use crate::events::TableEvent;
+use crate::{Column, OrderedTable, UnorderedTable};
+use crate::value::Value;
+
+/// The values for a new column, checked for count and kind
+fn backfill_values<I: IntoIterator<Item = Value>>(col: &dyn Column, nrows: usize, values: I) -> TableResult<Vec<Value>> {
+    let values: Vec<Value> = values.into_iter().collect();
+    if values.len() != nrows {
+        return Err(TableError::SchemaMismatch(format!(
+            "column '{}' needs {} values, got {}", col.name(), nrows, values.len()
+        )));
+    }
+    let kind = col.kind();
+    if let Some((row, bad)) = values.iter().enumerate().find(|(_, v)| !kind.accepts(v)) {
+        return Err(TableError::TypeMismatch { column: col.name().to_string(), expected: kind, value: bad.clone() }.at_row(row));
+    }
+    Ok(values)
+}
+
+impl OrderedTable {
+    /// Add `col` (any rows it holds are discarded) and fill it with one value per
+    /// existing row. Fails, leaving the table unchanged, unless there are exactly
+    /// `nrows()` values, all of the column's kind.
+    pub fn add_column_with<C, I>(&mut self, col: C, values: I) -> TableResult<()>
+    where
+        C: Column + 'static,
+        I: IntoIterator<Item = Value>,
+    {
+        let values = backfill_values(&col, self.nrows(), values)?;
+        let mut col: Box<dyn Column> = Box::new(col);
+        col.truncate(0);
+        for val in values { col.push(val); }
+        let column = col.name().to_string();
+        self.columns.push(col);
+        self.observers.emit(TableEvent::ColumnAdded { column });
+        Ok(())
+    }
+}
+
+impl UnorderedTable {
+    /// Drop the named column and its data, returning it. Clears the undo history,
+    /// which refers to columns by position.
+    pub fn remove_column(&mut self, name: &str) -> Option<Box<dyn Column>> {
+        let idx = self.columns.iter().position(|c| c.name() == name)?;
+        self.drop_index(name);
+        self.history.clear();
+        let removed = self.columns.remove(idx);
+        self.observers.emit(TableEvent::ColumnRemoved { column: name.to_string() });
+        Some(removed)
+    }
+
+    /// Add `col` (any rows it holds are discarded) and fill it with one value per
+    /// existing row, in logical order. Fails, leaving the table unchanged, unless there
+    /// are exactly `nrows()` values, all of the column's kind.
+    pub fn add_column_with<C, I>(&mut self, col: C, values: I) -> TableResult<()>
+    where
+        C: Column + 'static,
+        I: IntoIterator<Item = Value>,
+    {
+        let values = backfill_values(&col, self.nrows(), values)?;
+        let mut col: Box<dyn Column> = Box::new(col);
+        col.truncate(0);
+        for _ in 0..self.next_physical_index { col.push_empty(); }
+        for (phys, val) in self.logical_order.in_order().into_iter().zip(values) { col.update(phys, val); }
+        let column = col.name().to_string();
+        self.columns.push(col);
+        self.observers.emit(TableEvent::ColumnAdded { column });
+        Ok(())
+    }
+}
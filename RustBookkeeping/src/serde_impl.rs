@@ -0,0 +1,110 @@
+use std::collections::HashSet;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::events::Observers;
+use crate::history::History;
+use crate::slots::{FreeSlots, SlotPolicy};
+use crate::{Column, ColumnKind, OrderedTable, TreeArray, UnorderedTable};
+use crate::value::Value;
+
+// ----------------------------- Serialized forms -----------------------------
+/// A column as stored: its kind and every physical value, including free slots.
+#[derive(Serialize, Deserialize)]
+struct ColumnData {
+    name: String,
+    kind: ColumnKind,
+    values: Vec<Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OrderedTableData {
+    columns: Vec<ColumnData>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct UnorderedTableData {
+    columns: Vec<ColumnData>,
+    logical_order: Vec<usize>,
+    next_physical_index: usize,
+    free_physical: Vec<usize>,
+    #[serde(default)]
+    slot_policy: SlotPolicy,
+}
+
+fn save_columns(columns: &[Box<dyn Column>]) -> Vec<ColumnData> {
+    columns.iter()
+        .map(|c| ColumnData { name: c.name().to_string(), kind: c.kind(), values: (0..c.len()).map(|i| c.get(i)).collect() })
+        .collect()
+}
+
+fn load_columns<E: serde::de::Error>(data: Vec<ColumnData>) -> Result<Vec<Box<dyn Column>>, E> {
+    data.into_iter()
+        .map(|d| {
+            let mut col = d.kind.new_column(&d.name);
+            for val in d.values {
+                if !d.kind.accepts(&val) {
+                    return Err(E::custom(format!("column '{}' of kind {:?} cannot hold {:?}", d.name, d.kind, val)));
+                }
+                col.push(val);
+            }
+            Ok(col)
+        })
+        .collect()
+}
+
+// ----------------------------- OrderedTable -----------------------------
+impl Serialize for OrderedTable {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        OrderedTableData { columns: save_columns(&self.columns) }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderedTable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = OrderedTableData::deserialize(deserializer)?;
+        Ok(OrderedTable { columns: load_columns(data.columns)?, indexes: Vec::new(), observers: Observers::default() })
+    }
+}
+
+// ----------------------------- UnorderedTable -----------------------------
+impl Serialize for UnorderedTable {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        UnorderedTableData {
+            columns: save_columns(&self.columns),
+            logical_order: self.logical_order.in_order(),
+            next_physical_index: self.next_physical_index,
+            free_physical: self.free_physical.to_vec(),
+            slot_policy: self.free_physical.policy(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UnorderedTable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = UnorderedTableData::deserialize(deserializer)?;
+        let columns = load_columns(data.columns)?;
+
+        // Every physical slot below next_physical_index is either live or free, never both.
+        let mut free_physical = FreeSlots::new(data.slot_policy);
+        for &p in &data.free_physical { free_physical.insert(p); }
+        let mut seen = HashSet::new();
+        for &p in &data.logical_order {
+            if p >= data.next_physical_index || free_physical.contains(&p) || !seen.insert(p) {
+                return Err(D::Error::custom(format!("invalid physical index {} in logical order", p)));
+            }
+        }
+        if let Some(p) = data.free_physical.iter().find(|&&p| p >= data.next_physical_index) {
+            return Err(D::Error::custom(format!("free physical index {} out of range", p)));
+        }
+        if let Some(c) = columns.iter().find(|c| c.len() < data.next_physical_index) {
+            return Err(D::Error::custom(format!("column '{}' is shorter than the physical storage", c.name())));
+        }
+
+        let mut logical_order = TreeArray::new();
+        for p in data.logical_order { logical_order.append(p); }
+        Ok(UnorderedTable { columns, logical_order, next_physical_index: data.next_physical_index, free_physical, history: History::new(), indexes: Vec::new(), observers: Observers::default() })
+    }
+}
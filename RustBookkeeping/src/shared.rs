@@ -0,0 +1,36 @@
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::error::{TableError, TableResult};
+
+/// A table shared between threads behind one `RwLock`: any number of readers (a GUI
+/// rendering) or one writer (an import appending) at a time. Clones share the table.
+///
+/// A panic in a writer may leave the table half changed, so after one every access fails
+/// with `TableError::Poisoned` instead of handing out the table.
+#[derive(Debug, Default)]
+pub struct SharedTable<T> {
+    inner: Arc<RwLock<T>>,
+}
+
+impl<T> Clone for SharedTable<T> {
+    fn clone(&self) -> Self { Self { inner: Arc::clone(&self.inner) } }
+}
+
+impl<T> SharedTable<T> {
+    pub fn new(table: T) -> Self { Self { inner: Arc::new(RwLock::new(table)) } }
+
+    /// Shared access, blocking while a writer holds the table
+    pub fn read(&self) -> TableResult<RwLockReadGuard<'_, T>> { self.inner.read().map_err(|_| TableError::Poisoned) }
+
+    /// Exclusive access, blocking while anyone else holds the table
+    pub fn write(&self) -> TableResult<RwLockWriteGuard<'_, T>> { self.inner.write().map_err(|_| TableError::Poisoned) }
+
+    #[allow(dead_code)]
+    pub fn with_read<R, F: FnOnce(&T) -> R>(&self, f: F) -> TableResult<R> { Ok(f(&*self.read()?)) }
+
+    pub fn with_write<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> TableResult<R> { Ok(f(&mut *self.write()?)) }
+
+    /// Owned copy of the current state, so slow work (rendering, exporting) can run
+    /// without holding up writers
+    pub fn snapshot(&self) -> TableResult<T> where T: Clone { Ok(self.read()?.clone()) }
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Which freed physical slot `insert_row` recycles next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SlotPolicy {
+    /// The lowest free slot, keeping storage dense at the front
+    #[default]
+    LowestFirst,
+    /// The most recently freed slot, which is likely still in cache
+    Lifo,
+    /// Never recycle: every row keeps a physical id no other row ever had
+    NoReuse,
+}
+
+/// The free physical slots of an `UnorderedTable`, handed out according to a `SlotPolicy`.
+#[derive(Clone)]
+pub(crate) struct FreeSlots {
+    policy: SlotPolicy,
+    set: BTreeSet<usize>,
+    /// Freeing order, kept for `Lifo` only
+    stack: Vec<usize>,
+}
+
+impl FreeSlots {
+    pub(crate) fn new(policy: SlotPolicy) -> Self { Self { policy, set: BTreeSet::new(), stack: Vec::new() } }
+
+    pub(crate) fn policy(&self) -> SlotPolicy { self.policy }
+
+    pub(crate) fn insert(&mut self, slot: usize) -> bool {
+        let added = self.set.insert(slot);
+        if added && self.policy == SlotPolicy::Lifo { self.stack.push(slot); }
+        added
+    }
+
+    pub(crate) fn remove(&mut self, slot: &usize) -> bool {
+        let removed = self.set.remove(slot);
+        if removed && self.policy == SlotPolicy::Lifo {
+            if self.stack.last() == Some(slot) { self.stack.pop(); } else { self.stack.retain(|s| s != slot); }
+        }
+        removed
+    }
+
+    /// Remove and return the slot to reuse next, if the policy allows reuse
+    pub(crate) fn take(&mut self) -> Option<usize> {
+        match self.policy {
+            SlotPolicy::LowestFirst => self.set.pop_first(),
+            SlotPolicy::Lifo => {
+                let slot = self.stack.pop()?;
+                self.set.remove(&slot);
+                Some(slot)
+            }
+            SlotPolicy::NoReuse => None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn contains(&self, slot: &usize) -> bool { self.set.contains(slot) }
+
+    pub(crate) fn len(&self) -> usize { self.set.len() }
+
+    /// Free slots in the order they were freed for `Lifo`, ascending otherwise
+    #[allow(dead_code)]
+    pub(crate) fn to_vec(&self) -> Vec<usize> {
+        match self.policy {
+            SlotPolicy::Lifo => self.stack.clone(),
+            _ => self.set.iter().copied().collect(),
+        }
+    }
+}
+
+impl fmt::Debug for FreeSlots {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.debug_set().entries(&self.set).finish() }
+}
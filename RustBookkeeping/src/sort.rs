@@ -0,0 +1,104 @@
+use std::cmp::Ordering;
+
+use crate::error::{TableError, TableResult};
+use crate::events::TableEvent;
+use crate::{OrderedTable, TableChange, TreeArray, UnorderedTable, UnorderedTableMemento};
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    fn apply(self, ord: Ordering) -> Ordering {
+        match self {
+            SortOrder::Asc => ord,
+            SortOrder::Desc => ord.reverse(),
+        }
+    }
+}
+
+/// Stable order of `nrows` rows by the given key columns, compared with `Value::compare`.
+pub(crate) fn sorted_order(nrows: usize, keys: &[(Vec<Value>, SortOrder)]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..nrows).collect();
+    order.sort_by(|&a, &b| {
+        keys.iter()
+            .map(|(vals, dir)| dir.apply(vals[a].compare(&vals[b])))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    order
+}
+
+impl OrderedTable {
+    /// Stable sort of all rows by the given (column name, order) keys, first key most significant.
+    /// An unknown column name is an error, and leaves the table untouched.
+    pub fn sort_by(&mut self, keys: &[(&str, SortOrder)]) -> TableResult<()> {
+        if let Some((name, _)) = keys.iter().find(|(name, _)| self.column(name).is_none()) {
+            return Err(TableError::UnknownColumn(name.to_string()));
+        }
+        let nrows = self.nrows();
+        for col in self.columns.iter_mut() {
+            while col.len() < nrows { col.push_empty(); }
+        }
+        let key_values: Vec<(Vec<Value>, SortOrder)> = keys.iter()
+            .filter_map(|(name, dir)| {
+                let col = self.column(name)?;
+                Some(((0..nrows).map(|r| col.get(r)).collect(), *dir))
+            })
+            .collect();
+        let order = sorted_order(nrows, &key_values);
+        for col in self.columns.iter_mut() { col.reorder(&order); }
+        self.reindex();
+        self.observers.emit(TableEvent::RowsChanged);
+        Ok(())
+    }
+}
+
+impl UnorderedTable {
+    /// Stable sort of the logical order by one column's values. Only the logical to
+    /// physical mapping is rearranged; no cell data moves.
+    pub fn sort_by(&mut self, column: &str, ascending: bool) -> TableResult<()> {
+        let col = self.columns.iter().find(|c| c.name() == column)
+            .ok_or_else(|| TableError::UnknownColumn(column.to_string()))?;
+        let physical = self.logical_order.in_order();
+        let values = physical.iter().map(|&p| if p < col.len() { col.get(p) } else { col.default_value() }).collect();
+        let dir = if ascending { SortOrder::Asc } else { SortOrder::Desc };
+        let sorted: Vec<usize> = sorted_order(physical.len(), &[(values, dir)]).into_iter().map(|i| physical[i]).collect();
+        self.logical_order = TreeArray::from_slice(&sorted);
+        self.history.record(UnorderedTableMemento { changes: vec![TableChange::LogicalOrder(physical)] });
+        self.observers.emit(TableEvent::RowsChanged);
+        Ok(())
+    }
+
+    /// Flip the logical order (newest first), rebuilding the mapping in one O(n) pass.
+    /// No cell data moves.
+    pub fn reverse(&mut self) {
+        let physical = self.logical_order.in_order();
+        let reversed: Vec<usize> = physical.iter().rev().copied().collect();
+        self.logical_order = TreeArray::from_slice(&reversed);
+        self.history.record(UnorderedTableMemento { changes: vec![TableChange::LogicalOrder(physical)] });
+        self.observers.emit(TableEvent::RowsChanged);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Table, TableColumn, TableTrait};
+
+    #[test]
+    fn sort_by_unknown_column_is_an_error() {
+        let mut table = OrderedTable::new();
+        table.add_column(TableColumn::<i32>::new("Age"));
+        table.append_row(vec![Value::Int(25)]);
+        table.append_row(vec![Value::Int(30)]);
+        let keys = [("Age", SortOrder::Desc), ("Salary", SortOrder::Asc)];
+        assert_eq!(table.sort_by(&keys), Err(TableError::UnknownColumn("Salary".to_string())));
+        assert_eq!(table.get_cell(0, "Age"), Some(Value::Int(25)));
+        assert_eq!(table.sort_by(&keys[..1]), Ok(()));
+        assert_eq!(table.get_cell(0, "Age"), Some(Value::Int(30)));
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+use std::mem;
+
+use crate::UnorderedTable;
+
+/// Snapshot of how an `UnorderedTable` uses its physical storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageStats {
+    /// Physical slots handed out so far, live or free
+    pub allocated_slots: usize,
+    pub live_rows: usize,
+    pub free_slots: usize,
+    /// (column name, stored cells) per column, in column order
+    pub column_lengths: Vec<(String, usize)>,
+    /// Rough total of column data, the logical order and the free set
+    pub estimated_bytes: usize,
+}
+
+impl StorageStats {
+    /// Share of allocated slots that are free, from 0.0 to 1.0; a hint for when to compact
+    pub fn fragmentation(&self) -> f64 {
+        if self.allocated_slots == 0 { 0.0 } else { self.free_slots as f64 / self.allocated_slots as f64 }
+    }
+}
+
+impl fmt::Display for StorageStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "{} live rows in {} slots ({} free, {:.0}% fragmented), ~{} bytes",
+            self.live_rows, self.allocated_slots, self.free_slots, self.fragmentation() * 100.0, self.estimated_bytes
+        )
+    }
+}
+
+impl UnorderedTable {
+    pub fn storage_stats(&self) -> StorageStats {
+        let tree_bytes = self.logical_order.heap_bytes();
+        // A tree node per free slot, plus the freeing order under `Lifo`
+        let free_bytes = self.free_physical.len() * mem::size_of::<usize>() * 4;
+        StorageStats {
+            allocated_slots: self.next_physical_index,
+            live_rows: self.nrows(),
+            free_slots: self.free_physical.len(),
+            column_lengths: self.columns.iter().map(|c| (c.name().to_string(), c.len())).collect(),
+            estimated_bytes: self.columns.iter().map(|c| c.estimated_bytes()).sum::<usize>() + tree_bytes + free_bytes,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{ColumnKind, OrderedTable, Table, UnorderedTable};
+use crate::value::Value;
+
+/// A plain struct that maps to one table row. Usually implemented with
+/// `#[derive(TableRow)]`, which maps each field to a column of the same name.
+pub trait TableRow: Sized {
+    /// Column names and kinds, in row order
+    fn schema() -> Vec<(&'static str, ColumnKind)>;
+    fn into_row(self) -> Vec<Value>;
+    /// None if the row has the wrong length or a value of the wrong kind
+    fn from_row(row: Vec<Value>) -> Option<Self>;
+}
+
+impl OrderedTable {
+    /// Empty table with the columns of `R`
+    pub fn with_schema<R: TableRow>() -> OrderedTable {
+        let mut table = OrderedTable::new();
+        table.columns = R::schema().iter().map(|(name, kind)| kind.new_column(name)).collect();
+        table
+    }
+
+    pub fn append<R: TableRow>(&mut self, row: R) { self.append_row(row.into_row()) }
+
+    /// Row `idx` read back as `R`, None if out of bounds or the columns do not fit `R`
+    pub fn row_as<R: TableRow>(&self, idx: usize) -> Option<R> {
+        let row = self.columns.iter().map(|c| if idx < c.len() { Some(c.get(idx)) } else { None }).collect::<Option<Vec<_>>>()?;
+        R::from_row(row)
+    }
+}
+
+impl UnorderedTable {
+    /// Empty table with the columns of `R`
+    #[allow(dead_code)]
+    pub fn with_schema<R: TableRow>() -> UnorderedTable {
+        let mut table = UnorderedTable::new();
+        table.columns = R::schema().iter().map(|(name, kind)| kind.new_column(name)).collect();
+        table
+    }
+
+    #[allow(dead_code)]
+    pub fn append<R: TableRow>(&mut self, row: R) { self.append_row(row.into_row()) }
+
+    /// Logical row `idx` read back as `R`, None if out of bounds or the columns do not fit `R`
+    #[allow(dead_code)]
+    pub fn row_as<R: TableRow>(&self, idx: usize) -> Option<R> {
+        let phys_idx = self.logical_order.get(idx)?;
+        R::from_row(self.columns.iter().map(|c| c.get(phys_idx)).collect())
+    }
+}
@@ -0,0 +1,584 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::format::group_thousands;
+
+// ----------------------------- Value enum -----------------------------
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    Int(i32),
+    Float(f32),
+    Str(String),
+    Bool(bool),
+    Double(f64),
+    Long(i64),
+    Date(u64),
+    Duration(Duration),
+    Time(TimeOfDay),
+    /// `amount` is in minor units of `currency` (öre for SEK, cents for EUR).
+    Money { amount: i64, currency: Currency },
+    /// External identifier of a transaction or document.
+    #[cfg(feature = "uuid")]
+    Uuid(uuid::Uuid),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(x) => write!(f, "{}", x),
+            Value::Float(x) => write!(f, "{:.2}", x),
+            Value::Str(x) => write!(f, "{}", x),
+            Value::Bool(x) => write!(f, "{}", x),
+            Value::Double(x) => write!(f, "{:.4}", x),
+            Value::Long(x) => write!(f, "{}", x),
+            Value::Date(x) => write!(f, "{}", x),
+            Value::Duration(x) => write!(f, "{}", x),
+            Value::Time(x) => write!(f, "{}", x),
+            Value::Money { amount, currency } => write!(f, "{}", Money::new(*amount, *currency)),
+            #[cfg(feature = "uuid")]
+            Value::Uuid(x) => write!(f, "{}", x.hyphenated()),
+        }
+    }
+}
+
+/// Structural equality: the variant must match, and floats compare by bit pattern
+/// so that `Eq` and `Hash` hold (NaN equals itself, `0.0 != -0.0`).
+/// Use `normalized_eq` to compare across numeric kinds.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Double(a), Value::Double(b)) => a.to_bits() == b.to_bits(),
+            (Value::Long(a), Value::Long(b)) => a == b,
+            (Value::Date(a), Value::Date(b)) => a == b,
+            (Value::Duration(a), Value::Duration(b)) => a == b,
+            (Value::Time(a), Value::Time(b)) => a == b,
+            (Value::Money { amount: a, currency: ca }, Value::Money { amount: b, currency: cb }) => a == b && ca == cb,
+            #[cfg(feature = "uuid")]
+            (Value::Uuid(a), Value::Uuid(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Value::Int(x) => x.hash(state),
+            Value::Float(x) => x.to_bits().hash(state),
+            Value::Str(x) => x.hash(state),
+            Value::Bool(x) => x.hash(state),
+            Value::Double(x) => x.to_bits().hash(state),
+            Value::Long(x) => x.hash(state),
+            Value::Date(x) => x.hash(state),
+            Value::Duration(x) => x.hash(state),
+            Value::Time(x) => x.hash(state),
+            Value::Money { amount, currency } => { amount.hash(state); currency.hash(state) }
+            #[cfg(feature = "uuid")]
+            Value::Uuid(x) => x.hash(state),
+        }
+    }
+}
+
+impl Value {
+    /// Canonical representative for numeric values: every integer kind and every
+    /// integral float becomes `Long`, other floats become `Double`, and `-0.0` becomes
+    /// `Long(0)`. Non-numeric values are returned unchanged. Use the result as a
+    /// HashMap key when `Int(1)` and `Long(1)` should land in the same group.
+    pub fn normalized(&self) -> Value {
+        match self {
+            Value::Int(x) => Value::Long(*x as i64),
+            Value::Float(x) => Self::normalized_float(*x as f64),
+            Value::Double(x) => Self::normalized_float(*x),
+            other => other.clone(),
+        }
+    }
+
+    /// Equality after `normalized`, so `Int(1) == Long(1) == Double(1.0)`.
+    /// Note that `Float(0.1)` and `Double(0.1)` differ, as widening an `f32` is not exact.
+    #[allow(dead_code)]
+    pub fn normalized_eq(&self, other: &Value) -> bool {
+        self.normalized() == other.normalized()
+    }
+
+    /// Parses a UUID in any of the usual textual forms (hyphenated, simple, braced, URN).
+    #[cfg(feature = "uuid")]
+    pub fn parse_uuid(s: &str) -> Result<Value, ParseValueError> {
+        uuid::Uuid::parse_str(s.trim()).map(Value::Uuid).map_err(|_| ParseValueError::new("uuid", s))
+    }
+
+    /// Sum of two values of the same kind: integers (overflow-checked), floats, durations
+    /// and money of the same currency. None for any other combination.
+    pub fn checked_add(&self, other: &Value) -> Option<Value> {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a.checked_add(*b).map(Value::Int),
+            (Value::Long(a), Value::Long(b)) => a.checked_add(*b).map(Value::Long),
+            (Value::Float(a), Value::Float(b)) => Some(Value::Float(a + b)),
+            (Value::Double(a), Value::Double(b)) => Some(Value::Double(a + b)),
+            (Value::Duration(a), Value::Duration(b)) => a.as_secs().checked_add(b.as_secs()).map(|s| Value::Duration(Duration::from_secs(s))),
+            (Value::Money { amount: a, currency: ca }, Value::Money { amount: b, currency: cb }) => {
+                Money::new(*a, *ca).checked_add(&Money::new(*b, *cb)).ok().map(Value::from)
+            }
+            _ => None,
+        }
+    }
+
+    /// Total order used for sorting. Numeric kinds compare by value across kinds
+    /// (`Int(2) < Double(2.5) < Long(3)`), money compares by currency code and then
+    /// amount, and values of unrelated kinds are ordered by kind.
+    pub fn compare(&self, other: &Value) -> Ordering {
+        match (self.numeric(), other.numeric()) {
+            (Some(Ok(a)), Some(Ok(b))) => return a.cmp(&b),
+            (Some(a), Some(b)) => {
+                let as_f64 = |n: Result<i64, f64>| n.map_or_else(|f| f, |i| i as f64);
+                return as_f64(a).total_cmp(&as_f64(b));
+            }
+            _ => {}
+        }
+        match (self, other) {
+            (Value::Str(a), Value::Str(b)) => a.cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Date(a), Value::Date(b)) => a.cmp(b),
+            (Value::Duration(a), Value::Duration(b)) => a.cmp(b),
+            (Value::Time(a), Value::Time(b)) => a.cmp(b),
+            (Value::Money { amount: a, currency: ca }, Value::Money { amount: b, currency: cb }) => ca.cmp(cb).then(a.cmp(b)),
+            #[cfg(feature = "uuid")]
+            (Value::Uuid(a), Value::Uuid(b)) => a.cmp(b),
+            _ => self.kind_rank().cmp(&other.kind_rank()),
+        }
+    }
+
+    /// Integer kinds as `Ok(i64)`, float kinds as `Err(f64)`, anything else `None`.
+    pub(crate) fn numeric(&self) -> Option<Result<i64, f64>> {
+        match self {
+            Value::Int(x) => Some(Ok(*x as i64)),
+            Value::Long(x) => Some(Ok(*x)),
+            Value::Float(x) => Some(Err(*x as f64)),
+            Value::Double(x) => Some(Err(*x)),
+            _ => None,
+        }
+    }
+
+    fn kind_rank(&self) -> u8 {
+        match self {
+            Value::Int(_) | Value::Long(_) | Value::Float(_) | Value::Double(_) => 0,
+            Value::Money { .. } => 1,
+            Value::Date(_) => 2,
+            Value::Time(_) => 3,
+            Value::Duration(_) => 4,
+            Value::Bool(_) => 5,
+            Value::Str(_) => 6,
+            #[cfg(feature = "uuid")]
+            Value::Uuid(_) => 7,
+        }
+    }
+
+    fn normalized_float(x: f64) -> Value {
+        if x.fract() == 0.0 && x >= i64::MIN as f64 && x < i64::MAX as f64 {
+            Value::Long(x as i64)
+        } else {
+            Value::Double(x)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseValueError {
+    kind: &'static str,
+    input: String,
+}
+
+impl ParseValueError {
+    pub(crate) fn new(kind: &'static str, input: &str) -> Self {
+        Self { kind, input: input.to_string() }
+    }
+}
+
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot parse {:?} as {}", self.input, self.kind)
+    }
+}
+
+impl std::error::Error for ParseValueError {}
+
+// ----------------------------- Duration -----------------------------
+/// Elapsed time with second resolution. Negative values are allowed so that
+/// corrections (e.g. "-0:15") can be booked as their own cells.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Duration {
+    secs: i64,
+}
+
+impl Duration {
+    pub fn from_secs(secs: i64) -> Self { Self { secs } }
+    /// `None` when the total does not fit in an `i64` of seconds.
+    pub fn from_hms(hours: i64, minutes: i64, seconds: i64) -> Option<Self> {
+        let secs = hours.checked_mul(3600)?.checked_add(minutes.checked_mul(60)?)?.checked_add(seconds)?;
+        Some(Self { secs })
+    }
+    pub fn as_secs(&self) -> i64 { self.secs }
+    /// Fractional hours, the unit payroll is usually computed in.
+    pub fn as_hours(&self) -> f64 { self.secs as f64 / 3600.0 }
+}
+
+/// Formats as `H:MM:SS` (hours are not wrapped at 24), prefixed with `-` when negative.
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.secs < 0 { "-" } else { "" };
+        let abs = self.secs.unsigned_abs();
+        write!(f, "{}{}:{:02}:{:02}", sign, abs / 3600, (abs / 60) % 60, abs % 60)
+    }
+}
+
+/// Accepts clock notation (`H:MM` or `H:MM:SS`) and unit notation (`1h30m`, `90m`, `45s`),
+/// either optionally prefixed with `-`.
+impl FromStr for Duration {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseValueError::new("duration", s);
+        let trimmed = s.trim();
+        let (negative, body) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        if body.is_empty() { return Err(err()); }
+
+        let secs = if body.contains(':') {
+            let parts: Vec<&str> = body.split(':').collect();
+            let nums: Vec<i64> = parts.iter().map(|p| p.parse::<i64>()).collect::<Result<_, _>>().map_err(|_| err())?;
+            // The sign was stripped above, so a second one ("--1:30") is an error
+            let duration = match nums.as_slice() {
+                [h, m] if *h >= 0 && (0..60).contains(m) => Self::from_hms(*h, *m, 0),
+                [h, m, sec] if *h >= 0 && (0..60).contains(m) && (0..60).contains(sec) => Self::from_hms(*h, *m, *sec),
+                _ => return Err(err()),
+            };
+            duration.ok_or_else(err)?.secs
+        } else {
+            let mut total = 0i64;
+            let mut digits = String::new();
+            for c in body.chars() {
+                match c {
+                    '0'..='9' => digits.push(c),
+                    'h' | 'm' | 's' => {
+                        let n: i64 = digits.parse().map_err(|_| err())?;
+                        digits.clear();
+                        let unit = match c { 'h' => 3600, 'm' => 60, _ => 1 };
+                        total = n.checked_mul(unit).and_then(|n| total.checked_add(n)).ok_or_else(err)?;
+                    }
+                    _ => return Err(err()),
+                }
+            }
+            if !digits.is_empty() { return Err(err()); }
+            total
+        };
+        Ok(Self { secs: if negative { -secs } else { secs } })
+    }
+}
+
+// ----------------------------- TimeOfDay -----------------------------
+/// Wall-clock time within a day, stored as seconds since midnight.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeOfDay {
+    secs: u32,
+}
+
+impl TimeOfDay {
+    pub const SECS_PER_DAY: u32 = 24 * 3600;
+
+    pub fn from_hms(hour: u32, minute: u32, second: u32) -> Option<Self> {
+        if hour < 24 && minute < 60 && second < 60 {
+            Some(Self { secs: hour * 3600 + minute * 60 + second })
+        } else {
+            None
+        }
+    }
+    pub fn hour(&self) -> u32 { self.secs / 3600 }
+    pub fn minute(&self) -> u32 { (self.secs / 60) % 60 }
+    pub fn second(&self) -> u32 { self.secs % 60 }
+    #[allow(dead_code)]
+    pub fn secs_since_midnight(&self) -> u32 { self.secs }
+
+    /// Elapsed time from `self` until `end`, wrapping past midnight (a 22:00 - 06:00 night shift is 8h).
+    pub fn until(&self, end: TimeOfDay) -> Duration {
+        let diff = (end.secs + Self::SECS_PER_DAY - self.secs) % Self::SECS_PER_DAY;
+        Duration::from_secs(diff as i64)
+    }
+}
+
+/// Formats as `HH:MM`, or `HH:MM:SS` when seconds are non-zero.
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.second() == 0 {
+            write!(f, "{:02}:{:02}", self.hour(), self.minute())
+        } else {
+            write!(f, "{:02}:{:02}:{:02}", self.hour(), self.minute(), self.second())
+        }
+    }
+}
+
+/// Accepts `HH:MM` or `HH:MM:SS`.
+impl FromStr for TimeOfDay {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseValueError::new("time of day", s);
+        let nums: Vec<u32> = s.trim().split(':').map(|p| p.parse::<u32>()).collect::<Result<_, _>>().map_err(|_| err())?;
+        match nums.as_slice() {
+            [h, m] => Self::from_hms(*h, *m, 0).ok_or_else(err),
+            [h, m, sec] => Self::from_hms(*h, *m, *sec).ok_or_else(err),
+            _ => Err(err()),
+        }
+    }
+}
+
+// ----------------------------- Money -----------------------------
+/// ISO 4217 currency code, e.g. `SEK`. Serialized as the code string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const SEK: Currency = Currency(*b"SEK");
+    pub const EUR: Currency = Currency(*b"EUR");
+    pub const USD: Currency = Currency(*b"USD");
+
+    pub fn code(&self) -> &str {
+        // Only constructed from validated ASCII uppercase letters.
+        std::str::from_utf8(&self.0).unwrap()
+    }
+
+    /// Number of decimal digits in the minor unit (ISO 4217 exponent).
+    pub fn minor_digits(&self) -> u32 {
+        match &self.0 {
+            b"JPY" | b"KRW" | b"ISK" | b"CLP" | b"VND" => 0,
+            b"BHD" | b"KWD" | b"OMR" | b"JOD" | b"TND" | b"LYD" | b"IQD" => 3,
+            _ => 2,
+        }
+    }
+}
+
+/// The default currency of the books.
+impl Default for Currency {
+    fn default() -> Self { Currency::SEK }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.code()) }
+}
+
+impl From<Currency> for String {
+    fn from(c: Currency) -> Self { c.code().to_string() }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = ParseValueError;
+    fn try_from(s: String) -> Result<Self, Self::Error> { s.parse() }
+}
+
+impl FromStr for Currency {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().as_bytes() {
+            &[a, b, c] if [a, b, c].iter().all(u8::is_ascii_alphabetic) => {
+                Ok(Currency([a.to_ascii_uppercase(), b.to_ascii_uppercase(), c.to_ascii_uppercase()]))
+            }
+            _ => Err(ParseValueError::new("currency code", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    CurrencyMismatch { left: Currency, right: Currency },
+    Overflow,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::CurrencyMismatch { left, right } => write!(f, "cannot combine {} with {}", left, right),
+            MoneyError::Overflow => write!(f, "money amount overflow"),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+/// An amount of a single currency. Arithmetic is only defined between equal
+/// currencies; converting between currencies is left to the caller.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Money {
+    pub amount: i64,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount: i64, currency: Currency) -> Self { Self { amount, currency } }
+
+    pub fn zero(currency: Currency) -> Self { Self { amount: 0, currency } }
+
+    pub fn checked_add(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.same_currency(other)?;
+        let amount = self.amount.checked_add(other.amount).ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.currency))
+    }
+
+    pub fn checked_sub(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.same_currency(other)?;
+        let amount = self.amount.checked_sub(other.amount).ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.currency))
+    }
+
+    #[allow(dead_code)]
+    pub fn checked_neg(&self) -> Result<Money, MoneyError> {
+        let amount = self.amount.checked_neg().ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.currency))
+    }
+
+    /// Sum of `items`, all of which must be in `currency`.
+    pub fn sum<'a, I: IntoIterator<Item = &'a Money>>(currency: Currency, items: I) -> Result<Money, MoneyError> {
+        items.into_iter().try_fold(Money::zero(currency), |acc, m| acc.checked_add(m))
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch { left: self.currency, right: other.currency })
+        }
+    }
+}
+
+impl From<Money> for Value {
+    fn from(m: Money) -> Self { Value::Money { amount: m.amount, currency: m.currency } }
+}
+
+impl From<i32> for Value {
+    fn from(x: i32) -> Self { Value::Int(x) }
+}
+
+impl From<i64> for Value {
+    fn from(x: i64) -> Self { Value::Long(x) }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self { Value::Double(x) }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self { Value::Bool(b) }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self { Value::Str(s.to_string()) }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self { Value::Str(s) }
+}
+
+impl From<Duration> for Value {
+    fn from(d: Duration) -> Self { Value::Duration(d) }
+}
+
+impl From<TimeOfDay> for Value {
+    fn from(t: TimeOfDay) -> Self { Value::Time(t) }
+}
+
+/// A date, as whole seconds since the Unix epoch; earlier times become the epoch.
+impl From<SystemTime> for Value {
+    fn from(t: SystemTime) -> Self {
+        Value::Date(t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()))
+    }
+}
+
+/// Formats as `1 234,56 SEK`: space-grouped thousands, decimal comma, trailing code.
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.currency.minor_digits();
+        let scale = 10u64.pow(digits);
+        let abs = self.amount.unsigned_abs();
+        let sign = if self.amount < 0 { "-" } else { "" };
+        let units = group_thousands(&(abs / scale).to_string(), ' ');
+        if digits == 0 {
+            write!(f, "{}{} {}", sign, units, self.currency)
+        } else {
+            write!(f, "{}{},{:0width$} {}", sign, units, abs % scale, self.currency, width = digits as usize)
+        }
+    }
+}
+
+/// Accepts an amount followed by a currency code, e.g. `1 234,56 SEK`, `1234.56 SEK` or `-12 EUR`.
+/// Either `,` or `.` may be used as decimal separator; spaces inside the amount are ignored.
+impl FromStr for Money {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseValueError::new("money", s);
+        let trimmed = s.trim();
+        let split = trimmed.rfind(char::is_whitespace).ok_or_else(err)?;
+        let currency: Currency = trimmed[split..].parse().map_err(|_| err())?;
+        let number: String = trimmed[..split].chars().filter(|c| !c.is_whitespace()).collect();
+        let (negative, number) = match number.strip_prefix('-') {
+            Some(rest) => (true, rest.to_string()),
+            None => (false, number),
+        };
+        let (units, fraction) = match number.split_once([',', '.']) {
+            Some((u, fr)) => (u, fr),
+            None => (number.as_str(), ""),
+        };
+        let digits = currency.minor_digits() as usize;
+        let all_digits = |p: &str| p.chars().all(|c| c.is_ascii_digit());
+        if units.is_empty() || !all_digits(units) || !all_digits(fraction) || fraction.len() > digits {
+            return Err(err());
+        }
+        let minor = format!("{}{:0<width$}", units, fraction, width = digits);
+        let amount: i64 = minor.parse().map_err(|_| err())?;
+        Ok(Money::new(if negative { -amount } else { amount }, currency))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_from_hms_checks_for_overflow() {
+        assert_eq!(Duration::from_hms(1, 30, 15), Some(Duration::from_secs(5415)));
+        assert_eq!(Duration::from_hms(i64::MAX / 3600, 59, 59), None);
+        assert_eq!(Duration::from_hms(0, i64::MAX, 0), None);
+    }
+
+    #[test]
+    fn duration_parses_one_sign_only() {
+        assert_eq!("-1:30".parse::<Duration>().unwrap(), Duration::from_secs(-5400));
+        assert_eq!("1h30m".parse::<Duration>().unwrap(), Duration::from_secs(5400));
+        assert!("--1:30".parse::<Duration>().is_err());
+        assert!("1:-30".parse::<Duration>().is_err());
+        assert!("9999999999999999:00".parse::<Duration>().is_err());
+    }
+
+    #[test]
+    fn money_checked_neg_overflows_at_the_minimum() {
+        let sek = Money::new(-1250, Currency::SEK);
+        assert_eq!(sek.checked_neg(), Ok(Money::new(1250, Currency::SEK)));
+        assert_eq!(Money::new(i64::MIN, Currency::SEK).checked_neg(), Err(MoneyError::Overflow));
+    }
+}
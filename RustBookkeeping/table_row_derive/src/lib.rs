@@ -0,0 +1,62 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, parse_macro_input};
+
+/// Derives `TableRow` for a struct with named fields. Each field becomes one column,
+/// named after the field, in declaration order; every field type must implement `CellType`.
+/// A field can be given another column name with `#[column("Name")]`.
+#[proc_macro_derive(TableRow, attributes(column))]
+pub fn derive_table_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            _ => return Err(syn::Error::new_spanned(name, "TableRow needs a struct with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(name, "TableRow can only be derived for structs")),
+    };
+
+    let mut idents = Vec::new();
+    let mut types = Vec::new();
+    let mut columns = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let mut column = ident.to_string();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("column")) {
+            column = attr.parse_args::<syn::LitStr>()?.value();
+        }
+        idents.push(ident);
+        types.push(&field.ty);
+        columns.push(column);
+    }
+    let count = idents.len();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics crate::TableRow for #name #ty_generics #where_clause {
+            fn schema() -> Vec<(&'static str, crate::ColumnKind)> {
+                vec![#((#columns, <#types as crate::CellType>::KIND)),*]
+            }
+
+            fn into_row(self) -> Vec<crate::Value> {
+                vec![#(<#types as crate::CellType>::to_value(&self.#idents)),*]
+            }
+
+            fn from_row(row: Vec<crate::Value>) -> Option<Self> {
+                if row.len() != #count { return None; }
+                let mut values = row.into_iter();
+                Some(Self {
+                    #(#idents: <#types as crate::CellType>::from_value(values.next()?)?),*
+                })
+            }
+        }
+    })
+}
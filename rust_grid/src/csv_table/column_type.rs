@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// --------- Column types ----------
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColumnType {
+    #[default]
+    Text,
+    Int,
+    Float,
+    Date, // YYYY-MM-DD
+    Bool, // true / false, any case
+}
+
+impl ColumnType {
+    /// Whether `value` is valid in a column of this type. Empty cells are always valid.
+    pub fn accepts(self, value: &str) -> bool {
+        let value = value.trim();
+        if value.is_empty() {
+            return true;
+        }
+        match self {
+            ColumnType::Text => true,
+            ColumnType::Int => value.parse::<i64>().is_ok(),
+            ColumnType::Float => is_float(value),
+            ColumnType::Date => is_date(value),
+            ColumnType::Bool => value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false"),
+        }
+    }
+
+    /// The narrowest type accepting every value, Text if there are no non-empty values
+    pub fn infer<'a, I: IntoIterator<Item = &'a str>>(values: I) -> ColumnType {
+        let mut candidates = vec![ColumnType::Bool, ColumnType::Int, ColumnType::Float, ColumnType::Date];
+        let mut seen_value = false;
+        for value in values {
+            if value.trim().is_empty() {
+                continue;
+            }
+            seen_value = true;
+            candidates.retain(|candidate| candidate.accepts(value));
+            if candidates.is_empty() {
+                return ColumnType::Text;
+            }
+        }
+        if seen_value { candidates[0] } else { ColumnType::Text }
+    }
+}
+
+fn is_float(value: &str) -> bool {
+    // Rejects "inf", "NaN" and friends, which f64 would parse
+    value.parse::<f64>().is_ok_and(|x| x.is_finite())
+        && value.chars().all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E'))
+}
+
+fn is_date(value: &str) -> bool {
+    parse_date(value).is_some()
+}
+
+/// `YYYY-MM-DD` as (year, month, day), None unless it is a real calendar date
+pub(super) fn parse_date(value: &str) -> Option<(i64, u32, u32)> {
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return None;
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (year.parse::<u32>(), month.parse::<u32>(), day.parse::<u32>()) else {
+        return None;
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    (1..=days_in_month).contains(&day).then_some((year as i64, month, day))
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ColumnType::Text => "text",
+            ColumnType::Int => "int",
+            ColumnType::Float => "float",
+            ColumnType::Date => "date",
+            ColumnType::Bool => "bool",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ColumnType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(ColumnType::Text),
+            "int" => Ok(ColumnType::Int),
+            "float" => Ok(ColumnType::Float),
+            "date" => Ok(ColumnType::Date),
+            "bool" => Ok(ColumnType::Bool),
+            _ => Err(format!("unknown column type '{}'", s)),
+        }
+    }
+}
+
+// --------- Validation error ----------
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellTypeError {
+    pub row: usize,
+    pub col: usize,
+    pub expected: ColumnType,
+    pub value: String,
+}
+
+impl fmt::Display for CellTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "\"{}\" is not a valid {} for cell ({}, {})",
+            self.value, self.expected, self.row, self.col
+        )
+    }
+}
+
+impl std::error::Error for CellTypeError {}
@@ -1,11 +1,105 @@
-use crate::tools::csv_read::{CsvReader, CsvWriter};
-use crate::tools::history::{History, TargetMementoTrait};
+use super::column_type::{CellTypeError, ColumnType};
+use super::edits::{CellEdit, EditError};
+use super::ranges::CellRange;
+use super::render::PrintOptions;
+use super::series::{series, SeriesError};
+use crate::tools::csv_read::{CsvDialect, CsvReader, CsvWriter};
+use crate::tools::history::{BranchInfo, CompositeMementoTrait, History, MementoSizeTrait, TargetMementoTrait};
 use crate::tools::treearray::TreeArray;
-use std::io::{BufRead, Write};
+use regex::{NoExpand, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::{BufRead, Read, Write};
 use std::mem;
+use std::time::{Duration, SystemTime};
+
+// --------- Find and replace options ----------
+#[derive(Debug, Clone, Default)]
+pub struct FindOptions {
+    pub regex: bool,            // pattern is a regular expression, not plain text
+    pub case_insensitive: bool,
+    pub in_col: Option<usize>, // only look in this column
+}
+
+impl FindOptions {
+    fn matcher(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        let source = if self.regex {
+            pattern.to_string()
+        } else {
+            regex::escape(pattern)
+        };
+        RegexBuilder::new(&source)
+            .case_insensitive(self.case_insensitive)
+            .build()
+    }
+}
+
+/// Why `replace_all` changed nothing
+#[derive(Debug)]
+pub enum ReplaceError {
+    Pattern(regex::Error),
+    Type(CellTypeError), // a replaced value its column's type does not accept
+}
+
+impl From<regex::Error> for ReplaceError {
+    fn from(source: regex::Error) -> Self {
+        ReplaceError::Pattern(source)
+    }
+}
+
+impl From<CellTypeError> for ReplaceError {
+    fn from(source: CellTypeError) -> Self {
+        ReplaceError::Type(source)
+    }
+}
+
+impl fmt::Display for ReplaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplaceError::Pattern(source) => write!(f, "invalid pattern: {}", source),
+            ReplaceError::Type(source) => write!(f, "{}", source),
+        }
+    }
+}
+
+impl std::error::Error for ReplaceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplaceError::Pattern(source) => Some(source),
+            ReplaceError::Type(source) => Some(source),
+        }
+    }
+}
+
+// --------- Sort options ----------
+#[derive(Debug, Clone, Default)]
+pub struct SortOptions {
+    pub descending: bool,
+    pub numeric: bool, // compare as numbers; cells that are not come last either way
+    pub header: bool,  // keep the first row in place
+}
+
+impl SortOptions {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        let ordering = if self.numeric {
+            let number = |value: &str| value.trim().parse::<f64>().ok().filter(|x| x.is_finite());
+            match (number(a), number(b)) {
+                (Some(x), Some(y)) => x.total_cmp(&y),
+                (Some(_), None) => return Ordering::Less,
+                (None, Some(_)) => return Ordering::Greater,
+                (None, None) => a.cmp(b),
+            }
+        } else {
+            a.cmp(b)
+        };
+        if self.descending { ordering.reverse() } else { ordering }
+    }
+}
 
 // --------- History for CSV Table changes ----------
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 enum TableChange {
     CellEdit(usize, usize, String),
 
@@ -18,13 +112,49 @@ enum TableChange {
     FreeRowPopped(usize),
     FreeColPushed(usize),
     FreeColPopped(usize),
+
+    RowsReordered(Vec<usize>), // the physical rows in their new order
+    ColTypeSet(usize, Option<ColumnType>), // physical column, Text when None
+
+    Replaced(Box<TableSnapshot>), // the whole table, as before a load
 }
 
-#[derive(Debug, Clone, Default)]
+/// Everything a load replaces
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct TableSnapshot {
+    table: Vec<Vec<String>>,
+    row_indirection: Vec<usize>,
+    col_indirection: Vec<usize>,
+    free_rows: Vec<usize>,
+    free_cols: Vec<usize>,
+    col_types: HashMap<usize, ColumnType>,
+    named_ranges: BTreeMap<String, CellRange>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct CSVTableMemento {
     changes: Vec<TableChange>,
 }
 
+/// The undo history as saved by `write_history`. The mementos refer to rows and
+/// columns by where they are stored, so the storage is saved along with them.
+#[derive(Serialize, Deserialize)]
+struct SavedHistory {
+    state: TableSnapshot,
+    history: History<CSVTableMemento>,
+}
+
+// --------- Row filter ----------
+type RowPredicate = dyn Fn(&[&str]) -> bool;
+
+struct RowFilter(Box<RowPredicate>);
+
+impl std::fmt::Debug for RowFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RowFilter")
+    }
+}
+
 // --------- Main CSV Table logic ---------
 #[derive(Debug)]
 #[allow(unused_assignments)]
@@ -35,6 +165,10 @@ pub struct CSVTable {
     free_rows: Vec<usize>,
     free_cols: Vec<usize>,
     history: History<CSVTableMemento>,
+    row_filter: Option<RowFilter>, // hides rows from printing, the data stays
+    col_types: HashMap<usize, ColumnType>, // by physical column, Text when absent
+    pub(super) named_ranges: BTreeMap<String, CellRange>,
+    coalesce_edits: bool, // consecutive writes to one cell are one undo step
 }
 
 #[allow(dead_code)]
@@ -47,6 +181,10 @@ impl CSVTable {
             free_rows: Vec::<usize>::new(),
             free_cols: Vec::<usize>::new(),
             history: History::<CSVTableMemento>::new(),
+            row_filter: None,
+            col_types: HashMap::new(),
+            named_ranges: BTreeMap::new(),
+            coalesce_edits: false,
         }
     }
 
@@ -74,23 +212,28 @@ impl CSVTable {
         let physical_row_index: usize = match self.free_rows.pop() {
             Some(value) => value,
             None => {
-                let value: usize = self.row_size();
+                // Past every stored row, deleted ones included, as wide as they are
+                let value: usize = self.table.len();
                 let col_size = self.col_size();
-                self.table.push(vec![String::new(); col_size]);
+                let width = self.table.first().map_or(col_size, |row| row.len());
+                self.table.push(vec![String::new(); width]);
                 value
             }
         };
         let row_index = self.row_size();
         self.row_indirection.append(physical_row_index);
-        self.history.record(CSVTableMemento {
-            changes: vec![TableChange::RowDeleted(row_index, physical_row_index)],
-        });
+        self.history.record_labeled(CSVTableMemento {
+            changes: vec![
+                TableChange::RowDeleted(row_index, physical_row_index),
+                TableChange::FreeRowPushed(physical_row_index),
+            ],
+        }, "Append row");
     }
 
     pub fn append_col(self: &mut Self) {
         let physical_col_index: usize = match self.free_cols.pop() {
             Some(value) => value,
-            None => match self.row_size() {
+            None => match self.table.len() {
                 0 => {
                     self.table.push(vec![String::new()]);
                     self.row_indirection.append(0);
@@ -106,32 +249,41 @@ impl CSVTable {
             },
         };
         let col_index = self.col_size();
+        self.col_types.remove(&physical_col_index);
         self.col_indirection.append(physical_col_index);
-        self.history.record(CSVTableMemento {
-            changes: vec![TableChange::ColDeleted(col_index, physical_col_index)],
-        });
+        self.history.record_labeled(CSVTableMemento {
+            changes: vec![
+                TableChange::ColDeleted(col_index, physical_col_index),
+                TableChange::FreeColPushed(physical_col_index),
+            ],
+        }, "Append column");
     }
 
     pub fn insert_row(self: &mut Self, row_index: usize) {
         let physical_row_index: usize = match self.free_rows.pop() {
             Some(value) => value,
             None => {
-                let value: usize = self.row_size();
+                // Past every stored row, deleted ones included, as wide as they are
+                let value: usize = self.table.len();
                 let col_size = self.col_size();
-                self.table.push(vec![String::new(); col_size]);
+                let width = self.table.first().map_or(col_size, |row| row.len());
+                self.table.push(vec![String::new(); width]);
                 value
             }
         };
         self.row_indirection.insert(row_index, physical_row_index);
-        self.history.record(CSVTableMemento {
-            changes: vec![TableChange::RowDeleted(row_index, physical_row_index)],
-        });
+        self.history.record_labeled(CSVTableMemento {
+            changes: vec![
+                TableChange::RowDeleted(row_index, physical_row_index),
+                TableChange::FreeRowPushed(physical_row_index),
+            ],
+        }, format!("Insert row {}", row_index));
     }
 
     pub fn insert_col(self: &mut Self, col_index: usize) {
         let physical_col_index: usize = match self.free_cols.pop() {
             Some(value) => value,
-            None => match self.row_size() {
+            None => match self.table.len() {
                 0 => {
                     self.table.push(vec![String::new()]);
                     self.row_indirection.append(0);
@@ -146,10 +298,14 @@ impl CSVTable {
                 }
             },
         };
+        self.col_types.remove(&physical_col_index);
         self.col_indirection.insert(col_index, physical_col_index);
-        self.history.record(CSVTableMemento {
-            changes: vec![TableChange::ColDeleted(col_index, physical_col_index)],
-        });
+        self.history.record_labeled(CSVTableMemento {
+            changes: vec![
+                TableChange::ColDeleted(col_index, physical_col_index),
+                TableChange::FreeColPushed(physical_col_index),
+            ],
+        }, format!("Insert column {}", col_index));
     }
 
     pub fn delete_row(self: &mut Self, row_index: usize) {
@@ -157,11 +313,14 @@ impl CSVTable {
             Some(value) => value,
             None => panic!("row_index parameter out of bound"),
         };
-        let mut changes: Vec<TableChange> =
-            vec![TableChange::RowInserted(row_index, physical_row_index)];
+        // Undoing takes the row back off the free list, so it is not handed out twice
+        let mut changes: Vec<TableChange> = vec![
+            TableChange::RowInserted(row_index, physical_row_index),
+            TableChange::FreeRowPopped(physical_row_index),
+        ];
         self.free_rows.push(physical_row_index);
         self.row_indirection.delete(row_index);
-        for physical_col_index in 0..self.col_size() {
+        for physical_col_index in 0..self.table[physical_row_index].len() {
             let old_value: String = self.table[physical_row_index][physical_col_index].clone();
             self.table[physical_row_index][physical_col_index] = String::new();
             changes.push(TableChange::CellEdit(
@@ -171,7 +330,8 @@ impl CSVTable {
             ));
         }
 
-        self.history.record(CSVTableMemento { changes: changes });
+        self.history
+            .record_labeled(CSVTableMemento { changes }, format!("Delete row {}", row_index));
     }
 
     pub fn delete_col(self: &mut Self, col_index: usize) {
@@ -179,11 +339,13 @@ impl CSVTable {
             Some(value) => value,
             None => panic!("col_index parameter out of bound"),
         };
-        let mut changes: Vec<TableChange> =
-            vec![TableChange::ColInserted(col_index, physical_col_index)];
+        let mut changes: Vec<TableChange> = vec![
+            TableChange::ColInserted(col_index, physical_col_index),
+            TableChange::FreeColPopped(physical_col_index),
+        ];
         self.free_cols.push(physical_col_index);
         self.col_indirection.delete(col_index);
-        for physical_row_index in 0..self.row_size() {
+        for physical_row_index in 0..self.table.len() {
             let old_value: String = self.table[physical_row_index][physical_col_index].clone();
             self.table[physical_row_index][physical_col_index] = String::new();
             changes.push(TableChange::CellEdit(
@@ -192,10 +354,17 @@ impl CSVTable {
                 old_value,
             ));
         }
-        self.history.record(CSVTableMemento { changes: changes });
+        self.history
+            .record_labeled(CSVTableMemento { changes }, format!("Delete column {}", col_index));
     }
 
-    pub fn write_cell(self: &mut Self, row_index: usize, col_index: usize, value: &str) {
+    /// Fails without writing if `value` does not fit the column's type
+    pub fn write_cell(
+        self: &mut Self,
+        row_index: usize,
+        col_index: usize,
+        value: &str,
+    ) -> Result<(), CellTypeError> {
         let physical_row_index = match self.row_indirection.get(row_index) {
             Some(value) => value,
             None => panic!("row_index parameter out of bound"),
@@ -204,15 +373,520 @@ impl CSVTable {
             Some(value) => value,
             None => panic!("col_index parameter out of bound"),
         };
+        let expected = self.physical_col_type(physical_col_index);
+        if !expected.accepts(value) {
+            return Err(CellTypeError {
+                row: row_index,
+                col: col_index,
+                expected,
+                value: value.to_string(),
+            });
+        }
         let old_value: String = self.table[physical_row_index][physical_col_index].clone();
         self.table[physical_row_index][physical_col_index] = value.to_string();
-        self.history.record(CSVTableMemento {
+        let memento = CSVTableMemento {
             changes: vec![TableChange::CellEdit(
                 physical_row_index,
                 physical_col_index,
                 old_value,
             )],
+        };
+        let label = format!("Write ({}, {})", row_index, col_index);
+        if self.coalesce_edits {
+            let key = format!("{} {}", physical_row_index, physical_col_index);
+            self.history.record_coalesced(memento, label, &key);
+        } else {
+            self.history.record_labeled(memento, label);
+        }
+        Ok(())
+    }
+
+    /// Write many cells, given as (row, col, value), as one undoable change. Every value
+    /// is checked against its column's type before anything is written.
+    fn write_cells(&mut self, cells: Vec<(usize, usize, String)>, label: String) -> Result<(), CellTypeError> {
+        let mut physical_cells = Vec::with_capacity(cells.len());
+        for (row_index, col_index, value) in cells {
+            let physical_row_index = match self.row_indirection.get(row_index) {
+                Some(value) => value,
+                None => panic!("row_index parameter out of bound"),
+            };
+            let physical_col_index = match self.col_indirection.get(col_index) {
+                Some(value) => value,
+                None => panic!("col_index parameter out of bound"),
+            };
+            let expected = self.physical_col_type(physical_col_index);
+            if !expected.accepts(&value) {
+                return Err(CellTypeError {
+                    row: row_index,
+                    col: col_index,
+                    expected,
+                    value,
+                });
+            }
+            physical_cells.push((physical_row_index, physical_col_index, value));
+        }
+
+        let mut changes = Vec::with_capacity(physical_cells.len());
+        for (physical_row_index, physical_col_index, value) in physical_cells {
+            let old_value = mem::replace(&mut self.table[physical_row_index][physical_col_index], value);
+            changes.push(TableChange::CellEdit(
+                physical_row_index,
+                physical_col_index,
+                old_value,
+            ));
+        }
+        if !changes.is_empty() {
+            self.history.record_labeled(CSVTableMemento { changes }, label);
+        }
+        Ok(())
+    }
+
+    /// Run all `edits` in order as one undoable change. If any of them is out of bounds
+    /// or writes a value its column's type rejects, the ones already run are reverted
+    /// and the table is left as it was.
+    pub fn apply_edits(&mut self, edits: Vec<CellEdit>) -> Result<(), EditError> {
+        let label = format!("{} edits", edits.len());
+        self.apply_edits_labeled(edits, &label)
+    }
+
+    fn apply_edits_labeled(&mut self, edits: Vec<CellEdit>, label: &str) -> Result<(), EditError> {
+        self.history.begin_transaction();
+        let mut result = Ok(());
+        for (edit_index, edit) in edits.into_iter().enumerate() {
+            let out_of_bounds = Err(EditError::OutOfBounds { edit: edit_index });
+            result = match edit {
+                CellEdit::Write { row, col, value } if self.has_cell(row, col) => self
+                    .write_cell(row, col, &value)
+                    .map_err(|source| EditError::Type { edit: edit_index, source }),
+                CellEdit::InsertRow(row) if row <= self.row_size() => {
+                    self.insert_row(row);
+                    Ok(())
+                }
+                CellEdit::DeleteRow(row) if self.has_row(row) => {
+                    self.delete_row(row);
+                    Ok(())
+                }
+                CellEdit::InsertCol(col) if col <= self.col_size() => {
+                    self.insert_col(col);
+                    Ok(())
+                }
+                CellEdit::DeleteCol(col) if self.has_col(col) => {
+                    self.delete_col(col);
+                    Ok(())
+                }
+                CellEdit::MoveRow(from, to) if self.has_row(from) && self.has_row(to) => {
+                    self.move_row(from, to);
+                    Ok(())
+                }
+                CellEdit::MoveCol(from, to) if self.has_col(from) && self.has_col(to) => {
+                    self.move_col(from, to);
+                    Ok(())
+                }
+                _ => out_of_bounds,
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+
+        if result.is_err() {
+            self.rollback();
+        } else {
+            self.history.commit_labeled(Some(label));
+        }
+        result
+    }
+
+    /// Group the changes made from now on into one undo step, until `commit` or
+    /// `rollback`
+    pub fn begin_transaction(&mut self) {
+        self.history.begin_transaction();
+    }
+
+    pub fn commit(&mut self) {
+        self.history.commit();
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.history.in_transaction()
+    }
+
+    /// Revert the changes made since `begin_transaction`
+    pub fn rollback(&mut self) {
+        let mut history = mem::take(&mut self.history);
+        history.rollback(self);
+        self.history = history;
+    }
+
+    /// Insert a copy of the row right after it, as one undoable change. Values are
+    /// copied as they are, even ones their column's type rejects. Returns the index of
+    /// the copy.
+    pub fn duplicate_row(&mut self, row_index: usize) -> usize {
+        if !self.has_row(row_index) {
+            panic!("row_index parameter out of bound");
+        }
+        let copy_index = row_index + 1;
+        self.history.begin_transaction();
+        self.insert_row(copy_index);
+        let from = self.row_indirection.get(row_index).expect("row_index is in bounds");
+        let to = self.row_indirection.get(copy_index).expect("the copy was inserted");
+        let cells = self.col_indirection.in_order().into_iter().map(|c| ((from, c), (to, c))).collect();
+        self.copy_physical_cells(cells);
+        self.history.commit_labeled(Some(&format!("Duplicate row {}", row_index)));
+        copy_index
+    }
+
+    /// Insert a copy of the column, with its type, right after it, as one undoable change.
+    /// Returns the index of the copy.
+    pub fn duplicate_col(&mut self, col_index: usize) -> usize {
+        if !self.has_col(col_index) {
+            panic!("col_index parameter out of bound");
+        }
+        let copy_index = col_index + 1;
+        self.history.begin_transaction();
+        self.insert_col(copy_index);
+        let from = self.col_indirection.get(col_index).expect("col_index is in bounds");
+        let to = self.col_indirection.get(copy_index).expect("the copy was inserted");
+        let previous_type = match self.col_types.get(&from) {
+            Some(column_type) => self.col_types.insert(to, *column_type),
+            None => self.col_types.remove(&to),
+        };
+        self.history.record(CSVTableMemento {
+            changes: vec![TableChange::ColTypeSet(to, previous_type)],
         });
+        let cells = self.row_indirection.in_order().into_iter().map(|r| ((r, from), (r, to))).collect();
+        self.copy_physical_cells(cells);
+        self.history.commit_labeled(Some(&format!("Duplicate column {}", col_index)));
+        copy_index
+    }
+
+    /// Copy the non-empty cells given as (from, to) pairs of physical (row, col), without
+    /// checking them against the column types, recording the change
+    fn copy_physical_cells(&mut self, cells: Vec<((usize, usize), (usize, usize))>) {
+        let mut changes = Vec::new();
+        for ((from_row, from_col), (to_row, to_col)) in cells {
+            let value = self.table[from_row][from_col].clone();
+            if !value.is_empty() {
+                let old_value = mem::replace(&mut self.table[to_row][to_col], value);
+                changes.push(TableChange::CellEdit(to_row, to_col, old_value));
+            }
+        }
+        if !changes.is_empty() {
+            self.history.record(CSVTableMemento { changes });
+        }
+    }
+
+    /// Write `value` to every cell of the rectangle between the two corners (inclusive)
+    pub fn fill_range(
+        &mut self,
+        r1: usize,
+        c1: usize,
+        r2: usize,
+        c2: usize,
+        value: &str,
+    ) -> Result<(), CellTypeError> {
+        let mut cells = Vec::new();
+        for row_index in r1.min(r2)..=r1.max(r2) {
+            for col_index in c1.min(c2)..=c1.max(c2) {
+                cells.push((row_index, col_index, value.to_string()));
+            }
+        }
+        let label = format!("Fill ({}, {})..({}, {})", r1, c1, r2, c2);
+        self.write_cells(cells, label)
+    }
+
+    /// Empty every cell of the rectangle between the two corners (inclusive) as one
+    /// undoable change
+    pub fn clear_range(&mut self, r1: usize, c1: usize, r2: usize, c2: usize) {
+        let mut cells = Vec::new();
+        for row_index in r1.min(r2)..=r1.max(r2) {
+            for col_index in c1.min(c2)..=c1.max(c2) {
+                if !self.read_cell(row_index, col_index).is_empty() {
+                    cells.push((row_index, col_index, String::new()));
+                }
+            }
+        }
+        let label = format!("Clear ({}, {})..({}, {})", r1, c1, r2, c2);
+        self.write_cells(cells, label).expect("empty cells fit every column type");
+    }
+
+    /// Drag-fill from `start_cell` to `end_cell`, both (row, col). A rectangle spanning
+    /// several rows is filled column by column in the direction of `end_cell`, a single
+    /// row is filled along the row. Each line continues the series started by its first
+    /// cell and, when it holds a value of the same kind, its second (see `series`).
+    pub fn fill_series(
+        &mut self,
+        start_cell: (usize, usize),
+        end_cell: (usize, usize),
+    ) -> Result<(), SeriesError> {
+        fn steps(from: usize, to: usize) -> Vec<usize> {
+            if from <= to {
+                (from..=to).collect()
+            } else {
+                (to..=from).rev().collect()
+            }
+        }
+        let rows = steps(start_cell.0, end_cell.0);
+        let cols = steps(start_cell.1, end_cell.1);
+        let lines: Vec<Vec<(usize, usize)>> = if rows.len() > 1 {
+            cols.iter()
+                .map(|&c| rows.iter().map(|&r| (r, c)).collect())
+                .collect()
+        } else {
+            vec![cols.iter().map(|&c| (rows[0], c)).collect()]
+        };
+
+        let mut cells = Vec::new();
+        for line in lines {
+            let seed: Vec<String> = line
+                .iter()
+                .take(2)
+                .map(|&(r, c)| self.read_cell(r, c).to_string())
+                .collect();
+            let seed: Vec<&str> = seed.iter().map(|s| s.as_str()).collect();
+            let (row, col) = line[0];
+            let values = series(&seed, line.len()).ok_or(SeriesError::Overflow { row, col })?;
+            for (&(r, c), value) in line.iter().zip(values) {
+                cells.push((r, c, value));
+            }
+        }
+        let label = format!("Fill series {:?}..{:?}", start_cell, end_cell);
+        Ok(self.write_cells(cells, label)?)
+    }
+
+    fn physical_col_type(&self, physical_col_index: usize) -> ColumnType {
+        self.col_types
+            .get(&physical_col_index)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn column_type(&self, col_index: usize) -> ColumnType {
+        match self.col_indirection.get(col_index) {
+            Some(physical_col_index) => self.physical_col_type(physical_col_index),
+            None => panic!("col_index parameter out of bound"),
+        }
+    }
+
+    /// Override the type of a column, as one undoable change. Existing cells are kept as
+    /// they are; returns the rows of those the new type does not accept.
+    pub fn set_column_type(&mut self, col_index: usize, column_type: ColumnType) -> Vec<usize> {
+        let physical_col_index = match self.col_indirection.get(col_index) {
+            Some(value) => value,
+            None => panic!("col_index parameter out of bound"),
+        };
+        let previous_type = self.col_types.insert(physical_col_index, column_type);
+        if previous_type != Some(column_type) {
+            self.history.record_labeled(
+                CSVTableMemento {
+                    changes: vec![TableChange::ColTypeSet(physical_col_index, previous_type)],
+                },
+                format!("Set type of column {}", col_index),
+            );
+        }
+        self.row_indirection
+            .in_order()
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| !column_type.accepts(&self.table[**r][physical_col_index]))
+            .map(|(row_index, _)| row_index)
+            .collect()
+    }
+
+    /// Detect the type of every column from its current values (see `ColumnType::infer`)
+    /// and validate later writes against it. With `header` the first row is left out.
+    /// Returns the types in column order. Undone as one change.
+    pub fn infer_column_types(&mut self, header: bool) -> Vec<ColumnType> {
+        let mut physical_rows = self.row_indirection.in_order();
+        if header && !physical_rows.is_empty() {
+            physical_rows.remove(0);
+        }
+        let mut types = Vec::with_capacity(self.col_size());
+        let mut changes = Vec::new();
+        for physical_col_index in self.col_indirection.in_order() {
+            let column_type = ColumnType::infer(
+                physical_rows
+                    .iter()
+                    .map(|r| self.table[*r][physical_col_index].as_str()),
+            );
+            let previous_type = self.col_types.insert(physical_col_index, column_type);
+            if previous_type != Some(column_type) {
+                changes.push(TableChange::ColTypeSet(physical_col_index, previous_type));
+            }
+            types.push(column_type);
+        }
+        if !changes.is_empty() {
+            self.history
+                .record_labeled(CSVTableMemento { changes }, "Infer column types".to_string());
+        }
+        types
+    }
+
+    pub fn move_row(&mut self, from: usize, to: usize) {
+        let physical_row_index = match self.row_indirection.get(from) {
+            Some(value) => value,
+            None => panic!("from parameter out of bound"),
+        };
+        if to >= self.row_size() {
+            panic!("to parameter out of bound");
+        }
+        if from == to {
+            return;
+        }
+        self.row_indirection.delete(from);
+        self.row_indirection.insert(to, physical_row_index);
+        self.history.record_labeled(CSVTableMemento {
+            changes: vec![
+                TableChange::RowDeleted(to, physical_row_index),
+                TableChange::RowInserted(from, physical_row_index),
+            ],
+        }, format!("Move row {} to {}", from, to));
+    }
+
+    pub fn move_col(&mut self, from: usize, to: usize) {
+        let physical_col_index = match self.col_indirection.get(from) {
+            Some(value) => value,
+            None => panic!("from parameter out of bound"),
+        };
+        if to >= self.col_size() {
+            panic!("to parameter out of bound");
+        }
+        if from == to {
+            return;
+        }
+        self.col_indirection.delete(from);
+        self.col_indirection.insert(to, physical_col_index);
+        self.history.record_labeled(CSVTableMemento {
+            changes: vec![
+                TableChange::ColDeleted(to, physical_col_index),
+                TableChange::ColInserted(from, physical_col_index),
+            ],
+        }, format!("Move column {} to {}", from, to));
+    }
+
+    /// Order the rows by their values in `col_index`, as one undoable step. The sort
+    /// is stable, so rows with equal values keep their order. Returns false, recording
+    /// nothing, if the rows were in order already.
+    pub fn sort_rows(&mut self, col_index: usize, options: &SortOptions) -> bool {
+        let physical_col_index = match self.col_indirection.get(col_index) {
+            Some(value) => value,
+            None => panic!("col_index parameter out of bound"),
+        };
+        let previous = self.row_indirection.in_order();
+        let mut order = previous.clone();
+        let start = usize::from(options.header).min(order.len());
+        order[start..].sort_by(|&a, &b| {
+            options.compare(&self.table[a][physical_col_index], &self.table[b][physical_col_index])
+        });
+        if order == previous {
+            return false;
+        }
+        self.set_row_order(&order);
+        self.history.record_labeled(CSVTableMemento {
+            changes: vec![TableChange::RowsReordered(previous)],
+        }, format!("Sort by column {}", col_index));
+        true
+    }
+
+    fn set_row_order(&mut self, order: &[usize]) {
+        self.row_indirection.clear();
+        for &physical_row_index in order {
+            self.row_indirection.append(physical_row_index);
+        }
+    }
+
+    /// Cells containing `pattern` as plain, case-sensitive text, as (row, col) in table order
+    pub fn find(&self, pattern: &str) -> Vec<(usize, usize)> {
+        self.find_cells(pattern, &FindOptions::default())
+            .expect("escaped pattern is a valid regex")
+    }
+
+    /// (row, col) of every matching cell, row by row
+    pub fn find_cells(
+        &self,
+        pattern: &str,
+        options: &FindOptions,
+    ) -> Result<Vec<(usize, usize)>, regex::Error> {
+        let matcher = options.matcher(pattern)?;
+        Ok(self.matching_cells(&matcher, options.in_col).collect())
+    }
+
+    /// The first matching cell after `after` in row by row order, starting over from
+    /// the top when there is none below; from the top when `after` is None
+    pub fn find_next(
+        &self,
+        pattern: &str,
+        options: &FindOptions,
+        after: Option<(usize, usize)>,
+    ) -> Result<Option<(usize, usize)>, regex::Error> {
+        let matcher = options.matcher(pattern)?;
+        let next = self
+            .matching_cells(&matcher, options.in_col)
+            .find(|cell| after.is_none_or(|after| *cell > after));
+        Ok(next.or_else(|| self.matching_cells(&matcher, options.in_col).next()))
+    }
+
+    fn matching_cells<'a>(
+        &'a self,
+        matcher: &'a Regex,
+        in_col: Option<usize>,
+    ) -> Box<dyn Iterator<Item = (usize, usize)> + 'a> {
+        let Some(col_index) = in_col else {
+            return Box::new(
+                self.iter_cells()
+                    .filter(|(_, _, value)| matcher.is_match(value))
+                    .map(|(row_index, col_index, _)| (row_index, col_index)),
+            );
+        };
+        let Some(physical_col_index) = self.col_indirection.get(col_index) else {
+            return Box::new(std::iter::empty());
+        };
+        Box::new(
+            self.row_indirection
+                .in_order()
+                .into_iter()
+                .enumerate()
+                .filter(move |(_, physical_row_index)| {
+                    matcher.is_match(&self.table[*physical_row_index][physical_col_index])
+                })
+                .map(move |(row_index, _)| (row_index, col_index)),
+        )
+    }
+
+    /// Replace every match of `pattern` in every cell, or every cell of `options.in_col`,
+    /// as one undoable change.
+    /// With `options.regex` the replacement may refer to groups (`$1`, `${name}`).
+    /// Every new value is checked against its column's type before anything is written.
+    /// Returns the number of cells changed.
+    pub fn replace_all(
+        &mut self,
+        pattern: &str,
+        replacement: &str,
+        options: &FindOptions,
+    ) -> Result<usize, ReplaceError> {
+        let matcher = options.matcher(pattern)?;
+        let cols: Vec<(usize, usize)> = match options.in_col {
+            Some(col_index) => self.col_indirection.get(col_index).map(|c| (col_index, c)).into_iter().collect(),
+            None => self.col_indirection.in_order().into_iter().enumerate().collect(),
+        };
+        let mut cells = Vec::new();
+        for (row_index, physical_row_index) in self.row_indirection.in_order().into_iter().enumerate() {
+            for &(col_index, physical_col_index) in &cols {
+                let cell = &self.table[physical_row_index][physical_col_index];
+                let new_value = if options.regex {
+                    matcher.replace_all(cell, replacement)
+                } else {
+                    matcher.replace_all(cell, NoExpand(replacement))
+                };
+                if new_value == *cell {
+                    continue;
+                }
+                cells.push((row_index, col_index, new_value.into_owned()));
+            }
+        }
+        let replaced = cells.len();
+        self.write_cells(cells, format!("Replace \"{}\"", pattern))?;
+        Ok(replaced)
     }
 
     pub fn read_cell(self: &mut Self, row_index: usize, col_index: usize) -> &str {
@@ -228,24 +902,124 @@ impl CSVTable {
         &self.table[physical_row_index][physical_col_index]
     }
 
-    pub fn pretty_print(self: &mut Self) {
-        for physical_row_index in self.row_indirection.in_order() {
-            let mut first: bool = true;
-            print!("[");
-            for physical_col_index in self.col_indirection.in_order() {
-                let deliminator: &str = if first {
-                    first = false;
-                    ""
-                } else {
-                    ", "
-                };
-                print!(
-                    "{}\"{}\"",
-                    deliminator, &self.table[physical_row_index][physical_col_index]
-                );
-            }
-            println!("]");
+    /// All values of a row, in column order
+    pub fn read_row(&self, row_index: usize) -> Vec<&str> {
+        let physical_row_index = match self.row_indirection.get(row_index) {
+            Some(value) => value,
+            None => panic!("row_index parameter out of bound"),
+        };
+        let row = &self.table[physical_row_index];
+        self.col_indirection
+            .in_order()
+            .into_iter()
+            .map(|physical_col_index| row[physical_col_index].as_str())
+            .collect()
+    }
+
+    /// All values of a column, in row order
+    pub fn read_col(&self, col_index: usize) -> Vec<&str> {
+        let physical_col_index = match self.col_indirection.get(col_index) {
+            Some(value) => value,
+            None => panic!("col_index parameter out of bound"),
+        };
+        self.row_indirection
+            .in_order()
+            .into_iter()
+            .map(|physical_row_index| self.table[physical_row_index][physical_col_index].as_str())
+            .collect()
+    }
+
+    /// Every row as (row, values in column order), in row order. The indirections are
+    /// walked once, unlike with `read_cell` for every cell.
+    pub fn iter_rows(&self) -> impl Iterator<Item = (usize, Vec<&str>)> + '_ {
+        let physical_cols = self.col_indirection.in_order();
+        self.row_indirection
+            .in_order()
+            .into_iter()
+            .enumerate()
+            .map(move |(row_index, physical_row_index)| {
+                let row = &self.table[physical_row_index];
+                (row_index, physical_cols.iter().map(|c| row[*c].as_str()).collect())
+            })
+    }
+
+    /// Every cell as (row, col, value), row by row
+    pub fn iter_cells(&self) -> impl Iterator<Item = (usize, usize, &str)> + '_ {
+        self.iter_rows().flat_map(|(row_index, values)| {
+            values
+                .into_iter()
+                .enumerate()
+                .map(move |(col_index, value)| (row_index, col_index, value))
+        })
+    }
+
+    /// Write `values` to the first cells of a row as one undoable change; fails without
+    /// writing if any of them does not fit its column's type
+    pub fn write_row(&mut self, row_index: usize, values: &[&str]) -> Result<(), CellTypeError> {
+        if values.len() > self.col_size() {
+            panic!("values parameter longer than the row");
         }
+        let cells = values
+            .iter()
+            .enumerate()
+            .map(|(col_index, value)| (row_index, col_index, value.to_string()))
+            .collect();
+        self.write_cells(cells, format!("Write row {}", row_index))
+    }
+
+    /// Write `values` to the first cells of a column as one undoable change; fails
+    /// without writing if any of them does not fit the column's type
+    pub fn write_col(&mut self, col_index: usize, values: &[&str]) -> Result<(), CellTypeError> {
+        if values.len() > self.row_size() {
+            panic!("values parameter longer than the column");
+        }
+        let cells = values
+            .iter()
+            .enumerate()
+            .map(|(row_index, value)| (row_index, col_index, value.to_string()))
+            .collect();
+        self.write_cells(cells, format!("Write column {}", col_index))
+    }
+
+    /// Show only the rows for which `filter` returns true, given the row's values in
+    /// column order. Hidden rows keep their indices and are still edited and saved.
+    pub fn set_row_filter<F: Fn(&[&str]) -> bool + 'static>(&mut self, filter: F) {
+        self.row_filter = Some(RowFilter(Box::new(filter)));
+    }
+
+    /// Show only the rows whose value in `col_index` matches `pattern`, which is
+    /// found as `find_cells` would; `options.in_col` is not used
+    pub fn filter_rows(&mut self, col_index: usize, pattern: &str, options: &FindOptions) -> Result<(), regex::Error> {
+        if !self.has_col(col_index) {
+            panic!("col_index parameter out of bound");
+        }
+        let matcher = options.matcher(pattern)?;
+        self.set_row_filter(move |row| row.get(col_index).is_some_and(|value| matcher.is_match(value)));
+        Ok(())
+    }
+
+    pub fn clear_filter(&mut self) {
+        self.row_filter = None;
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        self.row_filter.is_some()
+    }
+
+    /// Logical indices of the rows passing the filter (all rows when there is none)
+    pub fn visible_rows(&self) -> Vec<usize> {
+        let Some(RowFilter(filter)) = &self.row_filter else {
+            return (0..self.row_indirection.len()).collect();
+        };
+        self.iter_rows()
+            .filter(|(_, values)| filter(values))
+            .map(|(row_index, _)| row_index)
+            .collect()
+    }
+
+    pub fn pretty_print(self: &mut Self) {
+        self.render(std::io::stdout().lock(), &PrintOptions::default())
+            .expect("failed printing to stdout");
     }
 
     pub fn inspection_print(self: &mut Self) {
@@ -282,6 +1056,87 @@ impl CSVTable {
         self.history = history;
     }
 
+    /// Bytes held by the undo history
+    pub fn history_memory_usage(&self) -> usize {
+        self.history.memory_usage()
+    }
+
+    /// Keep the undo history within `budget` bytes (None for no budget), forgetting
+    /// the oldest steps beyond it
+    pub fn set_history_budget(&mut self, budget: Option<usize>) {
+        self.history.set_byte_budget(budget);
+    }
+
+    /// Keep at most `limit` undo steps (None for no limit), forgetting the oldest ones
+    pub fn set_history_limit(&mut self, limit: Option<usize>) {
+        self.history.set_limit(limit);
+    }
+
+    /// Labels of the undo steps, oldest first, such as "Delete row 4"
+    pub fn history_entries(&self) -> Vec<&str> {
+        self.history.entries()
+    }
+
+    /// Labels of the redo steps, the next one first
+    pub fn redo_entries(&self) -> Vec<&str> {
+        self.history.redo_entries()
+    }
+
+    /// When and what the undo steps made at or after `time` were, oldest first
+    pub fn history_since(&self, time: SystemTime) -> Vec<(SystemTime, &str)> {
+        self.history.entries_since(time)
+    }
+
+    /// Call `observer` with the label of every change undone, such as "Delete row 4"
+    pub fn on_undo<F: FnMut(&str) + 'static>(&mut self, observer: F) {
+        self.history.on_undo(observer);
+    }
+
+    pub fn on_redo<F: FnMut(&str) + 'static>(&mut self, observer: F) {
+        self.history.on_redo(observer);
+    }
+
+    pub fn on_record<F: FnMut(&str) + 'static>(&mut self, observer: F) {
+        self.history.on_record(observer);
+    }
+
+    /// Make consecutive writes to the same cell one undo step, undone at once
+    pub fn set_coalesce_edits(&mut self, coalesce: bool) {
+        self.coalesce_edits = coalesce;
+    }
+
+    /// Keep undone steps as a branch when new changes are made, instead of forgetting them
+    pub fn set_branching_history(&mut self, branching: bool) {
+        self.history.set_branching(branching);
+    }
+
+    pub fn history_branches(&self) -> Vec<BranchInfo> {
+        self.history.branches()
+    }
+
+    /// Go to the end of the history branch `id`; false if there is no such branch
+    pub fn switch_branch(&mut self, id: usize) -> bool {
+        let mut history = mem::take(&mut self.history);
+        let switched = history.switch_branch(id, self);
+        self.history = history;
+        switched
+    }
+
+    /// Undo until only the first `index` steps of `history_entries` are left
+    pub fn undo_to(&mut self, index: usize) {
+        let mut history = mem::take(&mut self.history);
+        history.undo_to(index, self);
+        self.history = history;
+    }
+
+    /// Undo the changes made in the last `duration`, returning how many there were
+    pub fn undo_since(&mut self, duration: Duration) -> usize {
+        let mut history = mem::take(&mut self.history);
+        let undone = history.undo_since(duration, self);
+        self.history = history;
+        undone
+    }
+
     pub fn undoable(self: &mut Self) -> bool {
         self.history.undoable()
     }
@@ -290,7 +1145,174 @@ impl CSVTable {
         self.history.redoable()
     }
 
+    /// Rewrite the physical storage in display order, dropping the cells of deleted
+    /// rows and columns. Physical indices change, so the history is cleared, open
+    /// transactions included.
+    /// Returns the number of (rows, columns) reclaimed.
+    pub fn compact(&mut self) -> (usize, usize) {
+        let physical_rows = self.row_indirection.in_order();
+        let physical_cols = self.col_indirection.in_order();
+        let reclaimed_rows = self.table.len() - physical_rows.len();
+        let reclaimed_cols = self.table.first().map_or(0, |row| row.len()) - physical_cols.len();
+
+        let mut old_table = mem::take(&mut self.table);
+        self.table = physical_rows
+            .iter()
+            .map(|r| {
+                let row = &mut old_table[*r];
+                physical_cols.iter().map(|c| mem::take(&mut row[*c])).collect()
+            })
+            .collect();
+        self.col_types = physical_cols
+            .iter()
+            .enumerate()
+            .filter_map(|(col_index, c)| self.col_types.get(c).map(|t| (col_index, *t)))
+            .collect();
+
+        self.row_indirection.clear();
+        for row_index in 0..physical_rows.len() {
+            self.row_indirection.append(row_index);
+        }
+        self.col_indirection.clear();
+        for col_index in 0..physical_cols.len() {
+            self.col_indirection.append(col_index);
+        }
+        self.free_rows.clear();
+        self.free_cols.clear();
+        self.history.clear();
+        (reclaimed_rows, reclaimed_cols)
+    }
+
     pub fn read_csv<R: BufRead>(&mut self, reader: R) -> std::io::Result<()> {
+        self.read_csv_with(reader, CsvDialect::default())
+    }
+
+    /// Like `read_csv`, for files with another delimiter, quote or escape style
+    pub fn read_csv_with<R: BufRead>(&mut self, reader: R, dialect: CsvDialect) -> std::io::Result<()> {
+        let records = CsvReader::with_dialect(reader, dialect).map(|record| record.map_err(std::io::Error::from));
+        self.load_records(records)
+    }
+
+    /// Run `load`, typically `read_csv` or `read_json`, as one undoable change that
+    /// keeps the history from before it. If it fails, the table is left as it was.
+    pub fn load_undoable<F>(&mut self, load: F) -> std::io::Result<()>
+    where
+        F: FnOnce(&mut CSVTable) -> std::io::Result<()>,
+    {
+        self.replace_undoable(load, "Load")
+    }
+
+    /// Replace the table with a blank one of `rows` rows under a first row of
+    /// `headers`, if any, and at least `cols` columns, as one undoable change
+    pub fn reset(&mut self, rows: usize, cols: usize, headers: &[&str]) {
+        let cols = cols.max(headers.len());
+        let mut records = Vec::with_capacity(rows + 1);
+        if !headers.is_empty() {
+            records.push(Ok(headers.iter().map(|header| header.to_string()).collect()));
+        }
+        records.extend((0..rows).map(|_| Ok(vec![String::new(); cols])));
+        self.replace_undoable(|csv| csv.load_records(records), "New table")
+            .expect("blank records cannot fail");
+    }
+
+    fn replace_undoable<F>(&mut self, replace: F, label: &str) -> std::io::Result<()>
+    where
+        F: FnOnce(&mut CSVTable) -> std::io::Result<()>,
+    {
+        let before = self.take_snapshot();
+        let history = mem::take(&mut self.history);
+        let result = replace(self);
+        self.history = history;
+        match result {
+            Ok(()) => self.history.record_labeled(
+                CSVTableMemento {
+                    changes: vec![TableChange::Replaced(Box::new(before))],
+                },
+                label,
+            ),
+            Err(_) => {
+                self.take_snapshot();
+                self.restore_snapshot(before);
+            }
+        }
+        result
+    }
+
+    /// Save the undo and redo steps, to be restored by `read_history` once the
+    /// table has been loaded again from what it is saved to
+    pub fn write_history<W: Write>(&mut self, writer: W) -> std::io::Result<()> {
+        let saved = SavedHistory {
+            history: mem::take(&mut self.history),
+            state: self.take_snapshot(),
+        };
+        let result = serde_json::to_writer(writer, &saved);
+        self.restore_snapshot(saved.state);
+        self.history = saved.history;
+        result.map_err(std::io::Error::from)
+    }
+
+    /// Restore the history saved by `write_history`, replacing the current one and
+    /// its open transactions.
+    /// Returns false, changing nothing, if the table's contents are not the ones
+    /// it was saved with, as when the file has been changed since.
+    pub fn read_history<R: Read>(&mut self, reader: R) -> std::io::Result<bool> {
+        let saved: SavedHistory = serde_json::from_reader(reader)?;
+        let state = &saved.state;
+        let same = state.row_indirection.len() == self.row_size()
+            && state.col_indirection.len() == self.col_size()
+            && self.iter_rows().all(|(row_index, row)| {
+                let physical_row = &state.table[state.row_indirection[row_index]];
+                state
+                    .col_indirection
+                    .iter()
+                    .zip(row)
+                    .all(|(physical_col_index, cell)| physical_row[*physical_col_index] == cell)
+            });
+        if !same {
+            return Ok(false);
+        }
+        self.take_snapshot();
+        self.restore_snapshot(saved.state);
+        self.history.replace(saved.history);
+        Ok(true)
+    }
+
+    /// Move the contents out, leaving the table empty
+    fn take_snapshot(&mut self) -> TableSnapshot {
+        let snapshot = TableSnapshot {
+            table: mem::take(&mut self.table),
+            row_indirection: self.row_indirection.in_order(),
+            col_indirection: self.col_indirection.in_order(),
+            free_rows: mem::take(&mut self.free_rows),
+            free_cols: mem::take(&mut self.free_cols),
+            col_types: mem::take(&mut self.col_types),
+            named_ranges: mem::take(&mut self.named_ranges),
+        };
+        self.row_indirection.clear();
+        self.col_indirection.clear();
+        snapshot
+    }
+
+    /// Put back contents taken by `take_snapshot` into an empty table
+    fn restore_snapshot(&mut self, snapshot: TableSnapshot) {
+        self.table = snapshot.table;
+        for physical_row_index in snapshot.row_indirection {
+            self.row_indirection.append(physical_row_index);
+        }
+        for physical_col_index in snapshot.col_indirection {
+            self.col_indirection.append(physical_col_index);
+        }
+        self.free_rows = snapshot.free_rows;
+        self.free_cols = snapshot.free_cols;
+        self.col_types = snapshot.col_types;
+        self.named_ranges = snapshot.named_ranges;
+    }
+
+    /// Replace the table, and clear its history, with `records`; short rows are padded
+    pub(super) fn load_records<I>(&mut self, records: I) -> std::io::Result<()>
+    where
+        I: IntoIterator<Item = std::io::Result<Vec<String>>>,
+    {
         // ---- Reset state ----
         self.table.clear();
         self.row_indirection.clear();
@@ -298,13 +1320,13 @@ impl CSVTable {
         self.free_rows.clear();
         self.free_cols.clear();
         self.history.clear();
-
-        let csv_reader = CsvReader::new(reader);
+        self.col_types.clear();
+        self.named_ranges.clear();
 
         let mut col_count = 0usize;
 
         // ---- Stream rows ----
-        for value in csv_reader {
+        for value in records {
             let record = value?;
             col_count = col_count.max(record.len());
             self.table.push(record);
@@ -328,20 +1350,67 @@ impl CSVTable {
     }
 
     pub fn write_csv<W: Write>(&mut self, writer: W) -> std::io::Result<()> {
-        let mut csv = CsvWriter::new(writer);
+        self.write_csv_with(writer, CsvDialect::default())
+    }
 
-        let rows = self.row_size();
-        let cols = self.col_size();
+    pub fn write_csv_with<W: Write>(&mut self, writer: W, dialect: CsvDialect) -> std::io::Result<()> {
+        let mut csv = CsvWriter::with_dialect(writer, dialect);
 
-        for r in 0..rows {
-            let mut record = Vec::with_capacity(cols);
-            for c in 0..cols {
-                record.push(self.read_cell(r, c).to_string());
-            }
+        for (_, values) in self.iter_rows() {
+            let record: Vec<String> = values.into_iter().map(String::from).collect();
             csv.write_record(&record)?;
         }
 
-        Ok(())
+        csv.flush()
+    }
+}
+
+impl CompositeMementoTrait for CSVTableMemento {
+    fn composite(mementos: Vec<Self>) -> Self {
+        // Changes are applied in order, so the newest memento's come first
+        CSVTableMemento {
+            changes: mementos.into_iter().rev().flat_map(|memento| memento.changes).collect(),
+        }
+    }
+}
+
+impl MementoSizeTrait for CSVTableMemento {
+    fn memory_usage(&self) -> usize {
+        let changes: usize = self
+            .changes
+            .iter()
+            .map(|change| match change {
+                TableChange::CellEdit(_, _, value) => value.capacity(),
+                TableChange::RowsReordered(order) => order.capacity() * mem::size_of::<usize>(),
+                TableChange::Replaced(snapshot) => snapshot.memory_usage(),
+                _ => 0,
+            })
+            .sum();
+        mem::size_of::<Self>() + self.changes.capacity() * mem::size_of::<TableChange>() + changes
+    }
+}
+
+impl TableSnapshot {
+    /// Bytes held, roughly: the hash map's and tree map's own overhead is not counted
+    fn memory_usage(&self) -> usize {
+        let cells: usize = self
+            .table
+            .iter()
+            .map(|row| {
+                row.capacity() * mem::size_of::<String>() + row.iter().map(String::capacity).sum::<usize>()
+            })
+            .sum();
+        let indexes = self.row_indirection.capacity()
+            + self.col_indirection.capacity()
+            + self.free_rows.capacity()
+            + self.free_cols.capacity();
+        let names: usize = self.named_ranges.keys().map(|name| name.capacity() + mem::size_of::<CellRange>()).sum();
+        mem::size_of::<Self>()
+            + self.table.capacity() * mem::size_of::<Vec<String>>()
+            + cells
+            + indexes * mem::size_of::<usize>()
+            + self.col_types.len() * mem::size_of::<(usize, ColumnType)>()
+            + names
     }
 }
 
@@ -391,10 +1460,132 @@ impl TargetMementoTrait<CSVTableMemento> for CSVTable {
                     self.free_cols.pop();
                     inverse_changes.push(TableChange::FreeColPushed(*physical));
                 }
+                TableChange::RowsReordered(order) => {
+                    inverse_changes.push(TableChange::RowsReordered(self.row_indirection.in_order()));
+                    self.set_row_order(order);
+                }
+                TableChange::ColTypeSet(physical, column_type) => {
+                    let previous_type = match column_type {
+                        Some(column_type) => self.col_types.insert(*physical, *column_type),
+                        None => self.col_types.remove(physical),
+                    };
+                    inverse_changes.push(TableChange::ColTypeSet(*physical, previous_type));
+                }
+                TableChange::Replaced(snapshot) => {
+                    let current = self.take_snapshot();
+                    self.restore_snapshot(snapshot.as_ref().clone());
+                    inverse_changes.push(TableChange::Replaced(Box::new(current)));
+                }
             }
         }
+        // Undoing a sequence of changes means reverting them last to first
+        inverse_changes.reverse();
         CSVTableMemento {
             changes: inverse_changes,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(csv: &str) -> CSVTable {
+        let mut table = CSVTable::new();
+        table.read_csv(csv.as_bytes()).unwrap();
+        table
+    }
+
+    fn rows(table: &CSVTable) -> Vec<Vec<&str>> {
+        table.iter_rows().map(|(_, values)| values).collect()
+    }
+
+    #[test]
+    fn undone_delete_row_is_not_handed_out_again() {
+        let mut csv = table("name,amount\na,2\nb,3\n");
+        csv.delete_row(2);
+        csv.undo();
+        csv.duplicate_row(0);
+        assert_eq!(rows(&csv), [["name", "amount"], ["name", "amount"], ["a", "2"], ["b", "3"]]);
+        csv.insert_row(0);
+        csv.write_cell(0, 0, "new").unwrap();
+        assert_eq!(rows(&csv)[1..], [["name", "amount"], ["name", "amount"], ["a", "2"], ["b", "3"]]);
+    }
+
+    #[test]
+    fn undone_delete_col_is_not_handed_out_again() {
+        let mut csv = table("a,b,c\n1,2,3\n");
+        csv.delete_col(1);
+        csv.undo();
+        csv.duplicate_col(0);
+        assert_eq!(rows(&csv), [["a", "a", "b", "c"], ["1", "1", "2", "3"]]);
+    }
+
+    #[test]
+    fn undone_insert_gives_its_row_back() {
+        let mut csv = table("a\nb\n");
+        csv.append_row();
+        csv.undo();
+        csv.append_row();
+        csv.write_cell(2, 0, "c").unwrap();
+        assert_eq!(rows(&csv), [["a"], ["b"], ["c"]]);
+        csv.undo();
+        csv.undo();
+        csv.redo();
+        assert_eq!(rows(&csv), [["a"], ["b"], [""]]);
+    }
+
+    #[test]
+    fn failed_batch_leaves_the_table_as_it_was() {
+        let mut csv = table("a,1\nb,2\nc,3\n");
+        let edits = vec![
+            CellEdit::DeleteRow(1),
+            CellEdit::DeleteCol(0),
+            CellEdit::Write { row: 9, col: 0, value: "x".to_string() },
+        ];
+        assert_eq!(csv.apply_edits(edits), Err(EditError::OutOfBounds { edit: 2 }));
+        assert_eq!(rows(&csv), [["a", "1"], ["b", "2"], ["c", "3"]]);
+        csv.append_row();
+        csv.append_col();
+        csv.write_cell(3, 2, "new").unwrap();
+        assert_eq!(rows(&csv), [["a", "1", ""], ["b", "2", ""], ["c", "3", ""], ["", "", "new"]]);
+    }
+
+    #[test]
+    fn reused_row_is_cleared_past_the_freed_columns() {
+        let mut csv = table("a,b,c\n1,2,3\n");
+        csv.delete_col(0);
+        csv.delete_row(1);
+        csv.append_row();
+        assert_eq!(rows(&csv), [["b", "c"], ["", ""]]);
+    }
+
+    #[test]
+    fn column_types_are_undone() {
+        let mut csv = table("name,amount\na,2\nb,3\n");
+        assert_eq!(csv.infer_column_types(true), [ColumnType::Text, ColumnType::Int]);
+        assert_eq!(csv.set_column_type(0, ColumnType::Int), [0, 1, 2]);
+        csv.undo();
+        assert_eq!(csv.column_type(0), ColumnType::Text);
+        assert_eq!(csv.column_type(1), ColumnType::Int);
+        csv.undo();
+        assert_eq!(csv.column_type(1), ColumnType::Text);
+        csv.redo();
+        assert_eq!(csv.column_type(1), ColumnType::Int);
+    }
+
+    #[test]
+    fn replace_all_checks_every_value_first() {
+        let mut csv = table("name,amount\na,2\nb,3\n");
+        csv.set_column_type(1, ColumnType::Int);
+        // "amount" in the Int column turns the whole replacement down, "name" included
+        let options = FindOptions::default();
+        assert!(matches!(csv.replace_all("a", "c", &options), Err(ReplaceError::Type(_))));
+        assert_eq!(rows(&csv), [["name", "amount"], ["a", "2"], ["b", "3"]]);
+        let regex = FindOptions { regex: true, ..FindOptions::default() };
+        assert!(matches!(csv.replace_all("[", "x", &regex), Err(ReplaceError::Pattern(_))));
+        let in_name = FindOptions { in_col: Some(0), ..FindOptions::default() };
+        assert_eq!(csv.replace_all("a", "c", &in_name).unwrap(), 2);
+        assert_eq!(rows(&csv), [["ncme", "amount"], ["c", "2"], ["b", "3"]]);
+    }
+}
@@ -0,0 +1,123 @@
+use super::csv_table::CSVTable;
+use std::collections::HashMap;
+use std::fmt;
+
+// --------- Table differences ----------
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellChange {
+    pub row: usize,       // in the old table
+    pub other_row: usize, // in the new table
+    pub col: usize,
+    pub old: String,
+    pub new: String,
+}
+
+/// What changed from one table (the old one) to another. Rows are compared by
+/// position, or by the value of a key column with `diff_by`; cells beyond the
+/// narrower table's last column count as empty.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TableDiff {
+    pub added_rows: Vec<(usize, Vec<String>)>,   // index in the new table, values
+    pub removed_rows: Vec<(usize, Vec<String>)>, // index in the old table, values
+    pub changed_cells: Vec<CellChange>,
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_rows.is_empty() && self.removed_rows.is_empty() && self.changed_cells.is_empty()
+    }
+}
+
+impl fmt::Display for TableDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (row, values) in &self.removed_rows {
+            writeln!(f, "- row {}: [{}]", row, quoted(values))?;
+        }
+        for (row, values) in &self.added_rows {
+            writeln!(f, "+ row {}: [{}]", row, quoted(values))?;
+        }
+        for change in &self.changed_cells {
+            let row = if change.row == change.other_row {
+                change.row.to_string()
+            } else {
+                format!("{} -> {}", change.row, change.other_row)
+            };
+            writeln!(f, "~ row {}, col {}: \"{}\" -> \"{}\"", row, change.col, change.old, change.new)?;
+        }
+        Ok(())
+    }
+}
+
+fn quoted(values: &[String]) -> String {
+    values.iter().map(|v| format!("\"{}\"", v)).collect::<Vec<_>>().join(", ")
+}
+
+impl CSVTable {
+    fn row_values(&mut self, row_index: usize) -> Vec<String> {
+        self.read_row(row_index).into_iter().map(String::from).collect()
+    }
+
+    /// Compare row i of this table with row i of `other`
+    pub fn diff(&mut self, other: &mut CSVTable) -> TableDiff {
+        let common = self.row_size().min(other.row_size());
+        let pairs: Vec<(usize, usize)> = (0..common).map(|r| (r, r)).collect();
+        let removed = (common..self.row_size()).collect();
+        let added = (common..other.row_size()).collect();
+        self.diff_rows(other, pairs, removed, added)
+    }
+
+    /// Compare the rows having the same value in column `key_col`, such as an invoice
+    /// number, wherever they are. When a key repeats, its occurrences are paired in order.
+    pub fn diff_by(&mut self, other: &mut CSVTable, key_col: usize) -> TableDiff {
+        let mut other_rows: HashMap<String, Vec<usize>> = HashMap::new();
+        if other.has_col(key_col) {
+            for r in (0..other.row_size()).rev() {
+                other_rows.entry(other.read_cell(r, key_col).to_string()).or_default().push(r);
+            }
+        }
+
+        let mut pairs = Vec::new();
+        let mut removed = Vec::new();
+        for r in 0..self.row_size() {
+            let key = if self.has_col(key_col) { self.read_cell(r, key_col) } else { "" };
+            match other_rows.get_mut(key).and_then(|rows| rows.pop()) {
+                Some(other_row) => pairs.push((r, other_row)),
+                None => removed.push(r),
+            }
+        }
+        let mut added: Vec<usize> = other_rows.into_values().flatten().collect();
+        added.sort_unstable();
+        self.diff_rows(other, pairs, removed, added)
+    }
+
+    fn diff_rows(
+        &mut self,
+        other: &mut CSVTable,
+        pairs: Vec<(usize, usize)>,
+        removed: Vec<usize>,
+        added: Vec<usize>,
+    ) -> TableDiff {
+        let mut diff = TableDiff {
+            removed_rows: removed.into_iter().map(|r| (r, self.row_values(r))).collect(),
+            added_rows: added.into_iter().map(|r| (r, other.row_values(r))).collect(),
+            changed_cells: Vec::new(),
+        };
+        let cols = self.col_size().max(other.col_size());
+        for (row, other_row) in pairs {
+            for col in 0..cols {
+                let old = if self.has_col(col) { self.read_cell(row, col) } else { "" };
+                let new = if other.has_col(col) { other.read_cell(other_row, col) } else { "" };
+                if old != new {
+                    diff.changed_cells.push(CellChange {
+                        row,
+                        other_row,
+                        col,
+                        old: old.to_string(),
+                        new: new.to_string(),
+                    });
+                }
+            }
+        }
+        diff
+    }
+}
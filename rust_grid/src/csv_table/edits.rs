@@ -0,0 +1,42 @@
+use super::column_type::CellTypeError;
+use std::fmt;
+
+// --------- Batch edits ----------
+/// One step of `CSVTable::apply_edits`. Indices refer to the table as it is when the
+/// step runs, after the steps before it.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(dead_code)]
+pub enum CellEdit {
+    Write { row: usize, col: usize, value: String },
+    InsertRow(usize),
+    DeleteRow(usize),
+    InsertCol(usize),
+    DeleteCol(usize),
+    MoveRow(usize, usize),
+    MoveCol(usize, usize),
+}
+
+/// Why a batch was rejected; `edit` is the position of the failing step
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditError {
+    OutOfBounds { edit: usize },
+    Type { edit: usize, source: CellTypeError },
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::OutOfBounds { edit } => write!(f, "edit {} is out of bounds", edit),
+            EditError::Type { edit, source } => write!(f, "edit {}: {}", edit, source),
+        }
+    }
+}
+
+impl std::error::Error for EditError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EditError::OutOfBounds { .. } => None,
+            EditError::Type { source, .. } => Some(source),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use super::column_type::ColumnType;
+use super::csv_table::CSVTable;
+use std::io::{self, Write};
+
+/// A cell in a Markdown table: '|' would end it and a line break the row
+fn markdown_cell(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|").replace("\r\n", "<br>").replace(['\r', '\n'], "<br>")
+}
+
+/// A cell in a TSV line: tabs, line breaks and backslashes are escaped as \t, \n,
+/// \r and \\, since TSV has no quoting
+fn tsv_cell(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl CSVTable {
+    /// Write the table as a Markdown table with the first row as its header, padded
+    /// to line up. Int and Float columns are aligned right.
+    pub fn write_markdown<W: Write>(&mut self, mut writer: W) -> io::Result<()> {
+        if self.row_size() == 0 || self.col_size() == 0 {
+            return Ok(());
+        }
+        let lines: Vec<Vec<String>> = self
+            .iter_rows()
+            .map(|(_, values)| values.iter().map(|v| markdown_cell(v)).collect())
+            .collect();
+        let right: Vec<bool> = (0..self.col_size())
+            .map(|c| matches!(self.column_type(c), ColumnType::Int | ColumnType::Float))
+            .collect();
+        // At least 3 wide, for the dashes of the alignment row
+        let widths: Vec<usize> = (0..self.col_size())
+            .map(|c| lines.iter().map(|cells| cells[c].chars().count()).max().unwrap_or(0).max(3))
+            .collect();
+
+        let write_line = |writer: &mut W, cells: &[String]| -> io::Result<()> {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(widths.iter().zip(&right))
+                .map(|(cell, (width, right))| {
+                    if *right { format!("{:>width$}", cell, width = width) } else { format!("{:<width$}", cell, width = width) }
+                })
+                .collect();
+            writeln!(writer, "| {} |", cells.join(" | "))
+        };
+        write_line(&mut writer, &lines[0])?;
+        let rule: Vec<String> = widths
+            .iter()
+            .zip(&right)
+            .map(|(width, right)| if *right { format!("{}:", "-".repeat(width - 1)) } else { "-".repeat(*width) })
+            .collect();
+        writeln!(writer, "| {} |", rule.join(" | "))?;
+        for cells in &lines[1..] {
+            write_line(&mut writer, cells)?;
+        }
+        writer.flush()
+    }
+
+    /// Write the table as tab-separated values, a row per line
+    pub fn write_tsv<W: Write>(&mut self, mut writer: W) -> io::Result<()> {
+        for (_, values) in self.iter_rows() {
+            let cells: Vec<String> = values.iter().map(|v| tsv_cell(v)).collect();
+            writeln!(writer, "{}", cells.join("\t"))?;
+        }
+        writer.flush()
+    }
+}
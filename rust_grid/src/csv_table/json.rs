@@ -0,0 +1,122 @@
+use super::column_type::ColumnType;
+use super::csv_table::CSVTable;
+use serde_json::{Map, Number, Value};
+use std::io::{self, Read, Write};
+
+// --------- JSON layouts ----------
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)]
+pub enum JsonLayout {
+    /// `[{"Date": "2024-01-31", "Amount": 120}, ...]`, keyed by the first row
+    #[default]
+    Objects,
+    /// `[["Date", "Amount"], ["2024-01-31", 120], ...]`, every row as it is
+    Arrays,
+}
+
+/// A cell as JSON: numbers and booleans of typed columns keep their type, and empty
+/// typed cells become null. Everything else is a string.
+fn cell_to_json(value: &str, column_type: ColumnType) -> Value {
+    let trimmed = value.trim();
+    if trimmed.is_empty() && column_type != ColumnType::Text {
+        return Value::Null;
+    }
+    let typed = match column_type {
+        ColumnType::Int => trimmed.parse::<i64>().ok().map(Value::from),
+        ColumnType::Float => trimmed.parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number),
+        ColumnType::Bool if trimmed.eq_ignore_ascii_case("true") => Some(Value::Bool(true)),
+        ColumnType::Bool if trimmed.eq_ignore_ascii_case("false") => Some(Value::Bool(false)),
+        ColumnType::Bool => None,
+        ColumnType::Text | ColumnType::Date => None,
+    };
+    typed.unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+fn json_to_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl CSVTable {
+    pub fn write_json<W: Write>(&mut self, mut writer: W, layout: JsonLayout) -> io::Result<()> {
+        let types: Vec<ColumnType> = (0..self.col_size()).map(|c| self.column_type(c)).collect();
+        let row_json = |csv: &mut CSVTable, r: usize| -> Vec<Value> {
+            types.iter().enumerate().map(|(c, t)| cell_to_json(csv.read_cell(r, c), *t)).collect()
+        };
+
+        let rows: Vec<Value> = match layout {
+            JsonLayout::Arrays => (0..self.row_size()).map(|r| Value::Array(row_json(self, r))).collect(),
+            JsonLayout::Objects => {
+                if self.row_size() == 0 {
+                    Vec::new()
+                } else {
+                    let keys = self.json_keys();
+                    (1..self.row_size())
+                        .map(|r| Value::Object(keys.iter().cloned().zip(row_json(self, r)).collect::<Map<_, _>>()))
+                        .collect()
+                }
+            }
+        };
+        serde_json::to_writer_pretty(&mut writer, &Value::Array(rows))?;
+        writeln!(writer)
+    }
+
+    /// Object keys from the first row; blank headers are named after their column and
+    /// repeated ones get a suffix, so that no cell is lost.
+    fn json_keys(&mut self) -> Vec<String> {
+        let mut keys: Vec<String> = Vec::with_capacity(self.col_size());
+        for c in 0..self.col_size() {
+            let header = self.read_cell(0, c).trim();
+            let base = if header.is_empty() { format!("column {}", c) } else { header.to_string() };
+            let mut key = base.clone();
+            let mut n = 2;
+            while keys.contains(&key) {
+                key = format!("{}_{}", base, n);
+                n += 1;
+            }
+            keys.push(key);
+        }
+        keys
+    }
+
+    /// Replace the table with a JSON array of objects (their keys, in order of first
+    /// appearance, become the first row) or of arrays. Clears the history like `read_csv`.
+    pub fn read_json<R: Read>(&mut self, reader: R) -> io::Result<()> {
+        let Value::Array(items) = serde_json::from_reader(reader)? else {
+            return Err(invalid("expected a JSON array"));
+        };
+
+        let records: Vec<Vec<String>> = if items.iter().all(Value::is_object) && !items.is_empty() {
+            let mut keys: Vec<&String> = Vec::new();
+            for item in &items {
+                for key in item.as_object().unwrap().keys() {
+                    if !keys.contains(&key) {
+                        keys.push(key);
+                    }
+                }
+            }
+            let mut records = vec![keys.iter().map(|k| k.to_string()).collect()];
+            for item in &items {
+                let object = item.as_object().unwrap();
+                records.push(keys.iter().map(|k| object.get(*k).map(json_to_cell).unwrap_or_default()).collect());
+            }
+            records
+        } else if items.iter().all(Value::is_array) {
+            items
+                .iter()
+                .map(|item| item.as_array().unwrap().iter().map(json_to_cell).collect())
+                .collect()
+        } else {
+            return Err(invalid("expected an array of objects or an array of arrays"));
+        };
+
+        self.load_records(records.into_iter().map(Ok))
+    }
+}
@@ -1,2 +1,17 @@
+pub mod column_type;
+pub use column_type::ColumnType;
 pub mod csv_table;
-pub use csv_table::CSVTable;
+pub mod diff;
+pub mod edits;
+pub mod export;
+pub use edits::CellEdit;
+pub mod json;
+pub mod ranges;
+pub mod render;
+pub use render::PrintOptions;
+pub use json::JsonLayout;
+mod series;
+pub mod stats;
+pub use csv_table::{CSVTable, FindOptions, SortOptions};
+pub mod paged;
+pub use paged::PagedCsvTable;
@@ -0,0 +1,328 @@
+use crate::tools::csv_read::{ByteRecord, CsvDialect, CsvReader, CsvWriter, FlexPolicy, RecordParser};
+use crate::tools::history::{CompositeMementoTrait, History, TargetMementoTrait};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::Path;
+
+const PAGE_ROWS: usize = 1024;
+const DEFAULT_CACHED_PAGES: usize = 64;
+
+// --------- History for paged edits ----------
+/// Cell values to restore, None for "as in the file"
+#[derive(Debug, Clone, Default)]
+struct PagedMemento {
+    cells: Vec<(usize, usize, Option<String>)>,
+}
+
+// --------- CSV file browsed and edited in pages ---------
+/// A CSV file that is never loaded as a whole: opening it only records where every
+/// page of rows starts, pages are parsed when one of their cells is read, and only
+/// the most recently used ones are kept. Edits stay in memory until `write_csv`
+/// streams the file through with them applied. Rows and columns cannot be inserted
+/// or deleted, load the file in a `CSVTable` for that.
+#[derive(Debug)]
+pub struct PagedCsvTable {
+    file: BufReader<File>,
+    dialect: CsvDialect,
+    page_offsets: Vec<u64>, // byte offset of the first row of every page
+    row_count: usize,
+    col_count: usize,
+    pages: HashMap<usize, Vec<Vec<String>>>,
+    recent_pages: VecDeque<usize>, // least recently used first
+    max_pages: usize,
+    edits: HashMap<(usize, usize), String>, // changed cells, by (row, col)
+    history: History<PagedMemento>,
+}
+
+impl PagedCsvTable {
+    /// Scan the file once to index its rows; nothing of it is kept in memory
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::open_with(path, CsvDialect::default())
+    }
+
+    /// Open a file written in `dialect`, which is also used to write it back
+    #[allow(dead_code)]
+    pub fn open_with<P: AsRef<Path>>(path: P, dialect: CsvDialect) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let (page_offsets, row_count, col_count) = index_rows(&mut file, dialect)?;
+        Ok(Self {
+            file,
+            dialect,
+            page_offsets,
+            row_count,
+            col_count,
+            pages: HashMap::new(),
+            recent_pages: VecDeque::new(),
+            max_pages: DEFAULT_CACHED_PAGES,
+            edits: HashMap::new(),
+            history: History::new(),
+        })
+    }
+
+    /// Keep at most `pages` pages of 1024 rows parsed at a time (at least one)
+    #[allow(dead_code)]
+    pub fn set_cached_pages(&mut self, pages: usize) {
+        self.max_pages = pages.max(1);
+        while self.recent_pages.len() > self.max_pages {
+            let evicted = self.recent_pages.pop_front().unwrap();
+            self.pages.remove(&evicted);
+        }
+    }
+
+    pub fn row_size(&self) -> usize {
+        self.row_count
+    }
+
+    pub fn col_size(&self) -> usize {
+        self.col_count
+    }
+
+    pub fn has_cell(&self, row_index: usize, col_index: usize) -> bool {
+        row_index < self.row_count && col_index < self.col_count
+    }
+
+    /// Number of cells changed since the file was opened
+    #[allow(dead_code)]
+    pub fn edited_cells(&self) -> usize {
+        self.edits.len()
+    }
+
+    pub fn read_cell(&mut self, row_index: usize, col_index: usize) -> io::Result<&str> {
+        if !self.has_cell(row_index, col_index) {
+            panic!("cell index parameter out of bound");
+        }
+        if self.edits.contains_key(&(row_index, col_index)) {
+            return Ok(&self.edits[&(row_index, col_index)]);
+        }
+        let page = self.load_page(row_index / PAGE_ROWS)?;
+        match page.get(row_index % PAGE_ROWS) {
+            Some(row) => Ok(&row[col_index]),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("row {} is no longer in the file", row_index),
+            )),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn write_cell(&mut self, row_index: usize, col_index: usize, value: &str) -> io::Result<()> {
+        // Make sure the page is readable before anything is changed
+        self.read_cell(row_index, col_index)?;
+        let previous = self.edits.insert((row_index, col_index), value.to_string());
+        self.history.record_labeled(
+            PagedMemento {
+                cells: vec![(row_index, col_index, previous)],
+            },
+            format!("Write ({}, {})", row_index, col_index),
+        );
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn undo(&mut self) {
+        let mut history = mem::take(&mut self.history);
+        history.undo(self);
+        self.history = history;
+    }
+
+    #[allow(dead_code)]
+    pub fn redo(&mut self) {
+        let mut history = mem::take(&mut self.history);
+        history.redo(self);
+        self.history = history;
+    }
+
+    #[allow(dead_code)]
+    pub fn undoable(&mut self) -> bool {
+        self.history.undoable()
+    }
+
+    #[allow(dead_code)]
+    pub fn redoable(&mut self) -> bool {
+        self.history.redoable()
+    }
+
+    /// Stream the file, with the edits applied, to `writer`, which must not be the
+    /// file itself: write to a temporary file and rename it over the original instead.
+    #[allow(dead_code)]
+    pub fn write_csv<W: Write>(&mut self, writer: W) -> io::Result<()> {
+        let mut csv = CsvWriter::with_dialect(writer, self.dialect);
+        self.file.seek(SeekFrom::Start(0))?;
+        for (row_index, value) in CsvReader::with_dialect(&mut self.file, self.dialect).enumerate() {
+            let mut record = value?;
+            record.resize(self.col_count, String::new());
+            if !self.edits.is_empty() {
+                for (col_index, field) in record.iter_mut().enumerate() {
+                    if let Some(edited) = self.edits.get(&(row_index, col_index)) {
+                        field.clone_from(edited);
+                    }
+                }
+            }
+            csv.write_record(&record)?;
+        }
+        csv.flush()
+    }
+
+    fn load_page(&mut self, page_index: usize) -> io::Result<&Vec<Vec<String>>> {
+        if self.pages.contains_key(&page_index) {
+            self.recent_pages.retain(|p| *p != page_index);
+        } else {
+            self.file.seek(SeekFrom::Start(self.page_offsets[page_index]))?;
+            let mut rows = Vec::with_capacity(PAGE_ROWS);
+            // The index has checked the widths already, from the first row on
+            let dialect = self.dialect.flex(FlexPolicy::Flexible);
+            for value in CsvReader::with_dialect(&mut self.file, dialect).take(PAGE_ROWS) {
+                let mut record = value?;
+                record.resize(self.col_count, String::new());
+                rows.push(record);
+            }
+            if self.recent_pages.len() == self.max_pages {
+                let evicted = self.recent_pages.pop_front().unwrap();
+                self.pages.remove(&evicted);
+            }
+            self.pages.insert(page_index, rows);
+        }
+        self.recent_pages.push_back(page_index);
+        Ok(&self.pages[&page_index])
+    }
+}
+
+impl CompositeMementoTrait for PagedMemento {
+    fn composite(mementos: Vec<Self>) -> Self {
+        // Cells are restored last to first, so the newest memento's come last
+        PagedMemento {
+            cells: mementos.into_iter().flat_map(|memento| memento.cells).collect(),
+        }
+    }
+}
+
+impl TargetMementoTrait<PagedMemento> for PagedCsvTable {
+    fn apply_memento(&mut self, memento: &PagedMemento) -> PagedMemento {
+        // Restored last to first, so the inverse, restored the same way, ends with the last
+        let mut inverse_cells = Vec::with_capacity(memento.cells.len());
+        for (row_index, col_index, value) in memento.cells.iter().rev() {
+            let key = (*row_index, *col_index);
+            let previous = match value {
+                Some(value) => self.edits.insert(key, value.clone()),
+                None => self.edits.remove(&key),
+            };
+            inverse_cells.push((*row_index, *col_index, previous));
+        }
+        PagedMemento {
+            cells: inverse_cells,
+        }
+    }
+}
+
+/// Read through the file with the parser `CsvReader` uses, returning the offsets of
+/// every `PAGE_ROWS`th row, the number of rows and the widest row's number of fields.
+fn index_rows<R: BufRead + Seek>(reader: &mut R, dialect: CsvDialect) -> io::Result<(Vec<u64>, usize, usize)> {
+    let mut page_offsets = Vec::new();
+    let mut row_count = 0usize;
+    let mut col_count = 0usize;
+
+    let mut parser = RecordParser::new(dialect);
+    let mut record = ByteRecord::new();
+    let mut offset = 0u64; // where the next row starts, or the lines skipped before it
+    while parser.start_record() {
+        let row_offset = offset;
+        let ended = loop {
+            let buf = reader.fill_buf().map_err(|e| parser.io_error(e))?;
+            if buf.is_empty() {
+                break parser.finish()?;
+            }
+            let (used, ended) = parser.feed(buf);
+            reader.consume(used);
+            offset += used as u64;
+            if ended {
+                break true;
+            }
+        };
+        if !ended {
+            break;
+        }
+        parser.take_record(&mut record)?;
+        if row_count.is_multiple_of(PAGE_ROWS) {
+            page_offsets.push(row_offset);
+        }
+        col_count = col_count.max(record.len());
+        row_count += 1;
+    }
+    reader.seek(SeekFrom::Start(0))?;
+    Ok((page_offsets, row_count, col_count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// `contents` written to a file of its own in the temporary directory
+    fn file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rust_grid_paged_{}_{}.csv", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn rows(paged: &mut PagedCsvTable) -> Vec<Vec<String>> {
+        (0..paged.row_size())
+            .map(|r| (0..paged.col_size()).map(|c| paged.read_cell(r, c).unwrap().to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn bom_only_file_has_no_rows() {
+        let path = file("bom", b"\xEF\xBB\xBF");
+        let paged = PagedCsvTable::open(&path).unwrap();
+        assert_eq!(paged.row_size(), 0);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rows_are_counted_as_the_reader_splits_them() {
+        // A quoted empty field without a line break after it is no row to CsvReader
+        let path = file("quoted_end", b"\xEF\xBB\xBFa,b\n\"x\ny\",c\n\"\"");
+        let mut paged = PagedCsvTable::open(&path).unwrap();
+        assert_eq!(rows(&mut paged), vec![vec!["a", "b"], vec!["x\ny", "c"]]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn dialect_is_used_to_read_and_write() {
+        let path = file("dialect", b"# notes\na;b\n1;\"2;3\"\n");
+        let dialect = CsvDialect::default().delimiter(b';').comment(Some(b'#'));
+        let mut paged = PagedCsvTable::open_with(&path, dialect).unwrap();
+        assert_eq!(rows(&mut paged), vec![vec!["a", "b"], vec!["1", "2;3"]]);
+        paged.write_cell(1, 0, "4").unwrap();
+        let mut written = Vec::new();
+        paged.write_csv(&mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), "a;b\n4;\"2;3\"\n");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rows_gone_from_the_file_are_an_error() {
+        let path = file("shrunk", b"a\nb\nc\n");
+        let mut paged = PagedCsvTable::open(&path).unwrap();
+        std::fs::write(&path, b"a\n").unwrap();
+        assert!(paged.read_cell(2, 0).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn composite_edits_redo_to_the_last_value() {
+        let path = file("composite", b"a\n");
+        let mut paged = PagedCsvTable::open(&path).unwrap();
+        paged.history.begin_transaction();
+        paged.write_cell(0, 0, "x").unwrap();
+        paged.write_cell(0, 0, "y").unwrap();
+        paged.history.commit();
+        paged.undo();
+        assert_eq!(paged.read_cell(0, 0).unwrap(), "a");
+        paged.redo();
+        assert_eq!(paged.read_cell(0, 0).unwrap(), "y");
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use super::csv_table::CSVTable;
+use crate::tools::csv_read::{CsvReader, CsvWriter};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+// --------- Named ranges ----------
+/// A rectangle of cells, corners included, with r1 <= r2 and c1 <= c2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellRange {
+    pub r1: usize,
+    pub c1: usize,
+    pub r2: usize,
+    pub c2: usize,
+}
+
+impl CellRange {
+    /// The range between two opposite corners, in any order
+    pub fn new(r1: usize, c1: usize, r2: usize, c2: usize) -> Self {
+        Self {
+            r1: r1.min(r2),
+            c1: c1.min(c2),
+            r2: r1.max(r2),
+            c2: c1.max(c2),
+        }
+    }
+
+    /// (row, col) of every cell, row by row
+    #[allow(dead_code)]
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.r1..=self.r2).flat_map(move |r| (self.c1..=self.c2).map(move |c| (r, c)))
+    }
+}
+
+impl fmt::Display for CellRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})..({}, {})", self.r1, self.c1, self.r2, self.c2)
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Named ranges refer to cells by index and do not follow rows or columns that are
+/// inserted, deleted or moved; loading a file drops them.
+impl CSVTable {
+    /// Name the rectangle between two corners, replacing and returning any range of
+    /// that name. Panics if a corner is outside the table.
+    pub fn define_range(&mut self, name: &str, r1: usize, c1: usize, r2: usize, c2: usize) -> Option<CellRange> {
+        if !self.has_cell(r1, c1) || !self.has_cell(r2, c2) {
+            panic!("range corner parameter out of bound");
+        }
+        self.named_ranges.insert(name.to_string(), CellRange::new(r1, c1, r2, c2))
+    }
+
+    pub fn range(&self, name: &str) -> Option<CellRange> {
+        self.named_ranges.get(name).copied()
+    }
+
+    pub fn remove_range(&mut self, name: &str) -> Option<CellRange> {
+        self.named_ranges.remove(name)
+    }
+
+    /// All named ranges, sorted by name
+    pub fn ranges(&self) -> Vec<(String, CellRange)> {
+        self.named_ranges.iter().map(|(name, range)| (name.clone(), *range)).collect()
+    }
+
+    /// Values of the named range row by row, None if there is no such range. Cells
+    /// of a range that no longer fits the table are left out.
+    pub fn read_range(&mut self, name: &str) -> Option<Vec<Vec<String>>> {
+        let range = self.range(name)?;
+        let rows_end = (range.r2 + 1).min(self.row_size());
+        let cols_end = (range.c2 + 1).min(self.col_size());
+        let mut rows = Vec::new();
+        for r in range.r1..rows_end {
+            rows.push((range.c1..cols_end).map(|c| self.read_cell(r, c).to_string()).collect());
+        }
+        Some(rows)
+    }
+
+    /// Write the named ranges as CSV records `name,r1,c1,r2,c2`, the format of the
+    /// sidecar file saved next to the table
+    pub fn write_ranges<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut csv = CsvWriter::new(writer);
+        for (name, range) in &self.named_ranges {
+            let corners = [range.r1, range.c1, range.r2, range.c2];
+            let mut record = vec![name.clone()];
+            record.extend(corners.iter().map(|i| i.to_string()));
+            csv.write_record(&record)?;
+        }
+        csv.flush()
+    }
+
+    /// Replace the named ranges with those written by `write_ranges`. Ranges are not
+    /// checked against the table, which may have been edited by another program.
+    pub fn read_ranges<R: BufRead>(&mut self, reader: R) -> io::Result<()> {
+        let mut ranges = Vec::new();
+        let mut csv = CsvReader::new(reader);
+        while let Some(value) = csv.next() {
+            let record = value?;
+            let corners: Vec<usize> = record.iter().skip(1).filter_map(|v| v.trim().parse().ok()).collect();
+            match (record.first(), &corners[..]) {
+                (Some(name), &[r1, c1, r2, c2]) if record.len() == 5 => {
+                    ranges.push((name.clone(), CellRange::new(r1, c1, r2, c2)))
+                }
+                _ => return Err(invalid(format!("line {}: expected name,r1,c1,r2,c2", csv.line()))),
+            }
+        }
+        self.named_ranges = ranges.into_iter().collect();
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use super::csv_table::CSVTable;
+use std::io::{self, Write};
+use std::ops::Range;
+
+// --------- Aligned printing ----------
+#[derive(Debug, Clone, Default)]
+pub struct PrintOptions {
+    pub header: bool,               // label columns with the first row instead of letters
+    pub max_width: Option<usize>,   // cut longer cells, ending them with '…'
+    pub rows: Option<Range<usize>>, // a page of the shown rows, by position among them
+    pub row_indices: Option<Range<usize>>, // only rows with these indices, as numbered
+    pub cols: Option<Range<usize>>,        // only these columns
+}
+
+/// "A", "B", ..., "Z", "AA", "AB", ...
+pub fn column_letters(col_index: usize) -> String {
+    let mut letters = Vec::new();
+    let mut n = col_index + 1;
+    while n > 0 {
+        letters.push(b'A' + ((n - 1) % 26) as u8);
+        n = (n - 1) / 26;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap()
+}
+
+/// A cell on one line, line breaks shown as "\n"
+pub fn display_cell(value: &str, max_width: Option<usize>) -> String {
+    let value = value.replace('\r', "").replace('\n', "\\n");
+    match max_width {
+        Some(width) if value.chars().count() > width => {
+            let mut cut: String = value.chars().take(width.saturating_sub(1)).collect();
+            cut.push('…');
+            cut
+        }
+        _ => value,
+    }
+}
+
+impl CSVTable {
+    /// Write the rows passing the filter as an aligned grid with row numbers and
+    /// column labels. With `options.rows` only that page is written, followed by
+    /// "(rows X..Y of N)". `options.row_indices` and `options.cols` cut out a window.
+    pub fn render<W: Write>(&mut self, mut writer: W, options: &PrintOptions) -> io::Result<()> {
+        let col_count = self.col_size();
+        let cols: Vec<usize> = match &options.cols {
+            Some(cols) => (cols.start..cols.end.min(col_count)).collect(),
+            None => (0..col_count).collect(),
+        };
+        if cols.is_empty() {
+            return Ok(());
+        }
+        let mut rows = self.visible_rows();
+        let labels: Vec<String> = if options.header && self.has_row(0) {
+            rows.retain(|row_index| *row_index != 0);
+            let values = self.read_row(0);
+            cols.iter().map(|c| display_cell(values[*c], options.max_width)).collect()
+        } else {
+            cols.iter().map(|c| column_letters(*c)).collect()
+        };
+        if let Some(row_indices) = &options.row_indices {
+            rows.retain(|row_index| row_indices.contains(row_index));
+        }
+
+        let total = rows.len();
+        let page = options.rows.as_ref().map(|rows| {
+            let start = rows.start.min(total);
+            start..rows.end.clamp(start, total)
+        });
+        if let Some(page) = &page {
+            rows = rows[page.clone()].to_vec();
+        }
+
+        let lines: Vec<(String, Vec<String>)> = rows
+            .iter()
+            .map(|row_index| {
+                let values = self.read_row(*row_index);
+                (row_index.to_string(), cols.iter().map(|c| display_cell(values[*c], options.max_width)).collect())
+            })
+            .collect();
+        let index_width = lines.iter().map(|(index, _)| index.len()).max().unwrap_or(1);
+        let widths: Vec<usize> = (0..cols.len())
+            .map(|c| {
+                let cells = lines.iter().map(|(_, cells)| cells[c].chars().count());
+                cells.chain([labels[c].chars().count()]).max().unwrap()
+            })
+            .collect();
+
+        let write_line = |writer: &mut W, index: &str, cells: &[String]| -> io::Result<()> {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            let line = format!("{:>width$} | {}", index, cells.join(" | "), width = index_width);
+            writeln!(writer, "{}", line.trim_end())
+        };
+        write_line(&mut writer, "", &labels)?;
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        writeln!(writer, "{}-+-{}", "-".repeat(index_width), rule.join("-+-"))?;
+        for (index, cells) in &lines {
+            write_line(&mut writer, index, cells)?;
+        }
+        if let Some(page) = page {
+            writeln!(writer, "(rows {}..{} of {})", page.start, page.end, total)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use super::column_type::{parse_date, CellTypeError};
+use std::fmt;
+
+// --------- Autofill series ----------
+/// Why `CSVTable::fill_series` wrote nothing
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeriesError {
+    /// The series of the line starting at (`row`, `col`) goes past the largest integer
+    Overflow { row: usize, col: usize },
+    Type(CellTypeError),
+}
+
+impl From<CellTypeError> for SeriesError {
+    fn from(source: CellTypeError) -> Self {
+        SeriesError::Type(source)
+    }
+}
+
+impl fmt::Display for SeriesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeriesError::Overflow { row, col } => write!(f, "the series from cell ({}, {}) overflows", row, col),
+            SeriesError::Type(source) => write!(f, "{}", source),
+        }
+    }
+}
+
+impl std::error::Error for SeriesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SeriesError::Overflow { .. } => None,
+            SeriesError::Type(source) => Some(source),
+        }
+    }
+}
+
+/// Values for `count` cells continuing from `seed`, the first one or two cells of the
+/// fill. A second seed of the same kind sets the step, otherwise numbers and dates count
+/// up by one. Integers, decimals, `YYYY-MM-DD` dates and text ending in digits ("Invoice 9")
+/// are incremented; anything else is repeated. None if an integer would overflow.
+pub(super) fn series(seed: &[&str], count: usize) -> Option<Vec<String>> {
+    let first = seed.first().map_or("", |s| s.trim());
+    let second = seed.get(1).map(|s| s.trim()).filter(|s| !s.is_empty());
+
+    let second_is_int = second.is_none_or(|s| s.parse::<i64>().is_ok());
+    if let (Ok(a), true) = (first.parse::<i64>(), second_is_int) {
+        let step = match second.and_then(|s| s.parse::<i64>().ok()) {
+            Some(b) => b.checked_sub(a)?,
+            None => 1,
+        };
+        return Some(count_from(a, step, count)?.iter().map(|x| x.to_string()).collect());
+    }
+    if let Some(a) = first.parse::<f64>().ok().filter(|x| x.is_finite()) {
+        let (step, decimals) = match second.map(|s| (s.parse::<f64>(), s)) {
+            Some((Ok(b), text)) => (b - a, decimals(first).max(decimals(text))),
+            _ => (1.0, decimals(first)),
+        };
+        return Some(
+            (0..count)
+                .map(|i| format!("{:.*}", decimals, a + step * i as f64))
+                .collect(),
+        );
+    }
+    if let Some((y, m, d)) = parse_date(first) {
+        let a = days_from_civil(y, m, d);
+        let step = match second.and_then(parse_date) {
+            Some((y, m, d)) => days_from_civil(y, m, d).checked_sub(a)?,
+            None => 1,
+        };
+        return Some(
+            count_from(a, step, count)?
+                .into_iter()
+                .map(|days| {
+                    let (y, m, d) = civil_from_days(days);
+                    format!("{:04}-{:02}-{:02}", y, m, d)
+                })
+                .collect(),
+        );
+    }
+    if let Some((prefix, a, width)) = split_trailing_number(first) {
+        let step = match second.and_then(split_trailing_number) {
+            Some((other_prefix, b, _)) if other_prefix == prefix => b.checked_sub(a)?,
+            _ => 1,
+        };
+        return Some(
+            count_from(a, step, count)?
+                .into_iter()
+                .map(|x| format!("{}{:0width$}", prefix, x, width = width))
+                .collect(),
+        );
+    }
+    Some(
+        (0..count)
+            .map(|i| seed.get(i % seed.len().max(1)).copied().unwrap_or("").to_string())
+            .collect(),
+    )
+}
+
+/// `a`, `a + step`, `a + 2 * step`... `count` of them, None if one overflows
+fn count_from(a: i64, step: i64, count: usize) -> Option<Vec<i64>> {
+    (0..count)
+        .map(|i| step.checked_mul(i64::try_from(i).ok()?)?.checked_add(a))
+        .collect()
+}
+
+fn decimals(number: &str) -> usize {
+    number.split_once('.').map_or(0, |(_, fraction)| fraction.len())
+}
+
+/// "Invoice 009" -> ("Invoice ", 9, 3)
+fn split_trailing_number(text: &str) -> Option<(&str, i64, usize)> {
+    let digits = text.len() - text.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 || digits == text.len() {
+        return None;
+    }
+    let (prefix, number) = text.split_at(text.len() - digits);
+    Some((prefix, number.parse().ok()?, digits))
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (m as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
@@ -0,0 +1,74 @@
+use super::csv_table::CSVTable;
+use std::collections::HashSet;
+use std::fmt;
+
+// --------- Column statistics ----------
+/// Summary of one column. Cells parsing as a (finite) number make up the numeric
+/// part, so a header or a stray note does not hide the total of an amounts column.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ColStats {
+    pub count: usize,    // non-empty cells
+    pub empty: usize,    // empty or blank cells
+    pub distinct: usize, // different non-empty values
+    pub numeric: usize,  // cells that are numbers
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub sum: Option<f64>,
+    pub mean: Option<f64>,
+}
+
+impl fmt::Display for ColStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "count {}, empty {}, distinct {}, numeric {}",
+            self.count, self.empty, self.distinct, self.numeric
+        )?;
+        if let (Some(min), Some(max), Some(sum), Some(mean)) = (self.min, self.max, self.sum, self.mean) {
+            write!(f, ", min {}, max {}, sum {}, mean {}", min, max, sum, mean)?;
+        }
+        Ok(())
+    }
+}
+
+fn summarize(values: Vec<String>) -> ColStats {
+    let mut stats = ColStats::default();
+    let mut distinct = HashSet::new();
+    let mut numbers = Vec::new();
+    for value in values {
+        let value = value.trim();
+        if value.is_empty() {
+            stats.empty += 1;
+            continue;
+        }
+        stats.count += 1;
+        if let Some(number) = value.parse::<f64>().ok().filter(|x| x.is_finite()) {
+            numbers.push(number);
+        }
+        distinct.insert(value.to_string());
+    }
+    stats.distinct = distinct.len();
+    stats.numeric = numbers.len();
+    if !numbers.is_empty() {
+        let sum: f64 = numbers.iter().sum();
+        stats.min = numbers.iter().copied().reduce(f64::min);
+        stats.max = numbers.iter().copied().reduce(f64::max);
+        stats.sum = Some(sum);
+        stats.mean = Some(sum / numbers.len() as f64);
+    }
+    stats
+}
+
+impl CSVTable {
+    /// Statistics over every row of the column, filtered out or not
+    pub fn column_stats(&mut self, col_index: usize) -> ColStats {
+        let values = self.read_col(col_index).into_iter().map(String::from).collect();
+        summarize(values)
+    }
+
+    /// Statistics over the cells of a named range, None if there is no such range
+    pub fn range_stats(&mut self, name: &str) -> Option<ColStats> {
+        let values = self.read_range(name)?.into_iter().flatten().collect();
+        Some(summarize(values))
+    }
+}
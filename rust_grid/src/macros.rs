@@ -0,0 +1,118 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::Rc;
+
+/// How deep `macro play` may go in macros that play macros, so that one playing
+/// itself ends
+pub const MAX_DEPTH: usize = 8;
+
+/// A command of a macro being played
+#[derive(Debug)]
+pub struct MacroStep {
+    pub line: String,
+    pub repetition: usize, // counted from 0, for {n}
+    pub depth: usize,      // 1 for a macro played from the prompt, 2 for one it plays...
+}
+
+/// A macro being played, queued one step at a time so that a large count takes
+/// no more memory than a small one
+#[derive(Debug)]
+pub struct MacroRun {
+    lines: Rc<[String]>,
+    next: usize,       // of `lines`, the one to run next
+    repetition: usize, // counted from 0
+    count: usize,
+    depth: usize,
+}
+
+/// Commands recorded with `macro record`, by name, and the macros being played,
+/// the innermost first
+#[derive(Debug, Default)]
+pub struct Macros {
+    pub saved: BTreeMap<String, Vec<String>>,
+    pub recording: Option<(String, Vec<String>)>,
+    pub queue: VecDeque<MacroRun>,
+}
+
+impl Macros {
+    /// Run the commands of `lines` `count` times, before any others left to run
+    pub fn play(&mut self, lines: &[String], count: usize, depth: usize) {
+        if lines.is_empty() || count == 0 {
+            return;
+        }
+        self.queue.push_front(MacroRun {
+            lines: lines.into(),
+            next: 0,
+            repetition: 0,
+            count,
+            depth,
+        });
+    }
+
+    /// The next command to run of the macros being played, if any
+    pub fn next_step(&mut self) -> Option<MacroStep> {
+        let run = self.queue.front_mut()?;
+        let step = MacroStep {
+            line: run.lines[run.next].clone(),
+            repetition: run.repetition,
+            depth: run.depth,
+        };
+        run.next += 1;
+        if run.next == run.lines.len() {
+            run.next = 0;
+            run.repetition += 1;
+            if run.repetition == run.count {
+                self.queue.pop_front();
+            }
+        }
+        Some(step)
+    }
+}
+
+/// Whether `line` controls the recording rather than being part of it. A nested
+/// `macro play` is recorded as such.
+pub fn is_control(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    words.next() == Some("macro") && words.next() != Some("play")
+}
+
+/// `line` with {row} and {col} replaced by the cursor's place, and {n} by the
+/// repetition
+pub fn expand(line: &str, repetition: usize, cursor: (usize, usize)) -> String {
+    line.replace("{row}", &cursor.0.to_string())
+        .replace("{col}", &cursor.1.to_string())
+        .replace("{n}", &repetition.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn plays_one_step_at_a_time() {
+        let mut macros = Macros::default();
+        macros.play(&lines(&["a", "b"]), usize::MAX, 1);
+        assert_eq!(macros.queue.len(), 1);
+        let steps: Vec<(String, usize)> = (0..5)
+            .map(|_| macros.next_step().map(|step| (step.line, step.repetition)).unwrap())
+            .collect();
+        assert_eq!(steps, [("a".into(), 0), ("b".into(), 0), ("a".into(), 1), ("b".into(), 1), ("a".into(), 2)]);
+    }
+
+    #[test]
+    fn nested_macro_runs_before_the_rest() {
+        let mut macros = Macros::default();
+        macros.play(&lines(&["play", "after"]), 1, 1);
+        assert_eq!(macros.next_step().unwrap().line, "play");
+        macros.play(&lines(&["inner"]), 2, 2);
+        macros.play(&[], 5, 2);
+        let rest: Vec<(String, usize)> = std::iter::from_fn(|| macros.next_step())
+            .map(|step| (step.line, step.depth))
+            .collect();
+        assert_eq!(rest, [("inner".into(), 2), ("inner".into(), 2), ("after".into(), 1)]);
+        assert!(macros.queue.is_empty());
+    }
+}
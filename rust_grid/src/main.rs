@@ -1,48 +1,507 @@
 mod tools;       // <- THIS IS MANDATORY
 mod csv_table;    // <- optional if you have csv_table as a module
+mod macros;
+mod prompt;
+#[cfg(feature = "tui")]
+mod tui;
 
 
-use crate::csv_table::CSVTable;
-use std::io::{self, Write};
+use crate::csv_table::{CSVTable, CellEdit, ColumnType, FindOptions, JsonLayout, PagedCsvTable, PrintOptions, SortOptions};
+use crate::macros::Macros;
+use crate::prompt::Commands;
+use crate::tools::{CsvDialect, FlexPolicy, QuoteStyle, Terminator};
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime};
 
-#[derive(Debug)]
+/// Print why a command failed, which stops a script
+macro_rules! problem {
+    ($state:expr, $($arg:tt)*) => {{
+        println!("PROBLEM: {}", format_args!($($arg)*));
+        $state.failed = true;
+    }};
+}
+
+#[derive(Debug, Default)]
 struct SessionState {
     dirty: bool,                      // unsaved changes
     path: Option<std::path::PathBuf>, // None = never saved / untitled
+    last_found: Option<(usize, usize)>, // where find_next continues from
+    dialect: CsvDialect,                // of the loaded file, used again to save it
+    failed: bool,                       // the last command failed
+    readonly: Option<std::path::PathBuf>, // opened with --readonly, so never saved over
+    filter: Option<String>,               // the row filter set, shown in the prompt
+    cursor: (usize, usize),               // the cell of goto, set and the moves
+}
+
+/// A file open in the session, with its own table, undo history and unsaved changes
+struct Buffer {
+    csv: CSVTable,
+    state: SessionState,
+}
+
+impl Buffer {
+    fn new(csv: CSVTable) -> Self {
+        Buffer {
+            csv,
+            state: SessionState::default(),
+        }
+    }
+}
+
+/// The buffers open in the session. The current one is worked on as `csv` and
+/// `state`, while its place in `list` holds an empty buffer.
+struct Buffers {
+    list: Vec<Buffer>,
+    current: usize,
+}
+
+impl Buffers {
+    /// Make buffer `index` the current one
+    fn switch(&mut self, index: usize, csv: &mut CSVTable, state: &mut SessionState) {
+        let failed = state.failed; // of the command, not the buffer
+        let current = &mut self.list[self.current];
+        std::mem::swap(csv, &mut current.csv);
+        std::mem::swap(state, &mut current.state);
+        let next = &mut self.list[index];
+        std::mem::swap(csv, &mut next.csv);
+        std::mem::swap(state, &mut next.state);
+        self.current = index;
+        state.failed = failed;
+    }
+
+    /// Add an empty buffer after the others and make it the current one
+    fn open(&mut self, csv: &mut CSVTable, state: &mut SessionState) {
+        self.list.push(Buffer::new(new_table()));
+        self.switch(self.list.len() - 1, csv, state);
+    }
+
+    /// Drop the current buffer and make the one before it current, or an empty
+    /// one if it was the only one
+    fn close(&mut self, csv: &mut CSVTable, state: &mut SessionState) {
+        let failed = state.failed;
+        let next = if self.list.len() == 1 {
+            Buffer::new(new_table())
+        } else {
+            self.list.remove(self.current);
+            self.current = self.current.saturating_sub(1);
+            std::mem::replace(&mut self.list[self.current], Buffer::new(CSVTable::new()))
+        };
+        *csv = next.csv;
+        *state = next.state;
+        state.failed = failed;
+    }
+
+    /// The buffer other than the current one with the file at `path`, if any
+    fn find(&self, path: &std::path::Path) -> Option<usize> {
+        (0..self.list.len()).find(|&index| {
+            index != self.current && self.list[index].state.path.as_deref().is_some_and(|open| is_same_file(open, path))
+        })
+    }
+
+    /// A buffer other than the current one with unsaved changes, if any
+    fn unsaved(&self) -> Option<usize> {
+        (0..self.list.len()).find(|&index| index != self.current && self.list[index].state.dirty)
+    }
+
+    /// Buffer `reference`, written "#<n>", if it is open and not the current one
+    fn other(&mut self, reference: &str) -> Result<&mut CSVTable, String> {
+        let index = reference
+            .strip_prefix('#')
+            .and_then(|n| n.parse::<usize>().ok())
+            .ok_or_else(|| format!("Buffers are given as #<n>, not '{}'.", reference))?;
+        if index == self.current {
+            return Err(format!("Buffer {} is the current one.", index));
+        }
+        match self.list.get_mut(index) {
+            Some(buffer) => Ok(&mut buffer.csv),
+            None => Err(format!("There is no buffer {}, see 'buffers'.", index)),
+        }
+    }
 }
 
-fn cli_test() -> std::io::Result<()> {
+/// An empty table that reports undo and redo steps
+fn new_table() -> CSVTable {
     let mut csv = CSVTable::new();
-    println!("CSV Table CLI");
-    println!("Type 'help' for commands.\n");
+    csv.on_undo(|label| println!("SUCCESS: Undid: {}", describe_step(label)));
+    csv.on_redo(|label| println!("SUCCESS: Redid: {}", describe_step(label)));
+    csv
+}
+
+/// How the CLI was started
+#[derive(Debug, Default)]
+struct Options {
+    path: Option<std::path::PathBuf>,   // loaded before the first command
+    delimiter: Option<u8>,              // of the file at `path`
+    readonly: bool,                     // the file at `path` is not to be saved over
+    script: Option<std::path::PathBuf>, // commands to run instead of reading them from stdin
+    keep_going: bool,                   // run the rest of a script after a command fails
+    tui: bool,                          // start in the grid view
+}
+
+const USAGE: &str = "Usage: rust_grid [<path> [--delimiter <char>] [--readonly]] [--tui] [--script <file>] [--keep-going]";
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--script" => match args.next() {
+                Some(path) => options.script = Some(path.into()),
+                None => return Err("--script needs a file".into()),
+            },
+            "--keep-going" => options.keep_going = true,
+            "--delimiter" => match args.next().as_deref().and_then(parse_delimiter) {
+                Some(delimiter) => options.delimiter = Some(delimiter),
+                None => return Err("--delimiter needs one character, like ';' or '\\t'".into()),
+            },
+            "--readonly" => options.readonly = true,
+            "--tui" => options.tui = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown argument '{}'", arg)),
+            _ if options.path.is_some() => return Err(format!("only one file can be opened, not also '{}'", arg)),
+            _ => options.path = Some(arg.into()),
+        }
+    }
+    if options.path.is_none() && (options.delimiter.is_some() || options.readonly) {
+        return Err("--delimiter and --readonly need a file to open".into());
+    }
+    Ok(options)
+}
+
+/// Files ending in .json are read and written as JSON, anything else as CSV
+fn is_json(path: &std::path::Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+/// A table read from a CSV or JSON file, as `load` would
+fn read_table(path: &std::path::Path) -> std::io::Result<CSVTable> {
+    let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut table = CSVTable::new();
+    if is_json(path) {
+        table.read_json(reader)?;
+    } else {
+        table.read_csv(reader)?;
+    }
+    Ok(table)
+}
+
+/// Named ranges and the undo history are saved next to the table, in
+/// "<file>.ranges" and "<file>.history"
+fn sidecar_path(path: &std::path::Path, extension: &str) -> std::path::PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    std::path::PathBuf::from(name)
+}
+
+/// `file` as UTF-8, or decoded from the encoding called `encoding`
+fn decoded(file: std::fs::File, encoding: Option<&str>) -> std::io::Result<Box<dyn std::io::BufRead>> {
+    match encoding {
+        None => Ok(Box::new(std::io::BufReader::new(file))),
+        #[cfg(feature = "encoding_rs")]
+        Some(label) => match tools::decode::encoding_for_label(label) {
+            Some(encoding) => Ok(Box::new(tools::decode::DecodingReader::new(file, encoding))),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unknown encoding '{}'", label))),
+        },
+        #[cfg(not(feature = "encoding_rs"))]
+        Some(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "other encodings than UTF-8 need the encoding_rs feature",
+        )),
+    }
+}
+
+/// A single ASCII character, or "\t" for tabs
+fn parse_delimiter(text: &str) -> Option<u8> {
+    match text.as_bytes() {
+        b"\\t" => Some(b'\t'),
+        [delimiter] if delimiter.is_ascii() => Some(*delimiter),
+        _ => None,
+    }
+}
+
+/// "30s", "5m" or "2h"; a bare number is seconds
+fn parse_duration(text: &str) -> Option<Duration> {
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => text.split_at(i),
+        None => (text, "s"),
+    };
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "s" => number,
+        "m" => number.checked_mul(60)?,
+        "h" => number.checked_mul(3600)?,
+        _ => return None,
+    };
+    Some(Duration::from_secs(seconds))
+}
+
+/// "3:10", the indices 3 to 10, as the range 3..11
+fn parse_span(text: &str) -> Option<std::ops::Range<usize>> {
+    let (first, last) = text.split_once(':')?;
+    let (first, last) = (first.parse::<usize>().ok()?, last.parse::<usize>().ok()?);
+    (first <= last).then_some(first..last + 1)
+}
+
+/// "4096", "500K" or "20M"
+fn parse_bytes(text: &str) -> Option<usize> {
+    let (number, factor) = match text.strip_suffix(['K', 'k']) {
+        Some(number) => (number, 1 << 10),
+        None => match text.strip_suffix(['M', 'm']) {
+            Some(number) => (number, 1 << 20),
+            None => (text, 1),
+        },
+    };
+    number.parse::<usize>().ok()?.checked_mul(factor)
+}
+
+/// How long ago `time` was, like "5m ago"
+fn format_age(time: SystemTime) -> String {
+    let seconds = time.elapsed().map(|age| age.as_secs()).unwrap_or(0);
+    if seconds < 60 {
+        format!("{}s ago", seconds)
+    } else if seconds < 3600 {
+        format!("{}m ago", seconds / 60)
+    } else {
+        format!("{}h ago", seconds / 3600)
+    }
+}
+
+fn describe_step(label: &str) -> &str {
+    if label.is_empty() { "unlabeled step" } else { label }
+}
+
+/// Remove a sidecar that is no longer needed, if there is one
+fn remove_sidecar(sidecar: &std::path::Path) -> std::io::Result<()> {
+    match std::fs::remove_file(sidecar) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Whether the two paths are to the same existing file
+fn is_same_file(a: &std::path::Path, b: &std::path::Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Load the table at `path`, with its named ranges and undo history if they were saved
+fn load_file(
+    csv: &mut CSVTable,
+    state: &mut SessionState,
+    path: std::path::PathBuf,
+    dialect: CsvDialect,
+    encoding: Option<&str>,
+) {
+    match std::fs::File::open(&path).and_then(|file| decoded(file, encoding)) {
+        Ok(reader) => {
+            let loaded = if is_json(&path) {
+                csv.load_undoable(|csv| csv.read_json(reader))
+            } else {
+                csv.load_undoable(|csv| csv.read_csv_with(reader, dialect))
+            };
+            match loaded {
+                Ok(_) => {
+                    println!("SUCCESS: Loaded '{}'.", path.display());
+                    state.dialect = dialect;
+                    let sidecar = sidecar_path(&path, "ranges");
+                    if let Ok(file) = std::fs::File::open(&sidecar) {
+                        match csv.read_ranges(std::io::BufReader::new(file)) {
+                            Ok(_) => println!("INFO: Loaded {} named ranges.", csv.ranges().len()),
+                            Err(e) => println!("WARNING: Ignored '{}': {}", sidecar.display(), e),
+                        }
+                    }
+                    let sidecar = sidecar_path(&path, "history");
+                    if csv.in_transaction() {
+                        if sidecar.exists() {
+                            println!(
+                                "WARNING: '{}' not restored, it would end the open transaction.",
+                                sidecar.display()
+                            );
+                        }
+                    } else if let Ok(file) = std::fs::File::open(&sidecar) {
+                        match csv.read_history(std::io::BufReader::new(file)) {
+                            Ok(true) => println!(
+                                "INFO: Restored {} undo steps.",
+                                csv.history_entries().len()
+                            ),
+                            Ok(false) => println!(
+                                "WARNING: '{}' changed since its undo history was saved. History not restored.",
+                                path.display()
+                            ),
+                            Err(e) => println!("WARNING: Ignored '{}': {}", sidecar.display(), e),
+                        }
+                    }
+                    state.path = Some(path);
+                    state.dirty = false;
+                }
+                Err(e) => problem!(state, "Failed to read '{}': {}", path.display(), e),
+            }
+        }
+        Err(e) => problem!(state, "Cannot open file '{}': {}", path.display(), e),
+    }
+}
+
+/// The grid view of `tui`, in a terminal
+#[cfg(feature = "tui")]
+fn open_grid(csv: &mut CSVTable, state: &mut SessionState, interactive: bool) {
+    if !interactive {
+        problem!(state, "The grid view needs a terminal.");
+        return;
+    }
+    match tui::run(csv) {
+        Ok(changed) => {
+            state.dirty |= changed;
+            println!("SUCCESS: Left the grid view.");
+        }
+        Err(e) => problem!(state, "Grid view failed: {}", e),
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+fn open_grid(_csv: &mut CSVTable, state: &mut SessionState, _interactive: bool) {
+    problem!(state, "This build has no grid view. Build it with `--features tui`.");
+}
+
+/// Run commands typed at the prompt, or from a script or pipe without one. A
+/// script stops at the first command that fails unless `keep_going` is set.
+/// Returns whether every command succeeded.
+fn cli_test(options: Options) -> std::io::Result<bool> {
+    let interactive = options.script.is_none() && io::stdin().is_terminal();
+    let mut commands = match &options.script {
+        Some(path) => match std::fs::File::open(path) {
+            Ok(file) => Commands::Lines(Box::new(io::BufReader::new(file))),
+            Err(e) => {
+                println!("PROBLEM: Cannot open script '{}': {}", path.display(), e);
+                return Ok(false);
+            }
+        },
+        None if interactive => Commands::prompt()?,
+        None => Commands::Lines(Box::new(io::stdin().lock())),
+    };
+
+    let mut csv = new_table();
+    let mut buffers = Buffers {
+        list: vec![Buffer::new(CSVTable::new())],
+        current: 0,
+    };
+    if interactive {
+        println!("CSV Table CLI");
+        println!("Type 'help' for commands.\n");
+    }
 
     let mut state = SessionState {
         dirty: false,
         path: None,
+        last_found: None,
+        dialect: CsvDialect::default(),
+        failed: false,
+        readonly: None,
+        filter: None,
+        cursor: (0, 0),
     };
+    if let Some(path) = options.path {
+        let dialect = CsvDialect::default().delimiter(options.delimiter.unwrap_or(b','));
+        load_file(&mut csv, &mut state, path.clone(), dialect, None);
+        if state.failed {
+            return Ok(false);
+        }
+        if options.readonly {
+            state.readonly = Some(path);
+        }
+    }
+    if options.tui {
+        open_grid(&mut csv, &mut state, interactive);
+        if state.failed {
+            return Ok(false);
+        }
+    }
+    let mut failures = 0;
+    let mut line_number = 0;
+    let mut input = String::new();
+    let mut warned_at_eof = false;
+    let mut macros = Macros::default();
+    let mut just_run = None; // the command typed last, recorded if it did not fail
 
     loop {
-        print!(
-            "[{}{}] > ",
+        if let Some(line) = just_run.take()
+            && !state.failed
+            && let Some((_, lines)) = &mut macros.recording
+        {
+            lines.push(line);
+        }
+        if state.failed && !macros.queue.is_empty() {
+            macros.queue.clear();
+            println!("INFO: Stopped playing the macro at the failed command.");
+        }
+        if std::mem::take(&mut state.failed) {
+            failures += 1;
+            if !interactive && !options.keep_going {
+                println!("PROBLEM: Stopped at line {}: {}", line_number, input.trim());
+                break;
+            }
+        }
+
+        let prompt = format!(
+            "[{}{}{}{}{}{}] > ",
+            if buffers.list.len() > 1 { format!("{}: ", buffers.current) } else { String::new() },
             state
                 .path
                 .as_ref()
                 .map(|p| p.display().to_string())
                 .unwrap_or("untitled".into()),
-            if state.dirty { "*" } else { "" }
+            if state.dirty { "*" } else { "" },
+            if state.readonly.is_some() && state.readonly == state.path { " (read-only)" } else { "" },
+            if csv.has_cell(state.cursor.0, state.cursor.1) { format!(" at {:?}", state.cursor) } else { String::new() },
+            state.filter.as_ref().map(|filter| format!(" (filter: {})", filter)).unwrap_or_default()
         );
-        io::stdout().flush().unwrap();
 
-        let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_err() {
-            println!("Input error.");
-            continue;
+        input.clear();
+        let step = macros.next_step();
+        if let Some(step) = &step {
+            input.push_str(&step.line);
+        } else {
+            line_number += 1;
+            match commands.read(&prompt, &mut input) {
+                Ok(false) if interactive && (state.dirty || buffers.unsaved().is_some()) && !warned_at_eof => {
+                    // Ctrl-D is taken as 'quit', and once more as 'quit!'
+                    println!("WARNING: You have unsaved changes.");
+                    println!("Press Ctrl-D again to exit without saving, or type 'save' to save.");
+                    warned_at_eof = true;
+                    continue;
+                }
+                Ok(false) => {
+                    if (state.dirty || buffers.unsaved().is_some()) && !interactive {
+                        problem!(state, "The script ended with unsaved changes, end it with 'save' or 'quit!'.");
+                        failures += 1;
+                    }
+                    break;
+                }
+                Ok(true) => warned_at_eof = false,
+                Err(e) => {
+                    problem!(state, "Cannot read the next command: {}", e);
+                    failures += 1;
+                    break;
+                }
+            }
         }
 
-        let input = input.trim();
-        if input.is_empty() {
+        // Lines starting with '#' are comments, for scripts
+        let typed = input.trim();
+        if typed.is_empty() || typed.starts_with('#') {
             continue;
         }
+        if step.is_none() && macros.recording.is_some() && !macros::is_control(typed) {
+            just_run = Some(typed.to_string());
+        }
+        // While recording, a command runs as the macro's first repetition will
+        let expanded = match &step {
+            Some(step) => macros::expand(typed, step.repetition, state.cursor),
+            None if macros.recording.is_some() => macros::expand(typed, 0, state.cursor),
+            None => typed.to_string(),
+        };
+        let input = expanded.as_str();
 
         let mut parts = input.split_whitespace();
         let cmd = parts.next().unwrap();
@@ -51,21 +510,133 @@ fn cli_test() -> std::io::Result<()> {
             "help" => {
                 println!("Commands:");
                 println!("  Print: p or print");
+                println!("  View: view [-h] [-w <width>] <r1>:<r2> [<c1>:<c2>] (print only rows r1 to r2, and columns c1 to c2)");
                 println!("  Append row: ar, append_row");
                 println!("  Append column: ac, append_col");
                 println!("  Insert row: ir <index>, insert_row <index>");
                 println!("  Insert column: ic <index>, insert_col <index>");
                 println!("  Delete row: dr <index>, delete_row <index>");
                 println!("  Delete column: dc <index>, delete_col <index>");
+                println!("  Duplicate row: dupr <index>, duplicate_row <index>");
+                println!("  Duplicate column: dupc <index>, duplicate_col <index>");
+                println!("  Move row: mr <from> <to>, move_row <from> <to>");
+                println!("  Move column: mc <from> <to>, move_col <from> <to>");
                 println!("  Write: w <row> <col> <value>, write <row> <col> <value>");
-                println!("  Read: read <row> <col>");
+                println!("  Read: read <row> <col>, read_row <row>, read_col <col>");
+                println!("  Cursor: goto <row> <col>, up/down/left/right [count] (move it), set <value> (write at it)");
+                println!("  Macros: macro record <name>, then commands, then macro stop; macro play <name> [count], macro list");
+                println!("        ({{row}} and {{col}} in a command are the cursor's place when it runs, {{n}} the repetition from 0)");
+                println!("  Paste: paste <row> <col> <a,b;c,d> (rows split by ';', cells by ',')");
+                println!("  Fill: fill <r1> <c1> <r2> <c2> <value>");
+                println!("  Clear: clear <r1> <c1> <r2> <c2>");
+                println!("  Fill series: series <r1> <c1> <r2> <c2> (continue numbers/dates from the first cells)");
+                println!("  Column types: types [-h] (detect from the data; -h leaves out the first row), type <col> <text|int|float|date|bool>");
+                println!("  Statistics: stats <col|range> (count, empty, distinct, and min/max/sum/mean of numbers)");
+                println!("  Named ranges: range <name> <r1> <c1> <r2> <c2> (define), range <name> (show), ranges, unrange <name>");
+                println!("  Sort: sort <col> [desc] [numeric] [-h] (order the rows by a column; -h keeps the first row on top)");
+                println!("  Filter: filter [-i] [-r] <col> <pattern> (show rows where the cell contains pattern), filter clear (show all)");
+                println!("  Find: f [-i] [-r] [-c <col>] <pattern>, find [-i] [-r] [-c <col>] <pattern>");
+                println!("  Find next: fn [-i] [-r] [-c <col>] <pattern>, find_next ... (after the last cell found, wrapping around)");
+                println!("  Replace: replace [-i] [-r] [-c <col>] <pattern> <replacement>");
+                println!("  Compact: compact (free the storage of deleted rows and columns, clears undo history)");
+                println!("  Transaction: begin, then commit (one undo step for all changes since) or rollback (revert them)");
                 println!("  Undo: u, undo");
+                println!("  Undo tree: undo_tree <on|off> (keep undone steps as branches), branches, branch <id> (go to a branch)");
+                println!("  History: history [since] (list undo and redo steps, or those of the last 30s, 5m, 2h...), undo_to <n> (undo step n and all later ones)");
+                println!("  Undo since: undo_since <30s|5m|2h...> (undo the changes made in that time)");
+                println!("  Coalesce: coalesce <on|off> (consecutive writes to one cell are undone at once)");
+                println!("  Undo budget: undo_budget [bytes|off] (show the undo history's memory, or forget the oldest steps beyond a size like 20M)");
+                println!("  Undo limit: undo_limit <steps|off> (forget the oldest undo steps beyond it)");
                 println!("  Redo: r, redo");
+                println!("  New: new [rows] [cols] [--headers a,b,c] (a blank untitled table, rows below the headers), new! (discard changes)");
+                println!("  Load: load [-d <delimiter>] [-c <comment>] [-b] [-e <encoding>] [-f error|pad|truncate] <file> (.json as JSON, anything else as CSV;");
+                println!("        -c skips lines starting with it, -b blank lines, -e reads e.g. windows-1252 or utf-16le,");
+                println!("        -f rejects rows not as wide as the first, pads shorter ones, or also truncates longer ones)");
+                println!("        load! ... over unsaved changes (undo brings them back)");
+                println!("  Save: s [file], save [-q always|necessary|never] [-t lf|crlf] [file] (.json saves the first row as keys;");
+                println!("        -q quotes fields, -t ends lines, kept for later saves)");
+                println!("  Export: export <json|markdown|tsv> <file> (write a copy in another format, the table stays where it is saved)");
+                println!("  Diff: diff <file> [key_col] (changes from this table to the file, rows matched by position or key)");
+                println!("  Peek: peek <file> [from] [count] (show rows of a CSV file of any size without loading it)");
+                println!("  Line editing: Up and Down for earlier commands, Ctrl-R to search them, Tab to complete commands and file names");
+                println!("  Buffers: open [load options] <file_path> (in a new buffer), buffers, switch <n>, close, close!");
+                println!("  Between buffers: diff #<n> [key_col], copy #<n> <r1> <c1> <r2> <c2> <row> <col>");
                 println!("  Quit: quit, exit");
+                println!("  Grid view: tui (arrows move, Enter edits the cell, Del clears it, q leaves), or start with --tui");
+                println!("  Open a file at startup: rust_grid <path> [--delimiter <char>] [--readonly]");
+                println!("  Scripts: rust_grid --script <file> [--keep-going], or pipe commands in; lines starting with '#' are skipped");
             }
 
             "p" | "print" => {
-                csv.pretty_print();
+                let mut options = PrintOptions {
+                    max_width: Some(40),
+                    ..PrintOptions::default()
+                };
+                let mut page = Vec::new();
+                let mut bad_usage = false;
+                while let Some(part) = parts.next() {
+                    match part {
+                        "-h" => options.header = true,
+                        "-w" => match parts.next().and_then(|v| v.parse::<usize>().ok()) {
+                            Some(0) => options.max_width = None,
+                            Some(width) => options.max_width = Some(width),
+                            None => bad_usage = true,
+                        },
+                        _ => match part.parse::<usize>() {
+                            Ok(n) => page.push(n),
+                            Err(_) => bad_usage = true,
+                        },
+                    }
+                }
+                match page[..] {
+                    [] if !bad_usage => {}
+                    [from] if !bad_usage => options.rows = Some(from..from + 20),
+                    [from, count] if !bad_usage => options.rows = Some(from..from + count),
+                    _ => {
+                        problem!(state, "Usage: print [-h] [-w <width>] [<from> [count]]");
+                        continue;
+                    }
+                }
+                csv.render(io::stdout().lock(), &options)?;
+            }
+
+            "view" => {
+                let mut options = PrintOptions {
+                    max_width: Some(40),
+                    ..PrintOptions::default()
+                };
+                let mut spans = Vec::new();
+                let mut bad_usage = false;
+                while let Some(part) = parts.next() {
+                    match part {
+                        "-h" => options.header = true,
+                        "-w" => match parts.next().and_then(|v| v.parse::<usize>().ok()) {
+                            Some(0) => options.max_width = None,
+                            Some(width) => options.max_width = Some(width),
+                            None => bad_usage = true,
+                        },
+                        _ => match parse_span(part) {
+                            Some(span) => spans.push(span),
+                            None => bad_usage = true,
+                        },
+                    }
+                }
+                let (rows, cols) = match &spans[..] {
+                    [rows] if !bad_usage => (rows.clone(), None),
+                    [rows, cols] if !bad_usage => (rows.clone(), Some(cols.clone())),
+                    _ => {
+                        problem!(state, "Usage: view [-h] [-w <width>] <r1>:<r2> [<c1>:<c2>]");
+                        continue;
+                    }
+                };
+                let first_col = cols.as_ref().map_or(0, |cols| cols.start);
+                if !csv.has_row(rows.start) || !csv.has_col(first_col) {
+                    problem!(state, "Cannot view from ({}, {}) out of bounds", rows.start, first_col);
+                    continue;
+                }
+                options.row_indices = Some(rows);
+                options.cols = cols;
+                csv.render(io::stdout().lock(), &options)?;
             }
 
             "ar" | "append_row" => {
@@ -86,7 +657,7 @@ fn cli_test() -> std::io::Result<()> {
                     state.dirty = true;
                     println!("SUCCESS: Row inserted at {}.", r);
                 } else {
-                    println!("PROBLEM: Usage: insert_row <index> or ir <index>");
+                    problem!(state, "Usage: insert_row <index> or ir <index>");
                 }
             }
 
@@ -96,7 +667,7 @@ fn cli_test() -> std::io::Result<()> {
                     state.dirty = true;
                     println!("SUCCESS: Column inserted at {}.", c);
                 } else {
-                    println!("PROBLEM: Usage: insert_col <index> or ic <index>");
+                    problem!(state, "Usage: insert_col <index> or ic <index>");
                 }
             }
 
@@ -107,10 +678,10 @@ fn cli_test() -> std::io::Result<()> {
                         state.dirty = true;
                         println!("SUCCESS: Row deleted at {}.", r);
                     } else {
-                        println!("PROBLEM: Cannot delete row {} out of bounds", r);
+                        problem!(state, "Cannot delete row {} out of bounds", r);
                     }
                 } else {
-                    println!("PROBLEM: Usage: delete_row <index> or dr <index>");
+                    problem!(state, "Usage: delete_row <index> or dr <index>");
                 }
             }
 
@@ -121,10 +692,72 @@ fn cli_test() -> std::io::Result<()> {
                         state.dirty = true;
                         println!("SUCCESS: Column deleted at {}.", c);
                     } else {
-                        println!("PROBLEM: Cannot delete column {} out of bounds", c);
+                        problem!(state, "Cannot delete column {} out of bounds", c);
+                    }
+                } else {
+                    problem!(state, "Usage: delete_col <index> or dc <index>");
+                }
+            }
+
+            "dupr" | "duplicate_row" => {
+                if let Some(r) = parts.next().and_then(|v| v.parse::<usize>().ok()) {
+                    if csv.has_row(r) {
+                        let copy = csv.duplicate_row(r);
+                        state.dirty = true;
+                        println!("SUCCESS: Row {} duplicated to {}.", r, copy);
+                    } else {
+                        problem!(state, "Cannot duplicate row {} out of bounds", r);
                     }
                 } else {
-                    println!("PROBLEM: Usage: delete_col <index> or dc <index>");
+                    problem!(state, "Usage: duplicate_row <index> or dupr <index>");
+                }
+            }
+
+            "dupc" | "duplicate_col" => {
+                if let Some(c) = parts.next().and_then(|v| v.parse::<usize>().ok()) {
+                    if csv.has_col(c) {
+                        let copy = csv.duplicate_col(c);
+                        state.dirty = true;
+                        println!("SUCCESS: Column {} duplicated to {}.", c, copy);
+                    } else {
+                        problem!(state, "Cannot duplicate column {} out of bounds", c);
+                    }
+                } else {
+                    problem!(state, "Usage: duplicate_col <index> or dupc <index>");
+                }
+            }
+
+            "mr" | "move_row" => {
+                let from = parts.next().and_then(|v| v.parse::<usize>().ok());
+                let to = parts.next().and_then(|v| v.parse::<usize>().ok());
+
+                if let (Some(from), Some(to)) = (from, to) {
+                    if csv.has_row(from) && csv.has_row(to) {
+                        csv.move_row(from, to);
+                        state.dirty = true;
+                        println!("SUCCESS: Row moved from {} to {}.", from, to);
+                    } else {
+                        problem!(state, "Cannot move row {} to {} out of bounds", from, to);
+                    }
+                } else {
+                    problem!(state, "Usage: move_row <from> <to> or mr <from> <to>");
+                }
+            }
+
+            "mc" | "move_col" => {
+                let from = parts.next().and_then(|v| v.parse::<usize>().ok());
+                let to = parts.next().and_then(|v| v.parse::<usize>().ok());
+
+                if let (Some(from), Some(to)) = (from, to) {
+                    if csv.has_col(from) && csv.has_col(to) {
+                        csv.move_col(from, to);
+                        state.dirty = true;
+                        println!("SUCCESS: Column moved from {} to {}.", from, to);
+                    } else {
+                        problem!(state, "Cannot move column {} to {} out of bounds", from, to);
+                    }
+                } else {
+                    problem!(state, "Usage: move_col <from> <to> or mc <from> <to>");
                 }
             }
 
@@ -135,14 +768,192 @@ fn cli_test() -> std::io::Result<()> {
 
                 if let (Some(r), Some(c)) = (r, c) {
                     if csv.has_cell(r, c) {
-                        csv.write_cell(r, c, &value);
+                        match csv.write_cell(r, c, &value) {
+                            Ok(()) => {
+                                state.dirty = true;
+                                println!("SUCCESS: Written to ({}, {}).", r, c);
+                            }
+                            Err(e) => problem!(state, "{}", e),
+                        }
+                    } else {
+                        problem!(state, "Cannot write cell ({}, {}) out of bounds", r, c);
+                    }
+                } else {
+                    problem!(state, "Usage: write <row> <col> <value> or w <row> <col> <value>");
+                }
+            }
+
+            "macro" => match (parts.next(), parts.next(), parts.next()) {
+                (Some("record"), Some(name), None) => {
+                    if let Some((recorded, _)) = &macros.recording {
+                        problem!(state, "Already recording macro '{}', end it with 'macro stop' first.", recorded);
+                    } else {
+                        macros.recording = Some((name.to_string(), Vec::new()));
+                        println!("SUCCESS: Recording macro '{}', end it with 'macro stop'.", name);
+                    }
+                }
+                (Some("stop"), None, None) => match macros.recording.take() {
+                    Some((name, lines)) => {
+                        println!("SUCCESS: Macro '{}' recorded with {} commands.", name, lines.len());
+                        macros.saved.insert(name, lines);
+                    }
+                    None => problem!(state, "Not recording a macro. Use 'macro record <name>' first."),
+                },
+                (Some("play"), Some(name), count) => {
+                    let count = match count.map(|v| v.parse::<usize>()) {
+                        None => 1,
+                        Some(Ok(count)) => count,
+                        Some(Err(_)) => {
+                            problem!(state, "Usage: macro play <name> [count]");
+                            continue;
+                        }
+                    };
+                    let depth = step.as_ref().map_or(0, |step| step.depth) + 1;
+                    match macros.saved.get(name).cloned() {
+                        Some(_) if depth > macros::MAX_DEPTH => {
+                            problem!(state, "Cannot play macro '{}', macros play each other too deep", name)
+                        }
+                        Some(lines) => macros.play(&lines, count, depth),
+                        None => problem!(state, "No macro named '{}'", name),
+                    }
+                }
+                (Some("list"), None, None) => {
+                    if macros.saved.is_empty() {
+                        println!("INFO: No macros recorded.");
+                    }
+                    for (name, lines) in &macros.saved {
+                        println!("{}: {}", name, lines.join("; "));
+                    }
+                }
+                _ => problem!(state, "Usage: macro record <name>, macro stop, macro play <name> [count] or macro list"),
+            },
+
+            "goto" => {
+                let r = parts.next().and_then(|v| v.parse::<usize>().ok());
+                let c = parts.next().and_then(|v| v.parse::<usize>().ok());
+
+                match (r, c) {
+                    (Some(r), Some(c)) if csv.has_cell(r, c) => {
+                        state.cursor = (r, c);
+                        println!("SUCCESS: Cursor at ({}, {}) = \"{}\"", r, c, csv.read_cell(r, c));
+                    }
+                    (Some(r), Some(c)) => problem!(state, "Cannot go to cell ({}, {}) out of bounds", r, c),
+                    _ => problem!(state, "Usage: goto <row> <col>"),
+                }
+            }
+
+            "up" | "down" | "left" | "right" => {
+                let count = match parts.next().map(|v| v.parse::<usize>()) {
+                    None => 1,
+                    Some(Ok(count)) => count,
+                    Some(Err(_)) => {
+                        problem!(state, "Usage: {} [count]", cmd);
+                        continue;
+                    }
+                };
+                if csv.row_size() == 0 || csv.col_size() == 0 {
+                    problem!(state, "Cannot move the cursor in an empty table");
+                    continue;
+                }
+                let (r, c) = state.cursor;
+                let (last_row, last_col) = (csv.row_size() - 1, csv.col_size() - 1);
+                state.cursor = match cmd {
+                    "up" => (r.min(last_row).saturating_sub(count), c.min(last_col)),
+                    "down" => (r.saturating_add(count).min(last_row), c.min(last_col)),
+                    "left" => (r.min(last_row), c.min(last_col).saturating_sub(count)),
+                    _ => (r.min(last_row), c.saturating_add(count).min(last_col)),
+                };
+                let (r, c) = state.cursor;
+                println!("SUCCESS: Cursor at ({}, {}) = \"{}\"", r, c, csv.read_cell(r, c));
+            }
+
+            "set" => {
+                let (r, c) = state.cursor;
+                let value = parts.collect::<Vec<_>>().join(" ");
+
+                if csv.has_cell(r, c) {
+                    match csv.write_cell(r, c, &value) {
+                        Ok(()) => {
+                            state.dirty = true;
+                            println!("SUCCESS: Written to ({}, {}).", r, c);
+                        }
+                        Err(e) => problem!(state, "{}", e),
+                    }
+                } else {
+                    problem!(state, "Cannot write at the cursor ({}, {}) out of bounds. Use 'goto' first.", r, c);
+                }
+            }
+
+            "paste" => {
+                let r = parts.next().and_then(|v| v.parse::<usize>().ok());
+                let c = parts.next().and_then(|v| v.parse::<usize>().ok());
+                let block = parts.collect::<Vec<_>>().join(" ");
+
+                if let (Some(r), Some(c), false) = (r, c, block.is_empty()) {
+                    let mut edits = Vec::new();
+                    for (i, line) in block.split(';').enumerate() {
+                        for (j, value) in line.split(',').enumerate() {
+                            edits.push(CellEdit::Write { row: r + i, col: c + j, value: value.to_string() });
+                        }
+                    }
+                    let count = edits.len();
+                    match csv.apply_edits(edits) {
+                        Ok(()) => {
+                            state.dirty = true;
+                            println!("SUCCESS: Pasted {} cells at ({}, {}).", count, r, c);
+                        }
+                        Err(e) => problem!(state, "Nothing pasted, {}", e),
+                    }
+                } else {
+                    problem!(state, "Usage: paste <row> <col> <a,b;c,d>");
+                }
+            }
+
+            "fill" | "series" => {
+                let coords: Vec<Option<usize>> = (0..4)
+                    .map(|_| parts.next().and_then(|v| v.parse::<usize>().ok()))
+                    .collect();
+                let value = parts.collect::<Vec<_>>().join(" ");
+
+                if let [Some(r1), Some(c1), Some(r2), Some(c2)] = coords[..] {
+                    if csv.has_cell(r1, c1) && csv.has_cell(r2, c2) {
+                        let result = if cmd == "fill" {
+                            csv.fill_range(r1, c1, r2, c2, &value).map_err(|e| e.to_string())
+                        } else {
+                            csv.fill_series((r1, c1), (r2, c2)).map_err(|e| e.to_string())
+                        };
+                        match result {
+                            Ok(()) => {
+                                state.dirty = true;
+                                println!("SUCCESS: Filled ({}, {})..({}, {}).", r1, c1, r2, c2);
+                            }
+                            Err(e) => problem!(state, "{}", e),
+                        }
+                    } else {
+                        problem!(state, "Cannot fill ({}, {})..({}, {}) out of bounds", r1, c1, r2, c2);
+                    }
+                } else if cmd == "fill" {
+                    problem!(state, "Usage: fill <r1> <c1> <r2> <c2> <value>");
+                } else {
+                    problem!(state, "Usage: series <r1> <c1> <r2> <c2>");
+                }
+            }
+
+            "clear" => {
+                let coords: Vec<Option<usize>> = (0..4)
+                    .map(|_| parts.next().and_then(|v| v.parse::<usize>().ok()))
+                    .collect();
+
+                if let [Some(r1), Some(c1), Some(r2), Some(c2)] = coords[..] {
+                    if csv.has_cell(r1, c1) && csv.has_cell(r2, c2) {
+                        csv.clear_range(r1, c1, r2, c2);
                         state.dirty = true;
-                        println!("SUCCESS: Written to ({}, {}).", r, c);
+                        println!("SUCCESS: Cleared ({}, {})..({}, {}).", r1, c1, r2, c2);
                     } else {
-                        println!("PROBLEM: Cannot write cell ({}, {}) out of bounds", r, c);
+                        problem!(state, "Cannot clear ({}, {})..({}, {}) out of bounds", r1, c1, r2, c2);
                     }
                 } else {
-                    println!("PROBLEM: Usage: write <row> <col> <value> or w <row> <col> <value>");
+                    problem!(state, "Usage: clear <r1> <c1> <r2> <c2>");
                 }
             }
 
@@ -155,96 +966,830 @@ fn cli_test() -> std::io::Result<()> {
                         let v = csv.read_cell(r, c);
                         println!("SUCCESS: Value at ({}, {}) = \"{}\"", r, c, v);
                     } else {
-                        println!("PROBLEM: Cannot read cell ({}, {}) out of bounds", r, c);
+                        problem!(state, "Cannot read cell ({}, {}) out of bounds", r, c);
+                    }
+                } else {
+                    problem!(state, "Usage: read <row> <col> or r <row> <col>");
+                }
+            }
+
+            "read_row" => match parts.next().and_then(|v| v.parse::<usize>().ok()) {
+                Some(r) if csv.has_row(r) => {
+                    let values: Vec<String> = csv.read_row(r).iter().map(|v| format!("\"{}\"", v)).collect();
+                    println!("SUCCESS: Row {} = [{}]", r, values.join(", "));
+                }
+                Some(r) => problem!(state, "Cannot read row {} out of bounds", r),
+                None => problem!(state, "Usage: read_row <row>"),
+            },
+
+            "read_col" => match parts.next().and_then(|v| v.parse::<usize>().ok()) {
+                Some(c) if csv.has_col(c) => {
+                    let values: Vec<String> = csv.read_col(c).iter().map(|v| format!("\"{}\"", v)).collect();
+                    println!("SUCCESS: Column {} = [{}]", c, values.join(", "));
+                }
+                Some(c) => problem!(state, "Cannot read column {} out of bounds", c),
+                None => problem!(state, "Usage: read_col <col>"),
+            },
+
+            "types" => {
+                let header = match parts.next() {
+                    None => false,
+                    Some("-h") => true,
+                    Some(_) => {
+                        problem!(state, "Usage: types [-h]");
+                        continue;
+                    }
+                };
+                let types = csv.infer_column_types(header);
+                let names: Vec<String> = types.iter().map(|t| t.to_string()).collect();
+                println!("SUCCESS: Column types: [{}]", names.join(", "));
+            }
+
+            "type" => {
+                let c = parts.next().and_then(|v| v.parse::<usize>().ok());
+                let column_type = parts.next().map(|v| v.parse::<ColumnType>());
+
+                match (c, column_type) {
+                    (Some(c), Some(Ok(column_type))) if csv.has_col(c) => {
+                        let misfits = csv.set_column_type(c, column_type);
+                        println!("SUCCESS: Column {} is now {}.", c, column_type);
+                        if !misfits.is_empty() {
+                            let rows: Vec<String> = misfits.iter().map(|r| r.to_string()).collect();
+                            println!("WARNING: Rows [{}] of column {} are not {}.", rows.join(", "), c, column_type);
+                        }
+                    }
+                    (Some(c), Some(Ok(_))) => problem!(state, "Cannot type column {} out of bounds", c),
+                    (_, Some(Err(e))) => problem!(state, "{}", e),
+                    _ => problem!(state, "Usage: type <col> <text|int|float|date|bool>"),
+                }
+            }
+
+            "stats" => match parts.next().map(|v| (v, v.parse::<usize>())) {
+                Some((_, Ok(c))) if csv.has_col(c) => println!("SUCCESS: Column {}: {}", c, csv.column_stats(c)),
+                Some((_, Ok(c))) => problem!(state, "Cannot summarize column {} out of bounds", c),
+                Some((name, Err(_))) => match csv.range_stats(name) {
+                    Some(stats) => println!("SUCCESS: Range {}: {}", name, stats),
+                    None => problem!(state, "No range named '{}'", name),
+                },
+                None => problem!(state, "Usage: stats <col|range>"),
+            },
+
+            "range" => {
+                let Some(name) = parts.next() else {
+                    problem!(state, "Usage: range <name> [<r1> <c1> <r2> <c2>]");
+                    continue;
+                };
+                let corners: Vec<usize> = parts.filter_map(|v| v.parse::<usize>().ok()).collect();
+                match corners[..] {
+                    [] => match (csv.range(name), csv.read_range(name)) {
+                        (Some(range), Some(rows)) => {
+                            println!("INFO: Range {} is {}", name, range);
+                            for row in rows {
+                                println!("[{}]", row.iter().map(|v| format!("\"{}\"", v)).collect::<Vec<_>>().join(", "));
+                            }
+                        }
+                        _ => problem!(state, "No range named '{}'", name),
+                    },
+                    [_, _, _, _] if name.parse::<usize>().is_ok() => {
+                        problem!(state, "Range names cannot be numbers, '{}' would be read as a column", name)
+                    }
+                    [r1, c1, r2, c2] if csv.has_cell(r1, c1) && csv.has_cell(r2, c2) => {
+                        let range = csv.define_range(name, r1, c1, r2, c2);
+                        state.dirty = true;
+                        println!(
+                            "SUCCESS: Range {} {} {}.",
+                            name,
+                            if range.is_some() { "redefined as" } else { "defined as" },
+                            csv.range(name).unwrap()
+                        );
+                    }
+                    [_, _, _, _] => problem!(state, "Cannot define range {} out of bounds", name),
+                    _ => problem!(state, "Usage: range <name> [<r1> <c1> <r2> <c2>]"),
+                }
+            }
+
+            "ranges" => {
+                let ranges = csv.ranges();
+                if ranges.is_empty() {
+                    println!("INFO: No named ranges.");
+                }
+                for (name, range) in ranges {
+                    println!("{}: {}", name, range);
+                }
+            }
+
+            "unrange" => match parts.next() {
+                Some(name) => match csv.remove_range(name) {
+                    Some(_) => {
+                        state.dirty = true;
+                        println!("SUCCESS: Range {} removed.", name);
+                    }
+                    None => problem!(state, "No range named '{}'", name),
+                },
+                None => problem!(state, "Usage: unrange <name>"),
+            },
+
+            "sort" => {
+                let c = parts.next().and_then(|v| v.parse::<usize>().ok());
+                let mut options = SortOptions::default();
+                let mut bad_option = None;
+                for part in parts {
+                    match part {
+                        "desc" => options.descending = true,
+                        "numeric" => options.numeric = true,
+                        "-h" => options.header = true,
+                        _ => bad_option = Some(part),
+                    }
+                }
+                if let Some(part) = bad_option {
+                    problem!(state, "Unknown sort option '{}', expected desc, numeric or -h", part);
+                    continue;
+                }
+
+                match c {
+                    Some(c) if csv.has_col(c) => {
+                        if csv.sort_rows(c, &options) {
+                            state.dirty = true;
+                            println!("SUCCESS: Rows sorted by column {}.", c);
+                        } else {
+                            println!("INFO: Rows already sorted by column {}.", c);
+                        }
+                    }
+                    Some(c) => problem!(state, "Cannot sort by column {} out of bounds", c),
+                    None => problem!(state, "Usage: sort <col> [desc] [numeric] [-h]"),
+                }
+            }
+
+            "filter" => {
+                let mut options = FindOptions::default();
+                let mut args = Vec::new();
+                for part in parts {
+                    match part {
+                        "-i" if args.is_empty() => options.case_insensitive = true,
+                        "-r" if args.is_empty() => options.regex = true,
+                        _ => args.push(part),
+                    }
+                }
+
+                match args[..] {
+                    [] | ["clear"] => {
+                        csv.clear_filter();
+                        state.filter = None;
+                        println!("SUCCESS: Filter cleared, showing all {} rows.", csv.row_size());
+                    }
+                    [c, ref pattern @ ..] if !pattern.is_empty() => match c.parse::<usize>() {
+                        Ok(c) if csv.has_col(c) => {
+                            let pattern = pattern.join(" ");
+                            match csv.filter_rows(c, &pattern, &options) {
+                                Ok(()) => {
+                                    state.filter = Some(format!("{} ~ {}", c, pattern));
+                                    println!(
+                                        "SUCCESS: Showing {} of {} rows.",
+                                        csv.visible_rows().len(),
+                                        csv.row_size()
+                                    );
+                                }
+                                Err(e) => problem!(state, "Invalid pattern: {}", e),
+                            }
+                        }
+                        Ok(c) => problem!(state, "Cannot filter on column {} out of bounds", c),
+                        Err(_) => problem!(state, "Usage: filter [-i] [-r] <col> <pattern> or filter clear"),
+                    },
+                    _ => problem!(state, "Usage: filter [-i] [-r] <col> <pattern> or filter clear"),
+                }
+            }
+
+            "f" | "find" | "fn" | "find_next" | "replace" => {
+                let mut options = FindOptions::default();
+                let mut args = Vec::new();
+                let mut bad_col = None;
+                while let Some(part) = parts.next() {
+                    match part {
+                        "-i" => options.case_insensitive = true,
+                        "-r" => options.regex = true,
+                        "-c" => match parts.next().and_then(|v| v.parse::<usize>().ok()) {
+                            Some(c) if csv.has_col(c) => options.in_col = Some(c),
+                            c => bad_col = Some(c),
+                        },
+                        _ => args.push(part),
+                    }
+                }
+                if let Some(c) = bad_col {
+                    match c {
+                        Some(c) => problem!(state, "Cannot search column {} out of bounds", c),
+                        None => problem!(state, "Usage: -c <col>"),
+                    }
+                    continue;
+                }
+
+                if cmd == "replace" {
+                    if let [pattern, replacement] = args[..] {
+                        match csv.replace_all(pattern, replacement, &options) {
+                            Ok(0) => println!("INFO: No cells matched '{}'.", pattern),
+                            Ok(n) => {
+                                state.dirty = true;
+                                println!("SUCCESS: Replaced in {} cells.", n);
+                            }
+                            Err(e) => problem!(state, "Cannot replace '{}': {}", pattern, e),
+                        }
+                    } else {
+                        problem!(state, "Usage: replace [-i] [-r] [-c <col>] <pattern> <replacement>");
+                    }
+                } else if let ([pattern], "fn" | "find_next") = (&args[..], cmd) {
+                    match csv.find_next(pattern, &options, state.last_found) {
+                        Ok(Some((r, c))) => {
+                            state.last_found = Some((r, c));
+                            println!("SUCCESS: Found at ({}, {}): \"{}\"", r, c, csv.read_cell(r, c));
+                        }
+                        Ok(None) => println!("INFO: No cells matched '{}'.", pattern),
+                        Err(e) => problem!(state, "Invalid pattern: {}", e),
+                    }
+                } else if let [pattern] = args[..] {
+                    match csv.find_cells(pattern, &options) {
+                        Ok(cells) if cells.is_empty() => println!("INFO: No cells matched '{}'.", pattern),
+                        Ok(cells) => println!("SUCCESS: Found at {:?}", cells),
+                        Err(e) => problem!(state, "Invalid pattern: {}", e),
                     }
                 } else {
-                    println!("PROBLEM: Usage: read <row> <col> or r <row> <col>");
+                    problem!(state, "Usage: {} [-i] [-r] [-c <col>] <pattern>", cmd);
                 }
             }
 
+            "begin" => {
+                csv.begin_transaction();
+                println!("SUCCESS: Transaction started, changes are grouped until 'commit' or 'rollback'.");
+            }
+
+            "commit" | "rollback" if !csv.in_transaction() => {
+                problem!(state, "No transaction to {}. Use 'begin' first.", cmd);
+            }
+
+            "commit" => {
+                csv.commit();
+                println!("SUCCESS: Transaction committed as one undo step.");
+            }
+
+            "rollback" => {
+                csv.rollback();
+                println!("SUCCESS: Transaction rolled back.");
+            }
+
             "u" | "undo" => {
                 if csv.undoable() {
                     csv.undo();
                     state.dirty = true;
-                    println!("SUCCESS: Undo done.");
                 } else {
                     println!("INFO: Nothing to undo.");
                 }
             }
 
+            "history" => {
+                let since = match parts.next().map(parse_duration) {
+                    Some(Some(duration)) => Some(duration),
+                    Some(None) => {
+                        problem!(state, "Usage: history [duration, like 30s, 5m or 2h]");
+                        continue;
+                    }
+                    None => None,
+                };
+                let start = since
+                    .and_then(|duration| SystemTime::now().checked_sub(duration))
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                let total = csv.history_entries().len();
+                let entries = csv.history_since(start);
+                let redo_entries = if since.is_none() { csv.redo_entries() } else { Vec::new() };
+                if entries.is_empty() && redo_entries.is_empty() {
+                    println!("INFO: No history.");
+                }
+                let first = total - entries.len();
+                for (i, (time, label)) in entries.iter().enumerate() {
+                    println!(
+                        "{:>3}: {} ({})",
+                        first + i,
+                        if label.is_empty() { "(change)" } else { label },
+                        format_age(*time)
+                    );
+                }
+                for label in redo_entries {
+                    println!("  redo: {}", if label.is_empty() { "(change)" } else { label });
+                }
+            }
+
+            "undo_since" => match parts.next().and_then(parse_duration) {
+                Some(duration) => {
+                    let undone = csv.undo_since(duration);
+                    if undone == 0 {
+                        println!("INFO: Nothing changed in that time.");
+                    } else {
+                        state.dirty = true;
+                        println!("SUCCESS: Undid {} steps.", undone);
+                    }
+                }
+                None => problem!(state, "Usage: undo_since <duration, like 30s, 5m or 2h>"),
+            },
+
+            "coalesce" => match parts.next() {
+                Some("on") => {
+                    csv.set_coalesce_edits(true);
+                    println!("SUCCESS: Consecutive writes to one cell are now one undo step.");
+                }
+                Some("off") => {
+                    csv.set_coalesce_edits(false);
+                    println!("SUCCESS: Every write is its own undo step.");
+                }
+                _ => problem!(state, "Usage: coalesce <on|off>"),
+            },
+
+            "undo_tree" => match parts.next() {
+                Some("on") => {
+                    csv.set_branching_history(true);
+                    println!("SUCCESS: Undone steps are kept as branches when you make new changes.");
+                }
+                Some("off") => {
+                    csv.set_branching_history(false);
+                    println!("SUCCESS: Undo tree off, branches forgotten.");
+                }
+                _ => problem!(state, "Usage: undo_tree <on|off>"),
+            },
+
+            "branches" => {
+                let branches = csv.history_branches();
+                if branches.is_empty() {
+                    println!("INFO: No branches.");
+                }
+                for branch in branches {
+                    println!(
+                        "{:>3}: after step {}, {} steps starting with {}",
+                        branch.id,
+                        branch.fork,
+                        branch.steps,
+                        if branch.label.is_empty() { "(change)" } else { &branch.label }
+                    );
+                }
+            }
+
+            "branch" => match parts.next().and_then(|v| v.parse::<usize>().ok()) {
+                Some(id) => {
+                    if csv.switch_branch(id) {
+                        state.dirty = true;
+                        println!("SUCCESS: Switched to branch {}.", id);
+                    } else {
+                        problem!(state, "No branch {}. Use 'branches' to list them.", id);
+                    }
+                }
+                None => problem!(state, "Usage: branch <id>"),
+            },
+
+            "undo_to" => match parts.next().and_then(|v| v.parse::<usize>().ok()) {
+                Some(n) if n < csv.history_entries().len() => {
+                    let last = csv.history_entries().len() - 1;
+                    csv.undo_to(n);
+                    state.dirty = true;
+                    println!("SUCCESS: Undid steps {} to {}.", n, last);
+                }
+                Some(n) => println!("INFO: No step {} to undo.", n),
+                None => problem!(state, "Usage: undo_to <step>"),
+            },
+
+            "undo_budget" => match parts.next() {
+                None => println!("INFO: The undo history holds {} bytes.", csv.history_memory_usage()),
+                Some("off") => {
+                    csv.set_history_budget(None);
+                    println!("SUCCESS: Undo history has no byte budget.");
+                }
+                Some(v) => match parse_bytes(v) {
+                    Some(budget) => {
+                        csv.set_history_budget(Some(budget));
+                        println!(
+                            "SUCCESS: Keeping the undo history within {} bytes, now {}.",
+                            budget,
+                            csv.history_memory_usage()
+                        );
+                    }
+                    None => problem!(state, "Usage: undo_budget [bytes, like 500K or 20M|off]"),
+                },
+            },
+
+            "undo_limit" => match parts.next() {
+                Some("off") => {
+                    csv.set_history_limit(None);
+                    println!("SUCCESS: Undo history is unlimited.");
+                }
+                Some(v) => match v.parse::<usize>() {
+                    Ok(limit) => {
+                        csv.set_history_limit(Some(limit));
+                        println!("SUCCESS: Keeping the last {} undo steps.", limit);
+                    }
+                    Err(_) => problem!(state, "Usage: undo_limit <steps|off>"),
+                },
+                None => problem!(state, "Usage: undo_limit <steps|off>"),
+            },
+
             "r" | "redo" => {
                 if csv.redoable() {
                     csv.redo();
                     state.dirty = true;
-                    println!("SUCCESS: Redo done.");
                 } else {
                     println!("INFO: Nothing to redo.");
                 }
             }
 
-            "load" => {
-                if state.dirty {
+            "diff" => {
+                let Some(path) = parts.next() else {
+                    problem!(state, "Usage: diff <file_path or #buffer> [key_col]");
+                    continue;
+                };
+                let key_col = parts.next().map(|v| v.parse::<usize>());
+                if path.starts_with('#') {
+                    let other = match buffers.other(path) {
+                        Ok(other) => other,
+                        Err(e) => {
+                            problem!(state, "{}", e);
+                            continue;
+                        }
+                    };
+                    let diff = match key_col {
+                        Some(Ok(key_col)) => csv.diff_by(other, key_col),
+                        Some(Err(_)) => {
+                            problem!(state, "Usage: diff <file_path or #buffer> [key_col]");
+                            continue;
+                        }
+                        None => csv.diff(other),
+                    };
+                    if diff.is_empty() {
+                        println!("INFO: No differences with buffer {}.", path);
+                    } else {
+                        print!("{}", diff);
+                        println!(
+                            "INFO: {} rows added, {} removed, {} cells changed in buffer {}.",
+                            diff.added_rows.len(),
+                            diff.removed_rows.len(),
+                            diff.changed_cells.len(),
+                            path
+                        );
+                    }
+                    continue;
+                }
+                let path = std::path::PathBuf::from(path);
+                match (read_table(&path), key_col) {
+                    (Ok(_), Some(Err(_))) => problem!(state, "Usage: diff <file_path or #buffer> [key_col]"),
+                    (Ok(mut other), key_col) => {
+                        let diff = match key_col {
+                            Some(Ok(key_col)) => csv.diff_by(&mut other, key_col),
+                            _ => csv.diff(&mut other),
+                        };
+                        if diff.is_empty() {
+                            println!("INFO: No differences with '{}'.", path.display());
+                        } else {
+                            print!("{}", diff);
+                            println!(
+                                "INFO: {} rows added, {} removed, {} cells changed in '{}'.",
+                                diff.added_rows.len(),
+                                diff.removed_rows.len(),
+                                diff.changed_cells.len(),
+                                path.display()
+                            );
+                        }
+                    }
+                    (Err(e), _) => problem!(state, "Failed to read '{}': {}", path.display(), e),
+                }
+            }
+
+            "compact" if csv.in_transaction() => {
+                problem!(state, "Cannot compact during a transaction. Use 'commit' or 'rollback' first.");
+            }
+
+            "compact" => {
+                let (rows, cols) = csv.compact();
+                println!("SUCCESS: Reclaimed {} rows and {} columns. Undo history cleared.", rows, cols);
+            }
+
+            "load" | "load!" | "open" => {
+                if state.dirty && cmd == "load" {
                     println!(
-                        "WARNING: You have unsaved changes. Save them before loading a new file"
+                        "WARNING: You have unsaved changes. Save them before loading a new file, or use 'load!'"
                     );
+                    state.failed = true;
                     continue;
                 }
-                if let Some(path) = parts.next() {
-                    let path = std::path::PathBuf::from(path);
-                    match std::fs::File::open(&path) {
-                        Ok(file) => {
-                            let reader = std::io::BufReader::new(file);
-                            match csv.read_csv(reader) {
-                                Ok(_) => {
-                                    println!("SUCCESS: Loaded '{}'.", path.display());
-                                    state.path = Some(path);
-                                    state.dirty = false;
+                let mut dialect = CsvDialect::default();
+                let mut encoding = None;
+                let mut path = None;
+                let mut bad_flag = None;
+                while let Some(part) = parts.next() {
+                    match part {
+                        "-d" => match parts.next().and_then(parse_delimiter) {
+                            Some(delimiter) => dialect = dialect.delimiter(delimiter),
+                            None => bad_flag = Some("The delimiter must be one character, like ';' or '\\t'."),
+                        },
+                        "-c" => match parts.next().and_then(parse_delimiter) {
+                            Some(comment) => dialect = dialect.comment(Some(comment)),
+                            None => bad_flag = Some("The comment start must be one character, like '#'."),
+                        },
+                        "-b" => dialect = dialect.skip_blank_lines(true),
+                        "-f" => match parts.next() {
+                            Some("error") => dialect = dialect.flex(FlexPolicy::Error),
+                            Some("pad") => dialect = dialect.flex(FlexPolicy::PadShorter),
+                            Some("truncate") => dialect = dialect.flex(FlexPolicy::TruncateLonger),
+                            _ => bad_flag = Some("Rows wider or narrower than the first can be an 'error', 'pad' or 'truncate'."),
+                        },
+                        "-e" => match parts.next() {
+                            Some(label) => encoding = Some(label),
+                            None => bad_flag = Some("Give the encoding, like 'windows-1252' or 'utf-16le'."),
+                        },
+                        _ => path = Some(part),
+                    }
+                }
+                if let Some(problem) = bad_flag {
+                    problem!(state, "{}", problem);
+                    continue;
+                }
+                if let Some(path) = path {
+                    if cmd == "open" {
+                        if let Some(index) = buffers.find(std::path::Path::new(path)) {
+                            buffers.switch(index, &mut csv, &mut state);
+                            println!("INFO: '{}' is already open, switched to buffer {}.", path, index);
+                            continue;
+                        }
+                        let previous = buffers.current;
+                        buffers.open(&mut csv, &mut state);
+                        load_file(&mut csv, &mut state, path.into(), dialect, encoding);
+                        if state.failed {
+                            buffers.close(&mut csv, &mut state);
+                            buffers.switch(previous, &mut csv, &mut state);
+                        } else {
+                            println!("INFO: Opened as buffer {}.", buffers.current);
+                        }
+                        continue;
+                    }
+                    load_file(&mut csv, &mut state, path.into(), dialect, encoding);
+                } else {
+                    problem!(state, "Usage: {} [-d <delimiter>] [-c <comment>] [-b] [-e <encoding>] [-f error|pad|truncate] <file_path>", cmd);
+                }
+            }
+
+            "new" | "new!" => {
+                if state.dirty && cmd == "new" {
+                    println!("WARNING: You have unsaved changes. Save them before starting a new table, or use 'new!'");
+                    state.failed = true;
+                    continue;
+                }
+                let mut size = Vec::new();
+                let mut headers = Vec::new();
+                let mut bad_usage = false;
+                while let Some(part) = parts.next() {
+                    match part {
+                        "--headers" => match parts.next() {
+                            Some(names) => headers = names.split(',').collect(),
+                            None => bad_usage = true,
+                        },
+                        _ => match part.parse::<usize>() {
+                            Ok(n) => size.push(n),
+                            Err(_) => bad_usage = true,
+                        },
+                    }
+                }
+                let (rows, cols) = match size[..] {
+                    [] if !bad_usage => (0, 0),
+                    [rows] if !bad_usage => (rows, headers.len().max(1)),
+                    [rows, cols] if !bad_usage => (rows, cols),
+                    _ => {
+                        problem!(state, "Usage: {} [rows] [cols] [--headers a,b,c]", cmd);
+                        continue;
+                    }
+                };
+                csv.reset(rows, cols, &headers);
+                csv.clear_filter();
+                state.path = None;
+                state.dirty = false;
+                state.dialect = CsvDialect::default();
+                state.last_found = None;
+                state.filter = None;
+                state.cursor = (0, 0);
+                println!(
+                    "SUCCESS: New table with {} rows and {} columns.",
+                    csv.row_size(),
+                    csv.col_size()
+                );
+            }
+
+            "peek" => {
+                let Some(path) = parts.next() else {
+                    problem!(state, "Usage: peek <file_path> [from] [count]");
+                    continue;
+                };
+                let from = parts.next().and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+                let count = parts.next().and_then(|v| v.parse::<usize>().ok()).unwrap_or(20);
+                match PagedCsvTable::open(path) {
+                    Ok(mut paged) => {
+                        let rows = paged.row_size();
+                        for r in from..(from + count).min(rows) {
+                            let mut cells = Vec::with_capacity(paged.col_size());
+                            for c in 0..paged.col_size() {
+                                match paged.read_cell(r, c) {
+                                    Ok(value) => cells.push(format!("\"{}\"", value)),
+                                    Err(e) => {
+                                        problem!(state, "Failed to read '{}': {}", path, e);
+                                        break;
+                                    }
                                 }
-                                Err(e) => println!("PROBLEM: Failed to read CSV: {}", e),
                             }
+                            println!("{}: [{}]", r, cells.join(", "));
                         }
-                        Err(e) => println!("PROBLEM: Cannot open file '{}': {}", path.display(), e),
+                        println!("INFO: '{}' has {} rows and {} columns.", path, rows, paged.col_size());
                     }
-                } else {
-                    println!("PROBLEM: Usage: load <file_path>");
+                    Err(e) => problem!(state, "Cannot open file '{}': {}", path, e),
                 }
             }
 
-            "s" | "save" => {
-                let target_path = if let Some(path) = parts.next() {
-                    let p = std::path::PathBuf::from(path);
-                    state.path = Some(p.clone());
-                    Some(p)
-                } else {
-                    state.path.clone()
+            "export" => {
+                let (format, path) = match (parts.next(), parts.next(), parts.next()) {
+                    (Some(format), Some(path), None) => (format, std::path::PathBuf::from(path)),
+                    _ => {
+                        problem!(state, "Usage: export <json|markdown|tsv> <file_path>");
+                        continue;
+                    }
                 };
+                if !matches!(format, "json" | "markdown" | "md" | "tsv") {
+                    problem!(state, "Unknown export format '{}', expected json, markdown or tsv", format);
+                    continue;
+                }
+                if let Some(readonly) = &state.readonly
+                    && is_same_file(&path, readonly)
+                {
+                    problem!(state, "'{}' was opened read-only. Export to another file.", path.display());
+                    continue;
+                }
+                let written = std::fs::File::create(&path).and_then(|file| {
+                    let writer = std::io::BufWriter::new(file);
+                    match format {
+                        "json" => csv.write_json(writer, JsonLayout::Objects),
+                        "tsv" => csv.write_tsv(writer),
+                        _ => csv.write_markdown(writer),
+                    }
+                });
+                match written {
+                    Ok(()) => println!("SUCCESS: Exported as {} to '{}'.", format, path.display()),
+                    Err(e) => problem!(state, "Cannot export to '{}': {}", path.display(), e),
+                }
+            }
+
+            "s" | "save" => {
+                let mut path = None;
+                let mut bad_flag = false;
+                while let Some(part) = parts.next() {
+                    match part {
+                        "-q" => match parts.next() {
+                            Some("always") => state.dialect = state.dialect.quoting(QuoteStyle::Always),
+                            Some("necessary") => state.dialect = state.dialect.quoting(QuoteStyle::Necessary),
+                            Some("never") => state.dialect = state.dialect.quoting(QuoteStyle::Never),
+                            _ => bad_flag = true,
+                        },
+                        "-t" => match parts.next() {
+                            Some("lf") => state.dialect = state.dialect.terminator(Terminator::Lf),
+                            Some("crlf") => state.dialect = state.dialect.terminator(Terminator::Crlf),
+                            _ => bad_flag = true,
+                        },
+                        _ => path = Some(part),
+                    }
+                }
+                if bad_flag {
+                    problem!(state, "Usage: save [-q always|necessary|never] [-t lf|crlf] [file_path]");
+                    continue;
+                }
+                let target_path = path.map(std::path::PathBuf::from).or_else(|| state.path.clone());
+                if let (Some(target), Some(readonly)) = (&target_path, &state.readonly)
+                    && is_same_file(target, readonly)
+                {
+                    problem!(state, "'{}' was opened read-only. Save to another file with `save <path>`.", target.display());
+                    continue;
+                }
+                state.path = target_path.clone();
 
                 match target_path {
                     Some(path) => match std::fs::File::create(&path) {
                         Ok(file) => {
                             let writer = std::io::BufWriter::new(file);
-                            match csv.write_csv(writer) {
+                            let written = if is_json(&path) {
+                                csv.write_json(writer, JsonLayout::Objects)
+                            } else {
+                                csv.write_csv_with(writer, state.dialect)
+                            };
+                            match written {
                                 Ok(_) => {
                                     println!("SUCCESS: Saved to '{}'.", path.display());
+                                    let sidecar = sidecar_path(&path, "ranges");
+                                    let sidecar_written = if csv.ranges().is_empty() {
+                                        remove_sidecar(&sidecar)
+                                    } else {
+                                        std::fs::File::create(&sidecar).and_then(|file| csv.write_ranges(std::io::BufWriter::new(file)))
+                                    };
+                                    if let Err(e) = sidecar_written {
+                                        println!("WARNING: Named ranges not saved to '{}': {}", sidecar.display(), e);
+                                    }
+                                    let sidecar = sidecar_path(&path, "history");
+                                    let sidecar_written = if csv.history_entries().is_empty() && csv.redo_entries().is_empty() {
+                                        remove_sidecar(&sidecar)
+                                    } else {
+                                        std::fs::File::create(&sidecar).and_then(|file| csv.write_history(std::io::BufWriter::new(file)))
+                                    };
+                                    if let Err(e) = sidecar_written {
+                                        println!("WARNING: Undo history not saved to '{}': {}", sidecar.display(), e);
+                                    }
                                     state.dirty = false;
                                 }
-                                Err(e) => println!("PROBLEM: Failed to write CSV: {}", e),
+                                Err(e) => problem!(state, "Failed to write '{}': {}", path.display(), e),
                             }
                         }
                         Err(e) => {
-                            println!("PROBLEM: Cannot create file '{}': {}", path.display(), e)
+                            problem!(state, "Cannot create file '{}': {}", path.display(), e)
                         }
                     },
                     None => {
-                        println!("PROBLEM: No file path. Use `save <path>` first.");
+                        problem!(state, "No file path. Use `save <path>` first.");
+                    }
+                }
+            }
+
+            "buffers" => {
+                for (index, buffer) in buffers.list.iter().enumerate() {
+                    let buffer_state = if index == buffers.current { &state } else { &buffer.state };
+                    println!(
+                        "{:>3}: {}{}{}",
+                        index,
+                        buffer_state.path.as_ref().map(|p| p.display().to_string()).unwrap_or("untitled".into()),
+                        if buffer_state.dirty { "*" } else { "" },
+                        if index == buffers.current { " (current)" } else { "" }
+                    );
+                }
+            }
+
+            "switch" => match parts.next().and_then(|v| v.parse::<usize>().ok()) {
+                Some(index) if index < buffers.list.len() => {
+                    buffers.switch(index, &mut csv, &mut state);
+                    println!("SUCCESS: Switched to buffer {}.", index);
+                }
+                Some(index) => problem!(state, "There is no buffer {}, see 'buffers'.", index),
+                None => problem!(state, "Usage: switch <n>"),
+            },
+
+            "close" | "close!" => {
+                if state.dirty && cmd == "close" {
+                    println!("WARNING: You have unsaved changes. Save them before closing, or use 'close!'");
+                    state.failed = true;
+                    continue;
+                }
+                buffers.close(&mut csv, &mut state);
+                println!("SUCCESS: Closed, now in buffer {}.", buffers.current);
+            }
+
+            "copy" => {
+                let from = parts.next();
+                let coords: Vec<Option<usize>> = parts.map(|v| v.parse::<usize>().ok()).collect();
+                let (Some(from), &[Some(r1), Some(c1), Some(r2), Some(c2), Some(row), Some(col)]) = (from, &coords[..]) else {
+                    problem!(state, "Usage: copy #<buffer> <r1> <c1> <r2> <c2> <row> <col>");
+                    continue;
+                };
+                let other = match buffers.other(from) {
+                    Ok(other) => other,
+                    Err(e) => {
+                        problem!(state, "{}", e);
+                        continue;
                     }
+                };
+                if !other.has_cell(r1, c1) || !other.has_cell(r2, c2) {
+                    problem!(state, "Buffer {} has no cells ({}, {}) to ({}, {})", from, r1, c1, r2, c2);
+                    continue;
+                }
+                let (top, left) = (r1.min(r2), c1.min(c2));
+                let mut edits = Vec::new();
+                for r in top..=r1.max(r2) {
+                    let values = &other.read_row(r)[left..=c1.max(c2)];
+                    for (offset, value) in values.iter().enumerate() {
+                        edits.push(CellEdit::Write { row: row + r - top, col: col + offset, value: value.to_string() });
+                    }
+                }
+                let count = edits.len();
+                match csv.apply_edits(edits) {
+                    Ok(()) => {
+                        state.dirty = true;
+                        println!("SUCCESS: Copied {} cells from buffer {} to ({}, {}).", count, from, row, col);
+                    }
+                    Err(e) => problem!(state, "Nothing copied, {}", e),
                 }
             }
 
             "quit" | "exit" => {
+                if let (false, Some(index)) = (state.dirty, buffers.unsaved()) {
+                    println!("WARNING: Buffer {} has unsaved changes, switch to it with 'switch {}'.", index, index);
+                    println!("Type 'quit!' to exit without saving, or 'save' them.");
+                    state.failed = true;
+                    continue;
+                }
                 if state.dirty {
                     println!("WARNING: You have unsaved changes.");
                     println!("Type 'quit!' to exit without saving, or 'save' to save.");
+                    state.failed = true;
 
                     // Optional immediate confirmation
                     // continue loop instead of exiting
@@ -255,20 +1800,34 @@ fn cli_test() -> std::io::Result<()> {
                 }
             }
 
+            "tui" => open_grid(&mut csv, &mut state, interactive),
+
             "quit!" => {
                 println!("FORCED: Exiting without saving.");
                 break;
             }
 
             _ => {
-                println!("PROBLEM: Unknown command. Type 'help'.");
+                problem!(state, "Unknown command. Type 'help'.");
             }
         }
     }
+    if let Err(e) = commands.save_history() {
+        println!("WARNING: Command history not saved: {}", e);
+    }
     println!("SUCCESS: Exit the system");
-    Ok(())
+    Ok(interactive || failures == 0)
 }
 
-fn main() -> std::io::Result<()> {
-    cli_test()
+fn main() -> std::io::Result<ExitCode> {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            return Ok(ExitCode::from(2));
+        }
+    };
+    let succeeded = cli_test(options)?;
+    Ok(if succeeded { ExitCode::SUCCESS } else { ExitCode::FAILURE })
 }
@@ -0,0 +1,113 @@
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+
+/// Command names for tab completion, short forms included
+const COMMANDS: &[&str] = &[
+    "ac", "append_col", "append_row", "ar", "begin", "branch", "branches", "buffers", "clear", "close",
+    "close!", "coalesce", "commit", "compact", "copy", "dc", "delete_col", "delete_row", "diff", "down", "dr",
+    "dupc", "duplicate_col", "duplicate_row", "dupr", "exit", "export", "f", "fill", "filter", "find",
+    "find_next", "fn", "goto", "help", "history", "ic", "insert_col", "insert_row", "ir", "left", "load",
+    "load!", "macro", "mc", "move_col", "move_row", "mr", "new", "new!", "open", "p", "paste", "peek",
+    "print", "quit", "quit!", "r", "range", "ranges", "read", "read_col", "read_row", "redo", "replace",
+    "right", "rollback", "s", "save", "series", "set", "sort", "stats", "switch", "tui", "type", "types", "u",
+    "undo", "undo_budget", "undo_limit", "undo_since", "undo_to", "undo_tree", "unrange", "up", "view", "w",
+    "write",
+];
+
+/// Completes command names in the first word, and file paths after it
+pub struct CommandHelper {
+    files: FilenameCompleter,
+}
+
+impl Completer for CommandHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        if before.trim_start().contains(char::is_whitespace) {
+            return self.files.complete(line, pos, ctx);
+        }
+        let start = before.len() - before.trim_start().len();
+        let candidates = COMMANDS
+            .iter()
+            .filter(|command| command.starts_with(&before[start..]))
+            .map(|command| Pair {
+                display: command.to_string(),
+                replacement: format!("{} ", command),
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for CommandHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CommandHelper {}
+
+impl Validator for CommandHelper {}
+
+impl Helper for CommandHelper {}
+
+/// Where commands come from: typed at a prompt with line editing, history
+/// (Up, Ctrl-R) and completion (Tab), or read from a script or pipe
+pub enum Commands {
+    Prompt(Box<Editor<CommandHelper, DefaultHistory>>),
+    Lines(Box<dyn BufRead>),
+}
+
+/// Commands typed at the prompt are kept across sessions in ~/.rust_grid_history
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".rust_grid_history"))
+}
+
+impl Commands {
+    pub fn prompt() -> io::Result<Self> {
+        let mut editor = Editor::new().map_err(io::Error::other)?;
+        editor.set_helper(Some(CommandHelper {
+            files: FilenameCompleter::new(),
+        }));
+        if let Some(path) = history_path() {
+            // There is none before the first session
+            let _ = editor.load_history(&path);
+        }
+        Ok(Commands::Prompt(Box::new(editor)))
+    }
+
+    /// Read the next command into `input`, showing `prompt` if there is one.
+    /// Returns false at the end of the input, Ctrl-D at the prompt.
+    pub fn read(&mut self, prompt: &str, input: &mut String) -> io::Result<bool> {
+        match self {
+            Commands::Prompt(editor) => match editor.readline(prompt) {
+                Ok(line) => {
+                    if !line.trim().is_empty() {
+                        editor.add_history_entry(line.as_str()).map_err(io::Error::other)?;
+                    }
+                    input.push_str(&line);
+                    Ok(true)
+                }
+                // Ctrl-C drops the line being typed
+                Err(ReadlineError::Interrupted) => Ok(true),
+                Err(ReadlineError::Eof) => Ok(false),
+                Err(e) => Err(io::Error::other(e)),
+            },
+            Commands::Lines(lines) => Ok(lines.read_line(input)? > 0),
+        }
+    }
+
+    /// Keep the commands typed at the prompt for the next session
+    pub fn save_history(&mut self) -> io::Result<()> {
+        match (self, history_path()) {
+            (Commands::Prompt(editor), Some(path)) => editor.save_history(&path).map_err(io::Error::other),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use super::csv_read::{ByteRecord, CsvDialect, CsvError, RecordParser, WRITE_BUFFER};
+use std::io;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+/// `CsvReader` for a tokio reader, so that a table uploaded to a server is read
+/// without blocking a worker thread. Records are read with `next_record`.
+pub struct AsyncCsvReader<R: AsyncBufRead + Unpin> {
+    reader: R,
+    parser: RecordParser,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncCsvReader<R> {
+    #[allow(dead_code)]
+    pub fn new(reader: R) -> Self {
+        Self::with_dialect(reader, CsvDialect::default())
+    }
+
+    pub fn with_dialect(reader: R, dialect: CsvDialect) -> Self {
+        Self {
+            reader,
+            parser: RecordParser::new(dialect),
+        }
+    }
+
+    /// The line, counted from 1, that the last record read starts on
+    #[allow(dead_code)]
+    pub fn line(&self) -> usize {
+        self.parser.line()
+    }
+
+    /// The first record. If nothing has been read yet, it is read now and the
+    /// records that follow are the data; empty if there are no records.
+    #[allow(dead_code)]
+    pub async fn headers(&mut self) -> Result<&[String], CsvError> {
+        if self.parser.headers().is_none() {
+            self.next_record().await?;
+        }
+        Ok(self.parser.headers().unwrap_or_default())
+    }
+
+    /// The next record, or None at the end of the input
+    pub async fn next_record(&mut self) -> Result<Option<Vec<String>>, CsvError> {
+        if !self.read_to_record_end().await? {
+            return Ok(None);
+        }
+        self.parser.take_strings().map(Some)
+    }
+
+    /// Read the next record into `record` without decoding it, reusing its buffer;
+    /// false at the end of the input
+    #[allow(dead_code)]
+    pub async fn read_byte_record(&mut self, record: &mut ByteRecord) -> Result<bool, CsvError> {
+        if !self.read_to_record_end().await? {
+            return Ok(false);
+        }
+        self.parser.take_record(record)?;
+        Ok(true)
+    }
+
+    /// Feed the parser up to the end of the next record; false if there is none
+    async fn read_to_record_end(&mut self) -> Result<bool, CsvError> {
+        if !self.parser.start_record() {
+            return Ok(false);
+        }
+        loop {
+            let buf = self.reader.fill_buf().await.map_err(|e| self.parser.io_error(e))?;
+            if buf.is_empty() {
+                // EOF
+                return self.parser.finish();
+            }
+            let (used, ended) = self.parser.feed(buf);
+            self.reader.consume(used);
+            if ended {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+/// `CsvWriter` for a tokio writer, so that a table is downloaded from a server
+/// without blocking a worker thread. Records are buffered, and as an async write
+/// cannot be done on drop, `flush` or `into_inner` must be called after the last.
+pub struct AsyncCsvWriter<W: AsyncWrite + Unpin> {
+    writer: W,
+    dialect: CsvDialect,
+    buffer: Vec<u8>, // encoded records not written yet
+}
+
+impl<W: AsyncWrite + Unpin> AsyncCsvWriter<W> {
+    #[allow(dead_code)]
+    pub fn new(writer: W) -> Self {
+        Self::with_dialect(writer, CsvDialect::default())
+    }
+
+    pub fn with_dialect(writer: W, dialect: CsvDialect) -> Self {
+        Self {
+            writer,
+            dialect,
+            buffer: Vec::with_capacity(WRITE_BUFFER),
+        }
+    }
+
+    /// Encode `record` into the buffer, which is written out once it is full
+    #[allow(dead_code)]
+    pub async fn write_record(&mut self, record: &[String]) -> io::Result<()> {
+        self.dialect.encode_record(record, &mut self.buffer);
+        if self.buffer.len() >= WRITE_BUFFER {
+            self.write_buffer().await?;
+        }
+        Ok(())
+    }
+
+    /// Write the buffered records and flush the inner writer
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.write_buffer().await?;
+        self.writer.flush().await
+    }
+
+    /// Flush, then give back the inner writer
+    #[allow(dead_code)]
+    pub async fn into_inner(mut self) -> io::Result<W> {
+        self.flush().await?;
+        Ok(self.writer)
+    }
+
+    async fn write_buffer(&mut self) -> io::Result<()> {
+        let result = self.writer.write_all(&self.buffer).await;
+        self.buffer.clear();
+        result
+    }
+}
@@ -1,125 +1,917 @@
+use std::fmt;
 use std::io::{self, BufRead, Write};
+use std::ops::Range;
+use std::rc::Rc;
 
-pub struct CsvReader<R: BufRead> {
-    reader: R,
-    field: String,
-    record: Vec<String>,
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+
+/// Encoded records are written to the inner writer in chunks of about this size
+pub(super) const WRITE_BUFFER: usize = 64 * 1024;
+
+/// What is wrong with the file where a `CsvError` happened
+#[derive(Debug)]
+pub enum CsvErrorKind {
+    Io(io::Error),
+    InvalidUtf8,
+    UnterminatedQuote, // at the end of the file, the quote opened at the error's line
+    FieldCount { expected: usize, found: usize }, // as `FlexPolicy` does not allow
+    #[cfg(feature = "serde")]
+    Deserialize(String), // the field could not be turned into the type asked for
+}
+
+/// An error reading a CSV file, at a line counted from 1 like editors do and a field
+/// of the record counted from 1
+#[derive(Debug)]
+pub struct CsvError {
+    pub line: usize,
+    pub field: usize,
+    pub kind: CsvErrorKind,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, field {}: ", self.line, self.field)?;
+        match &self.kind {
+            CsvErrorKind::Io(e) => write!(f, "{}", e),
+            CsvErrorKind::InvalidUtf8 => write!(f, "not valid UTF-8"),
+            CsvErrorKind::UnterminatedQuote => write!(f, "quote not closed before the end of the file"),
+            CsvErrorKind::FieldCount { expected, found } => {
+                write!(f, "{} fields where the first record has {}", found, expected)
+            }
+            #[cfg(feature = "serde")]
+            CsvErrorKind::Deserialize(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            CsvErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<CsvError> for io::Error {
+    fn from(error: CsvError) -> Self {
+        let kind = match &error.kind {
+            CsvErrorKind::Io(e) => e.kind(),
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
+/// How quotes inside a quoted field are escaped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum EscapeStyle {
+    Doubled,  // "" stands for ", as in RFC 4180
+    Char(u8), // the byte after this one is taken as is, like \" or \\
+}
+
+/// What the reader does with records not as wide as the first one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlexPolicy {
+    Flexible,       // they are read as they are
+    Error,          // they are an error
+    PadShorter,     // shorter ones get empty fields, longer ones are an error
+    TruncateLonger, // longer ones lose their last fields, shorter ones get empty fields
+}
+
+/// When the writer quotes fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStyle {
+    Always,
+    Necessary, // only fields with a delimiter, quote, escape or line break in them
+    Never,     // fields are written as they are, even if that breaks the file
+}
+
+/// What the writer ends records with; the reader takes either
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminator {
+    Lf,
+    Crlf, // as Excel writes
+}
+
+/// The format of a CSV file, built like `CsvReader::builder().delimiter(b';').quote(b'\'')`.
+/// The default is RFC 4180: commas, double quotes, escaped by doubling them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvDialect {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub escape: EscapeStyle,
+    pub comment: Option<u8>,    // when reading, lines starting with it are skipped
+    pub skip_blank_lines: bool, // when reading, instead of taking them as one empty field
+    pub flex: FlexPolicy,       // when reading
+    pub quoting: QuoteStyle,    // when writing
+    pub terminator: Terminator, // when writing
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            escape: EscapeStyle::Doubled,
+            comment: None,
+            skip_blank_lines: false,
+            flex: FlexPolicy::Flexible,
+            quoting: QuoteStyle::Necessary,
+            terminator: Terminator::Lf,
+        }
+    }
+}
+
+impl CsvDialect {
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn quote(mut self, quote: u8) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn escape(mut self, escape: EscapeStyle) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Skip lines starting with `comment`, such as `Some(b'#')`, when reading
+    pub fn comment(mut self, comment: Option<u8>) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    /// Skip empty lines when reading, as many exports have around a preamble
+    pub fn skip_blank_lines(mut self, skip: bool) -> Self {
+        self.skip_blank_lines = skip;
+        self
+    }
+
+    pub fn flex(mut self, flex: FlexPolicy) -> Self {
+        self.flex = flex;
+        self
+    }
+
+    pub fn quoting(mut self, quoting: QuoteStyle) -> Self {
+        self.quoting = quoting;
+        self
+    }
+
+    pub fn terminator(mut self, terminator: Terminator) -> Self {
+        self.terminator = terminator;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn reader<R: BufRead>(self, reader: R) -> CsvReader<R> {
+        CsvReader::with_dialect(reader, self)
+    }
+
+    #[allow(dead_code)]
+    pub fn writer<W: Write>(self, writer: W) -> CsvWriter<W> {
+        CsvWriter::with_dialect(writer, self)
+    }
+}
+
+/// A record as the bytes of its fields, back to back in one buffer, for importers
+/// that decode only the fields they keep. Fields are also kept as bytes while being
+/// read, as multi-byte characters may span reads.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ByteRecord {
+    bytes: Vec<u8>,
+    ends: Vec<usize>, // where each field ends in bytes, once it has ended
+}
+
+impl ByteRecord {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of fields
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Where field `index` is in `as_bytes`
+    pub fn range(&self, index: usize) -> Option<Range<usize>> {
+        let end = *self.ends.get(index)?;
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+        Some(start..end)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        self.range(index).map(|range| &self.bytes[range])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        (0..self.len()).filter_map(|index| self.get(index))
+    }
+
+    /// The bytes of all the fields, without delimiters or quotes
+    #[allow(dead_code)]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.ends.last().copied().unwrap_or(0)]
+    }
+
+    fn end_field(&mut self) {
+        self.ends.push(self.bytes.len());
+    }
+
+    /// Keep the first `len` fields, or add empty ones up to `len`
+    fn resize(&mut self, len: usize) {
+        self.ends.truncate(len);
+        self.bytes.truncate(self.ends.last().copied().unwrap_or(0));
+        self.ends.resize(len, self.bytes.len());
+    }
+
+    fn clear(&mut self) {
+        self.bytes.clear();
+        self.ends.clear();
+    }
+}
+
+/// The reading state of a `CsvReader`, fed the bytes by whatever reads them
+pub(crate) struct RecordParser {
+    dialect: CsvDialect,
+    record: ByteRecord, // being read
     in_quotes: bool,
+    quote_seen: bool, // in quotes, right after a quote that may be the first of two
+    escaped: bool,    // in quotes, right after the escape character
+    at_line_start: bool, // nothing but '\r' read of the record yet
+    in_comment: bool,
+    bom_matched: Option<usize>, // bytes of a UTF-8 BOM at the start, until it is ruled out
     done: bool,
+    headers: Option<Vec<String>>, // the first record, once read
+    width: Option<usize>,         // of the first record, once read
+    next_line: usize,   // of the next byte
+    record_line: usize, // where the last record read starts
+    quote_line: usize,  // where the open quote is
 }
 
-impl<R: BufRead> CsvReader<R> {
-    pub fn new(reader: R) -> Self {
+impl RecordParser {
+    pub(crate) fn new(dialect: CsvDialect) -> Self {
         Self {
-            reader,
-            field: String::new(),
-            record: Vec::new(),
+            dialect,
+            record: ByteRecord::new(),
             in_quotes: false,
+            quote_seen: false,
+            escaped: false,
+            at_line_start: true,
+            in_comment: false,
+            bom_matched: Some(0),
             done: false,
+            headers: None,
+            width: None,
+            next_line: 1,
+            record_line: 1,
+            quote_line: 1,
         }
     }
-}
 
-impl<R: BufRead> Iterator for CsvReader<R> {
-    type Item = io::Result<Vec<String>>;
+    pub(super) fn line(&self) -> usize {
+        self.record_line
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    pub(super) fn headers(&self) -> Option<&[String]> {
+        self.headers.as_deref()
+    }
+
+    /// Start a record at the next byte; false once the input has ended
+    pub(crate) fn start_record(&mut self) -> bool {
         if self.done {
-            return None;
+            return false;
         }
+        self.record_line = self.next_line;
+        true
+    }
 
-        loop {
-            let buf = match self.reader.fill_buf() {
-                Ok(buf) => buf,
-                Err(e) => return Some(Err(e)),
-            };
+    /// The error for failing to read the next bytes
+    pub(crate) fn io_error(&self, error: io::Error) -> CsvError {
+        let line = self.next_line;
+        let field = self.record.len() + 1;
+        let kind = CsvErrorKind::Io(error);
+        CsvError { line, field, kind }
+    }
 
-            if buf.is_empty() {
-                // EOF
-                self.done = true;
-                if !self.field.is_empty() || !self.record.is_empty() {
-                    self.record.push(std::mem::take(&mut self.field));
-                    return Some(Ok(std::mem::take(&mut self.record)));
-                }
-                return None;
-            }
-
-            let mut i = 0;
-            while i < buf.len() {
-                let c = buf[i] as char;
-
-                match c {
-                    '"' => {
-                        if self.in_quotes {
-                            if i + 1 < buf.len() && buf[i + 1] == b'"' {
-                                self.field.push('"');
-                                i += 1;
-                            } else {
-                                self.in_quotes = false;
-                            }
-                        } else {
-                            self.in_quotes = true;
-                        }
-                    }
+    /// End the record in progress once the input has ended; false if there is none
+    pub(crate) fn finish(&mut self) -> Result<bool, CsvError> {
+        self.done = true;
+        if self.in_quotes && !self.quote_seen {
+            let line = self.quote_line;
+            let field = self.record.len() + 1;
+            let kind = CsvErrorKind::UnterminatedQuote;
+            return Err(CsvError { line, field, kind });
+        }
+        if self.record.is_empty() && self.record.bytes.is_empty() {
+            return Ok(false);
+        }
+        self.record.end_field();
+        Ok(true)
+    }
 
-                    ',' if !self.in_quotes => {
-                        self.record.push(std::mem::take(&mut self.field));
-                    }
+    /// Parse `buf` up to the end of the first record in it. Returns the number of
+    /// bytes used, all of them if the record goes on, and whether it ended.
+    pub(crate) fn feed(&mut self, buf: &[u8]) -> (usize, bool) {
+        let CsvDialect {
+            delimiter,
+            quote,
+            escape,
+            comment,
+            skip_blank_lines,
+            ..
+        } = self.dialect;
+        let mut i = 0;
+        while i < buf.len() {
+            let b = buf[i];
+            i += 1;
+            let line = self.next_line;
+            if b == b'\n' {
+                self.next_line += 1;
+            }
 
-                    '\n' if !self.in_quotes => {
-                        self.record.push(std::mem::take(&mut self.field));
-                        self.reader.consume(i + 1);
-                        return Some(Ok(std::mem::take(&mut self.record)));
-                    }
+            // A UTF-8 byte order mark is dropped, as spreadsheets write one
+            if let Some(matched) = self.bom_matched {
+                if b == UTF8_BOM[matched] {
+                    self.bom_matched = (matched + 1 < UTF8_BOM.len()).then_some(matched + 1);
+                    continue;
+                }
+                self.bom_matched = None;
+                if matched > 0 {
+                    // Not a BOM after all, and not UTF-8 either
+                    self.record.bytes.extend_from_slice(&UTF8_BOM[..matched]);
+                    self.at_line_start = false;
+                }
+            }
 
-                    '\r' => {}
+            if self.in_comment {
+                if b == b'\n' {
+                    self.in_comment = false;
+                    self.record_line = self.next_line;
+                }
+                continue;
+            }
+            if self.at_line_start && b != b'\r' {
+                if comment == Some(b) {
+                    self.in_comment = true;
+                    continue;
+                }
+                if b == b'\n' && skip_blank_lines {
+                    self.record_line = self.next_line;
+                    continue;
+                }
+                self.at_line_start = false;
+            }
 
-                    _ => self.field.push(c),
+            if self.escaped {
+                self.escaped = false;
+                self.record.bytes.push(b);
+                continue;
+            }
+            if self.quote_seen {
+                self.quote_seen = false;
+                if b == quote {
+                    self.record.bytes.push(b);
+                    continue;
                 }
+                self.in_quotes = false;
+            }
 
-                i += 1;
+            if self.in_quotes {
+                match escape {
+                    EscapeStyle::Char(e) if b == e => self.escaped = true,
+                    _ if b == quote => match escape {
+                        EscapeStyle::Doubled => self.quote_seen = true,
+                        EscapeStyle::Char(_) => self.in_quotes = false,
+                    },
+                    _ if b == b'\r' => {}
+                    _ => self.record.bytes.push(b),
+                }
+            } else if b == quote {
+                self.in_quotes = true;
+                self.quote_line = line;
+            } else if b == delimiter {
+                self.record.end_field();
+            } else if b == b'\n' {
+                self.at_line_start = true;
+                self.record.end_field();
+                return (i, true);
+            } else if b != b'\r' {
+                self.record.bytes.push(b);
             }
+        }
 
-            self.reader.consume(i);
+        (buf.len(), false)
+    }
+
+    /// The record read, with the flex policy applied, as text. The first is kept as
+    /// the headers.
+    pub(super) fn take_strings(&mut self) -> Result<Vec<String>, CsvError> {
+        let fields = self.decode_record().and_then(|mut fields| {
+            if let Some(width) = self.fit_width(fields.len())? {
+                fields.resize(width, String::new());
+            }
+            Ok(fields)
+        });
+        self.record.clear();
+        if let (None, Ok(fields)) = (&self.headers, &fields) {
+            self.headers = Some(fields.clone());
         }
+        fields
+    }
+
+    /// Swap the record read, with the flex policy applied, into `record`. The first
+    /// is kept as the headers, decoded with any bytes that are not UTF-8 replaced.
+    pub(crate) fn take_record(&mut self, record: &mut ByteRecord) -> Result<(), CsvError> {
+        let fitted = self.fit_width(self.record.len());
+        if let Ok(width) = fitted {
+            if let Some(width) = width {
+                self.record.resize(width);
+            }
+            std::mem::swap(&mut self.record, record);
+            if self.headers.is_none() {
+                let fields = record.iter().map(|field| String::from_utf8_lossy(field).into_owned());
+                self.headers = Some(fields.collect());
+            }
+        }
+        self.record.clear();
+        fitted.map(|_| ())
+    }
+
+    /// The fields of the record read, decoded from UTF-8
+    fn decode_record(&self) -> Result<Vec<String>, CsvError> {
+        let record = &self.record;
+        let decode = |(index, field): (usize, &[u8])| match std::str::from_utf8(field) {
+            Ok(field) => Ok(field.to_owned()),
+            Err(_) => {
+                // On the line the field ends on, after any line breaks quoted before it
+                let end = record.ends[index];
+                let breaks = record.bytes[..end].iter().filter(|&&b| b == b'\n').count();
+                Err(CsvError {
+                    line: self.record_line + breaks,
+                    field: index + 1,
+                    kind: CsvErrorKind::InvalidUtf8,
+                })
+            }
+        };
+        record.iter().enumerate().map(decode).collect()
+    }
+
+    /// Apply the flex policy to a record of `found` fields, the first record's deciding
+    /// the width. Returns the width to pad or truncate the record to, if any.
+    fn fit_width(&mut self, found: usize) -> Result<Option<usize>, CsvError> {
+        let expected = *self.width.get_or_insert(found);
+        let fits = match self.dialect.flex {
+            FlexPolicy::Flexible | FlexPolicy::TruncateLonger => true,
+            FlexPolicy::Error => found == expected,
+            FlexPolicy::PadShorter => found <= expected,
+        };
+        if !fits {
+            return Err(CsvError {
+                line: self.record_line,
+                field: found.min(expected) + 1,
+                kind: CsvErrorKind::FieldCount { expected, found },
+            });
+        }
+        Ok((self.dialect.flex != FlexPolicy::Flexible).then_some(expected))
     }
 }
 
-pub struct CsvWriter<W: Write> {
-    writer: W,
+pub struct CsvReader<R: BufRead> {
+    reader: R,
+    parser: RecordParser,
 }
 
-impl<W: Write> CsvWriter<W> {
-    pub fn new(writer: W) -> Self {
-        Self { writer }
+impl CsvReader<io::Empty> {
+    /// A dialect to build on, the reader is made with `CsvDialect::reader`
+    #[allow(dead_code)]
+    pub fn builder() -> CsvDialect {
+        CsvDialect::default()
+    }
+}
+
+impl<R: BufRead> CsvReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_dialect(reader, CsvDialect::default())
     }
 
-    pub fn write_record(&mut self, record: &[String]) -> io::Result<()> {
-        let mut first = true;
-        for field in record {
-            if !first {
-                write!(self.writer, ",")?;
-            } else {
-                first = false;
+    pub fn with_dialect(reader: R, dialect: CsvDialect) -> Self {
+        Self {
+            reader,
+            parser: RecordParser::new(dialect),
+        }
+    }
+
+    /// The line, counted from 1, that the last record read starts on
+    pub fn line(&self) -> usize {
+        self.parser.line()
+    }
+
+    /// The first record. If nothing has been read yet, it is read now and the
+    /// records that follow are the data; empty if there are no records.
+    pub fn headers(&mut self) -> Result<&[String], CsvError> {
+        if self.parser.headers().is_none() {
+            self.next().transpose()?;
+        }
+        Ok(self.parser.headers().unwrap_or_default())
+    }
+
+    /// The records after the headers, with their fields found by header
+    #[allow(dead_code)]
+    pub fn named_records(mut self) -> Result<impl Iterator<Item = Result<NamedRecord, CsvError>>, CsvError> {
+        let headers: Rc<[String]> = self.headers()?.into();
+        Ok(self.map(move |fields| {
+            fields.map(|fields| NamedRecord {
+                headers: Rc::clone(&headers),
+                fields,
+            })
+        }))
+    }
+
+    /// Read the next record into `record` without decoding it, reusing its buffer;
+    /// false at the end of the input
+    #[allow(dead_code)]
+    pub fn read_byte_record(&mut self, record: &mut ByteRecord) -> Result<bool, CsvError> {
+        if !self.read_to_record_end()? {
+            return Ok(false);
+        }
+        self.parser.take_record(record)?;
+        Ok(true)
+    }
+
+    /// Feed the parser up to the end of the next record; false if there is none
+    fn read_to_record_end(&mut self) -> Result<bool, CsvError> {
+        if !self.parser.start_record() {
+            return Ok(false);
+        }
+        loop {
+            let buf = self.reader.fill_buf().map_err(|e| self.parser.io_error(e))?;
+            if buf.is_empty() {
+                // EOF
+                return self.parser.finish();
+            }
+            let (used, ended) = self.parser.feed(buf);
+            self.reader.consume(used);
+            if ended {
+                return Ok(true);
             }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for CsvReader<R> {
+    type Item = Result<Vec<String>, CsvError>;
 
-            // Escape and quote if needed
-            let needs_quotes = field.contains(',') || field.contains('"') || field.contains('\n');
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_to_record_end() {
+            Ok(true) => Some(self.parser.take_strings()),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// A record read after headers, whose fields can be found by the header they are under
+#[derive(Debug, Clone)]
+pub struct NamedRecord {
+    headers: Rc<[String]>,
+    fields: Vec<String>,
+}
+
+impl NamedRecord {
+    /// The field under the first header called `name`, None if there is no such
+    /// header or the record is too short to have it
+    #[allow(dead_code)]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let index = self.headers.iter().position(|header| header == name)?;
+        self.fields.get(index).map(String::as_str)
+    }
+
+    #[allow(dead_code)]
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    #[allow(dead_code)]
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    #[allow(dead_code)]
+    pub fn into_fields(self) -> Vec<String> {
+        self.fields
+    }
+}
+
+impl CsvDialect {
+    /// Append `record` to `out` as a line of this dialect
+    pub(super) fn encode_record(&self, record: &[String], out: &mut Vec<u8>) {
+        let delimiter = self.delimiter;
+        let quote = self.quote;
+        let escape = match self.escape {
+            EscapeStyle::Doubled => quote,
+            EscapeStyle::Char(e) => e,
+        };
+
+        // The dialect's bytes are ASCII, so they never occur inside a UTF-8 character
+        for (index, field) in record.iter().enumerate() {
+            if index > 0 {
+                out.push(delimiter);
+            }
+
+            let bytes = field.as_bytes();
+            let needs_quotes = match self.quoting {
+                QuoteStyle::Always => true,
+                QuoteStyle::Necessary => bytes
+                    .iter()
+                    .any(|&b| b == delimiter || b == quote || b == escape || b == b'\n' || b == b'\r'),
+                QuoteStyle::Never => false,
+            };
             if needs_quotes {
-                write!(self.writer, "\"")?;
-                for c in field.chars() {
-                    if c == '"' {
-                        write!(self.writer, "\"\"")?;
-                    } else {
-                        write!(self.writer, "{}", c)?;
+                out.push(quote);
+                for &b in bytes {
+                    if b == quote || b == escape {
+                        out.push(escape);
                     }
+                    out.push(b);
                 }
-                write!(self.writer, "\"")?;
+                out.push(quote);
             } else {
-                write!(self.writer, "{}", field)?;
+                out.extend_from_slice(bytes);
             }
         }
-        writeln!(self.writer)?;
+        match self.terminator {
+            Terminator::Lf => out.push(b'\n'),
+            Terminator::Crlf => out.extend_from_slice(b"\r\n"),
+        }
+    }
+}
+
+pub struct CsvWriter<W: Write> {
+    writer: Option<W>, // only taken by into_inner
+    dialect: CsvDialect,
+    headers: Option<Vec<String>>, // as written by write_headers
+    buffer: Vec<u8>,              // encoded records not written yet
+}
+
+impl CsvWriter<io::Sink> {
+    /// A dialect to build on, the writer is made with `CsvDialect::writer`
+    #[allow(dead_code)]
+    pub fn builder() -> CsvDialect {
+        CsvDialect::default()
+    }
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_dialect(writer, CsvDialect::default())
+    }
+
+    pub fn with_dialect(writer: W, dialect: CsvDialect) -> Self {
+        Self {
+            writer: Some(writer),
+            dialect,
+            headers: None,
+            buffer: Vec::with_capacity(WRITE_BUFFER),
+        }
+    }
+
+    /// The headers written by `write_headers`, if any
+    #[allow(dead_code)]
+    pub fn headers(&self) -> Option<&[String]> {
+        self.headers.as_deref()
+    }
+
+    /// Write `headers` as the first record, for `write_named` to put fields under
+    #[allow(dead_code)]
+    pub fn write_headers(&mut self, headers: &[String]) -> io::Result<()> {
+        self.write_record(headers)?;
+        self.headers = Some(headers.to_vec());
+        Ok(())
+    }
+
+    /// Write a record with every (header, field) pair's field under its header, and
+    /// empty fields under the headers not given
+    #[allow(dead_code)]
+    pub fn write_named(&mut self, fields: &[(&str, &str)]) -> io::Result<()> {
+        let Some(headers) = &self.headers else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no headers written"));
+        };
+        let mut record = vec![String::new(); headers.len()];
+        for (name, field) in fields {
+            let Some(index) = headers.iter().position(|header| header == name) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("no header '{}'", name),
+                ));
+            };
+            record[index] = field.to_string();
+        }
+        self.write_record(&record)
+    }
+
+    /// Encode `record` into the buffer, which is written out once it is full. Call
+    /// `flush` after the last record to see any error in writing the rest; dropping
+    /// the writer flushes too, but ignores errors.
+    pub fn write_record(&mut self, record: &[String]) -> io::Result<()> {
+        self.dialect.encode_record(record, &mut self.buffer);
+        if self.buffer.len() >= WRITE_BUFFER {
+            self.write_buffer()?;
+        }
+        Ok(())
+    }
+
+    /// Write the buffered records and flush the inner writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write_buffer()?;
+        match &mut self.writer {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Flush, then give back the inner writer
+    #[allow(dead_code)]
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.writer.take().expect("writer is only taken here"))
+    }
+
+    fn write_buffer(&mut self) -> io::Result<()> {
+        if let Some(writer) = &mut self.writer {
+            // Cleared even on error, so a failed write is not repeated by drop
+            let result = writer.write_all(&self.buffer);
+            self.buffer.clear();
+            result?;
+        }
         Ok(())
     }
 }
+
+impl<W: Write> Drop for CsvWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    /// The records of `data`, read through a buffer of `capacity` bytes
+    fn read(data: &[u8], capacity: usize) -> Result<Vec<Vec<String>>, CsvError> {
+        CsvReader::new(BufReader::with_capacity(capacity, data)).collect()
+    }
+
+    fn record(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|field| field.to_string()).collect()
+    }
+
+    /// The records of `data` read in `dialect`, through a small buffer
+    fn read_with(data: &[u8], dialect: CsvDialect) -> Result<Vec<Vec<String>>, CsvError> {
+        dialect.reader(BufReader::with_capacity(3, data)).collect()
+    }
+
+    #[test]
+    fn reads_non_ascii_fields() {
+        let data = "Namn,Ort,Belopp\nÅsa Öberg,Växjö,\"1 200 €\"\n".as_bytes();
+        let records = read(data, 64).unwrap();
+        assert_eq!(records, [record(&["Namn", "Ort", "Belopp"]), record(&["Åsa Öberg", "Växjö", "1 200 €"])]);
+    }
+
+    #[test]
+    fn reads_characters_split_across_buffers() {
+        // Every capacity splits some of the two- and three-byte characters differently
+        let data = "å,ä,ö\n€€,\"ä\nö\",x€\n".as_bytes();
+        let expected = [record(&["å", "ä", "ö"]), record(&["€€", "ä\nö", "x€"])];
+        for capacity in 1..=data.len() {
+            assert_eq!(read(data, capacity).unwrap(), expected, "capacity {}", capacity);
+        }
+    }
+
+    #[test]
+    fn reports_line_and_field_of_invalid_utf8() {
+        for capacity in [1, 64] {
+            let error = read(b"a,b\nc,d\xff\n", capacity).unwrap_err();
+            assert!(matches!(error.kind, CsvErrorKind::InvalidUtf8));
+            assert_eq!((error.line, error.field), (2, 2));
+
+            // After a line break quoted in an earlier field
+            let error = read(b"a,\"b\nc\",\xc3\n", capacity).unwrap_err();
+            assert!(matches!(error.kind, CsvErrorKind::InvalidUtf8));
+            assert_eq!((error.line, error.field), (2, 3));
+        }
+    }
+
+    #[test]
+    fn strips_byte_order_mark() {
+        for capacity in [1, 2, 64] {
+            let records = read(b"\xef\xbb\xbfa,b\n1,2\n", capacity).unwrap();
+            assert_eq!(records, [record(&["a", "b"]), record(&["1", "2"])]);
+
+            // Only at the start of the input
+            let records = read("a\n\u{feff}b\n".as_bytes(), capacity).unwrap();
+            assert_eq!(records, [record(&["a"]), record(&["\u{feff}b"])]);
+
+            // The start of one that does not go on is kept, and is not UTF-8
+            let error = read(b"\xef\xbbx\n", capacity).unwrap_err();
+            assert_eq!((error.line, error.field), (1, 1));
+        }
+    }
+
+    #[test]
+    fn reads_and_writes_other_dialects() {
+        let dialect = CsvReader::builder().delimiter(b';').quote(b'\'').escape(EscapeStyle::Char(b'\\'));
+        let records = read_with(b"a;'b;c'\n'it\\'s';'x\\\\y'\n", dialect).unwrap();
+        assert_eq!(records, [record(&["a", "b;c"]), record(&["it's", "x\\y"])]);
+
+        let dialect = dialect.quoting(QuoteStyle::Necessary).terminator(Terminator::Crlf);
+        let mut writer = dialect.writer(Vec::new());
+        for record in &records {
+            writer.write_record(record).unwrap();
+        }
+        let written = writer.into_inner().unwrap();
+        assert_eq!(written, b"a;'b;c'\r\n'it\\'s';'x\\\\y'\r\n");
+        assert_eq!(read_with(&written, dialect).unwrap(), records);
+
+        let always = CsvDialect::default().quoting(QuoteStyle::Always);
+        let mut written = Vec::new();
+        always.encode_record(&record(&["a", "\"b\""]), &mut written);
+        assert_eq!(written, b"\"a\",\"\"\"b\"\"\"\n");
+    }
+
+    #[test]
+    fn applies_flex_policy() {
+        let data = b"a,b\n1\n1,2,3\n";
+        let records = read_with(data, CsvDialect::default()).unwrap();
+        assert_eq!(records, [record(&["a", "b"]), record(&["1"]), record(&["1", "2", "3"])]);
+
+        let truncated = read_with(data, CsvDialect::default().flex(FlexPolicy::TruncateLonger)).unwrap();
+        assert_eq!(truncated, [record(&["a", "b"]), record(&["1", ""]), record(&["1", "2"])]);
+
+        // Padding takes shorter records only
+        let error = read_with(data, CsvDialect::default().flex(FlexPolicy::PadShorter)).unwrap_err();
+        assert!(matches!(error.kind, CsvErrorKind::FieldCount { expected: 2, found: 3 }));
+        assert_eq!((error.line, error.field), (3, 3));
+
+        // The error is at the first field missing or too many
+        let error = read_with(data, CsvDialect::default().flex(FlexPolicy::Error)).unwrap_err();
+        assert!(matches!(error.kind, CsvErrorKind::FieldCount { expected: 2, found: 1 }));
+        assert_eq!((error.line, error.field), (2, 2));
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let data = b"# totals\na,b\n\n#1,2\n\"#3\",4\n\r\n5,6\n";
+        let dialect = CsvDialect::default().comment(Some(b'#'));
+        let records = read_with(data, dialect).unwrap();
+        assert_eq!(records, [record(&["a", "b"]), record(&[""]), record(&["#3", "4"]), record(&[""]), record(&["5", "6"])]);
+
+        let dialect = dialect.skip_blank_lines(true).flex(FlexPolicy::Error);
+        assert_eq!(read_with(data, dialect).unwrap(), [record(&["a", "b"]), record(&["#3", "4"]), record(&["5", "6"])]);
+
+        // Lines are still counted past the skipped ones
+        let mut reader = dialect.reader(&b"# c\n\na,b\n#\n1\n"[..]);
+        reader.next().unwrap().unwrap();
+        assert_eq!(reader.line(), 3);
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!((error.line, error.field), (5, 2));
+    }
+
+    #[test]
+    fn reads_byte_records_as_they_are() {
+        let mut reader = CsvReader::new(&b"a,\"b\"\"\"\n\xff,,x\n"[..]);
+        let mut record = ByteRecord::new();
+        assert!(reader.read_byte_record(&mut record).unwrap());
+        assert_eq!(record.iter().collect::<Vec<_>>(), [&b"a"[..], b"b\""]);
+
+        // Not UTF-8 is no error, and the buffer is reused
+        assert!(reader.read_byte_record(&mut record).unwrap());
+        assert_eq!(record.len(), 3);
+        assert_eq!(record.get(0), Some(&b"\xff"[..]));
+        assert_eq!(record.range(1), Some(1..1));
+        assert_eq!(record.get(3), None);
+        assert_eq!(record.as_bytes(), b"\xffx");
+        assert_eq!(reader.headers().unwrap(), ["a", "b\""]);
+
+        assert!(!reader.read_byte_record(&mut record).unwrap());
+    }
+
+    #[test]
+    fn reports_unterminated_quote_where_it_opens() {
+        let error = read(b"a,b\n1,\"2\n3\n", 64).unwrap_err();
+        assert!(matches!(error.kind, CsvErrorKind::UnterminatedQuote));
+        assert_eq!((error.line, error.field), (2, 2));
+    }
+}
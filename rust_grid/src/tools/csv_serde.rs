@@ -0,0 +1,598 @@
+use super::csv_read::{CsvError, CsvErrorKind, CsvReader, CsvWriter};
+use serde::de::value::StrDeserializer;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Impossible, Serialize, Serializer};
+use serde::forward_to_deserialize_any;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+/// Why a record could not be turned into a value, or a value into a record
+#[derive(Debug)]
+pub struct RecordError {
+    message: String,
+    field: Option<usize>, // counted from 1, when it is known which field
+}
+
+impl RecordError {
+    fn new(message: String) -> Self {
+        RecordError { message, field: None }
+    }
+
+    /// Blame field `index` (from 0), unless a field inside it was already blamed
+    fn in_field(mut self, index: usize) -> Self {
+        self.field.get_or_insert(index + 1);
+        self
+    }
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl de::Error for RecordError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        RecordError::new(msg.to_string())
+    }
+}
+
+impl ser::Error for RecordError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        RecordError::new(msg.to_string())
+    }
+}
+
+impl From<RecordError> for io::Error {
+    fn from(error: RecordError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+impl<R: BufRead> CsvReader<R> {
+    /// The records after the headers as `T`s, whose fields are found by header for
+    /// structs and maps, and in order for tuples and sequences, like the csv crate's
+    #[allow(dead_code)]
+    pub fn deserialize<T: DeserializeOwned>(mut self) -> impl Iterator<Item = Result<T, CsvError>> {
+        let (headers, error) = match self.headers() {
+            Ok(headers) => (headers.to_vec(), None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        let values = std::iter::from_fn(move || {
+            let fields = match self.next()? {
+                Ok(fields) => fields,
+                Err(e) => return Some(Err(e)),
+            };
+            let deserializer = RecordDeserializer {
+                headers: &headers,
+                fields: &fields,
+            };
+            Some(T::deserialize(deserializer).map_err(|e| CsvError {
+                line: self.line(),
+                field: e.field.unwrap_or(0),
+                kind: CsvErrorKind::Deserialize(e.message),
+            }))
+        });
+        error.map(Err).into_iter().chain(values)
+    }
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Write `value`, a struct, map, tuple or sequence of plain values, as a record.
+    /// The names of the first struct or map's fields are written as headers first.
+    #[allow(dead_code)]
+    pub fn serialize<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        let mut record = RecordSerializer::default();
+        value.serialize(&mut record)?;
+        if let (None, Some(names)) = (self.headers(), record.names) {
+            self.write_headers(&names)?;
+        }
+        self.write_record(&record.fields)
+    }
+}
+
+// --------- Record to value ----------
+struct RecordDeserializer<'a> {
+    headers: &'a [String],
+    fields: &'a [String],
+}
+
+impl<'de> Deserializer<'de> for RecordDeserializer<'_> {
+    type Error = RecordError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RecordError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RecordError> {
+        visitor.visit_map(FieldsByName {
+            fields: self.headers.iter().zip(self.fields).enumerate(),
+            value: None,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, RecordError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RecordError> {
+        visitor.visit_seq(FieldsInOrder(self.fields.iter().enumerate()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, RecordError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, RecordError> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct enum identifier ignored_any
+    }
+}
+
+struct FieldsByName<'a, I: Iterator<Item = (usize, (&'a String, &'a String))>> {
+    fields: I,
+    value: Option<(usize, &'a str)>, // of the key just read
+}
+
+impl<'a, 'de, I: Iterator<Item = (usize, (&'a String, &'a String))>> MapAccess<'de> for FieldsByName<'a, I> {
+    type Error = RecordError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, RecordError> {
+        match self.fields.next() {
+            Some((index, (header, field))) => {
+                self.value = Some((index, field));
+                seed.deserialize(StrDeserializer::new(header)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, RecordError> {
+        let (index, field) = self.value.take().unwrap_or_default();
+        seed.deserialize(FieldDeserializer(field)).map_err(|e| e.in_field(index))
+    }
+}
+
+struct FieldsInOrder<'a>(std::iter::Enumerate<std::slice::Iter<'a, String>>);
+
+impl<'de> SeqAccess<'de> for FieldsInOrder<'_> {
+    type Error = RecordError;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>, RecordError> {
+        match self.0.next() {
+            Some((index, field)) => seed.deserialize(FieldDeserializer(field)).map(Some).map_err(|e| e.in_field(index)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A field, parsed as whatever type is asked for; empty is None for options
+struct FieldDeserializer<'a>(&'a str);
+
+impl FieldDeserializer<'_> {
+    fn parse<T: std::str::FromStr>(&self, what: &str) -> Result<T, RecordError> {
+        self.0
+            .trim()
+            .parse()
+            .map_err(|_| RecordError::new(format!("'{}' is not {}", self.0, what)))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident, $what:expr;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RecordError> {
+                visitor.$visit(self.parse($what)?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for FieldDeserializer<'_> {
+    type Error = RecordError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RecordError> {
+        visitor.visit_str(self.0)
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool, "true or false";
+        deserialize_i8 => visit_i8, "a number";
+        deserialize_i16 => visit_i16, "a number";
+        deserialize_i32 => visit_i32, "a number";
+        deserialize_i64 => visit_i64, "a number";
+        deserialize_u8 => visit_u8, "a positive number";
+        deserialize_u16 => visit_u16, "a positive number";
+        deserialize_u32 => visit_u32, "a positive number";
+        deserialize_u64 => visit_u64, "a positive number";
+        deserialize_f32 => visit_f32, "a number";
+        deserialize_f64 => visit_f64, "a number";
+        deserialize_char => visit_char, "one character";
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RecordError> {
+        if self.0.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RecordError> {
+        if self.0.is_empty() {
+            visitor.visit_unit()
+        } else {
+            Err(RecordError::new(format!("'{}' is not empty", self.0)))
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, RecordError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, RecordError> {
+        // Only unit variants, by name
+        let field: StrDeserializer<RecordError> = self.0.into_deserializer();
+        field.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 str string bytes byte_buf unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+// --------- Value to record ----------
+#[derive(Default)]
+struct RecordSerializer {
+    names: Option<Vec<String>>, // of a struct or map's fields
+    fields: Vec<String>,
+}
+
+impl RecordSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RecordError> {
+        self.fields.push(value.serialize(FieldSerializer)?);
+        Ok(())
+    }
+
+    fn push_named<T: Serialize + ?Sized>(&mut self, name: String, value: &T) -> Result<(), RecordError> {
+        self.names.get_or_insert_with(Vec::new).push(name);
+        self.push(value)
+    }
+}
+
+fn not_a_record() -> RecordError {
+    RecordError::new("a record must be a struct, map, tuple or sequence".to_string())
+}
+
+macro_rules! serialize_not_a_record {
+    ($($method:ident($($ty:ty),*);)*) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<(), RecordError> {
+                Err(not_a_record())
+            }
+        )*
+    };
+}
+
+impl<'a> Serializer for &'a mut RecordSerializer {
+    type Ok = ();
+    type Error = RecordError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), RecordError>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), RecordError>;
+
+    serialize_not_a_record! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), RecordError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<(), RecordError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), RecordError> {
+        Err(not_a_record())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, RecordError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, RecordError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, RecordError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, RecordError> {
+        Err(not_a_record())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer<'a>, RecordError> {
+        Ok(MapSerializer { record: self, key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, RecordError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, RecordError> {
+        Err(not_a_record())
+    }
+}
+
+impl ser::SerializeSeq for &mut RecordSerializer {
+    type Ok = ();
+    type Error = RecordError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RecordError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), RecordError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut RecordSerializer {
+    type Ok = ();
+    type Error = RecordError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RecordError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), RecordError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut RecordSerializer {
+    type Ok = ();
+    type Error = RecordError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RecordError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), RecordError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut RecordSerializer {
+    type Ok = ();
+    type Error = RecordError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, name: &'static str, value: &T) -> Result<(), RecordError> {
+        self.push_named(name.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), RecordError> {
+        Ok(())
+    }
+}
+
+struct MapSerializer<'a> {
+    record: &'a mut RecordSerializer,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for MapSerializer<'_> {
+    type Ok = ();
+    type Error = RecordError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), RecordError> {
+        self.key = Some(key.serialize(FieldSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RecordError> {
+        let key = self.key.take().unwrap_or_default();
+        self.record.push_named(key, value)
+    }
+
+    fn end(self) -> Result<(), RecordError> {
+        Ok(())
+    }
+}
+
+/// A plain value as the text of a field; None and unit are empty
+struct FieldSerializer;
+
+fn not_a_field() -> RecordError {
+    RecordError::new("a field must be a plain value, such as a number or text".to_string())
+}
+
+macro_rules! serialize_displayed {
+    ($($method:ident($ty:ty);)*) => {
+        $(
+            fn $method(self, value: $ty) -> Result<String, RecordError> {
+                Ok(value.to_string())
+            }
+        )*
+    };
+}
+
+impl Serializer for FieldSerializer {
+    type Ok = String;
+    type Error = RecordError;
+    type SerializeSeq = Impossible<String, RecordError>;
+    type SerializeTuple = Impossible<String, RecordError>;
+    type SerializeTupleStruct = Impossible<String, RecordError>;
+    type SerializeTupleVariant = Impossible<String, RecordError>;
+    type SerializeMap = Impossible<String, RecordError>;
+    type SerializeStruct = Impossible<String, RecordError>;
+    type SerializeStructVariant = Impossible<String, RecordError>;
+
+    serialize_displayed! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_i128(i128);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_u128(u128);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<String, RecordError> {
+        Err(not_a_field())
+    }
+
+    fn serialize_none(self) -> Result<String, RecordError> {
+        Ok(String::new())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, RecordError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, RecordError> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, RecordError> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, RecordError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<String, RecordError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, RecordError> {
+        Err(not_a_field())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, RecordError> {
+        Err(not_a_field())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, RecordError> {
+        Err(not_a_field())
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, RecordError> {
+        Err(not_a_field())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, RecordError> {
+        Err(not_a_field())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, RecordError> {
+        Err(not_a_field())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, RecordError> {
+        Err(not_a_field())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, RecordError> {
+        Err(not_a_field())
+    }
+}
@@ -0,0 +1,70 @@
+use encoding_rs::{Decoder, Encoding};
+use std::io::{self, BufRead, Read};
+
+const CHUNK: usize = 8 * 1024;
+
+/// Reads a file in a legacy encoding, such as Windows-1252 from older accounting
+/// software, as UTF-8 for `CsvReader`. A UTF-8 or UTF-16 byte order mark overrides
+/// the encoding. Malformed bytes are read as U+FFFD.
+pub struct DecodingReader<R: Read> {
+    reader: R,
+    decoder: Decoder,
+    input: Vec<u8>,
+    output: Vec<u8>, // decoded, from `position` on not consumed yet
+    position: usize,
+    done: bool,
+}
+
+impl<R: Read> DecodingReader<R> {
+    pub fn new(reader: R, encoding: &'static Encoding) -> Self {
+        Self {
+            reader,
+            decoder: encoding.new_decoder(),
+            input: vec![0; CHUNK],
+            output: Vec::new(),
+            position: 0,
+            done: false,
+        }
+    }
+}
+
+/// The encoding called `label`, like "windows-1252", "latin1" or "utf-16le"
+pub fn encoding_for_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.as_bytes())
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for DecodingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.position == self.output.len() && !self.done {
+            let read = self.reader.read(&mut self.input)?;
+            let last = read == 0;
+            let capacity = self
+                .decoder
+                .max_utf8_buffer_length(read)
+                .ok_or_else(|| io::Error::other("decoded chunk too large"))?;
+            self.output.resize(capacity, 0);
+            // With room for the worst case, all the input is decoded at once
+            let (_, _, written, _) = self
+                .decoder
+                .decode_to_utf8(&self.input[..read], &mut self.output, last);
+            self.output.truncate(written);
+            self.position = 0;
+            self.done = last;
+        }
+        Ok(&self.output[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.output.len());
+    }
+}
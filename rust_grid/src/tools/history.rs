@@ -1,11 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::mem;
+use std::time::{Duration, SystemTime};
+
 pub trait TargetMementoTrait<T> {
     fn apply_memento(self: &mut Self, memento: &T) -> T;
 }
 
-#[derive(Debug, Default)]
+/// Mementos that can be squashed into one, which transactions need
+pub trait CompositeMementoTrait: Sized {
+    /// One memento doing what `mementos`, oldest first, do when applied newest first
+    fn composite(mementos: Vec<Self>) -> Self;
+}
+
+/// Mementos that can tell how much memory they hold, which byte budgets need
+pub trait MementoSizeTrait {
+    /// Bytes held, its own and those it owns on the heap
+    fn memory_usage(&self) -> usize;
+}
+
+/// A memento with the description of its step, such as "Delete row 4", empty if none
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry<T> {
+    memento: T,
+    label: String,
+    id: u64, // the step's, kept when it is undone and redone
+    time: SystemTime, // when it was recorded, or last merged into
+    #[serde(skip)]
+    key: Option<String>, // what it changed, while later changes to it can be merged in
+}
+
+/// Steps that were undone and then replaced by new ones, kept in branching mode.
+/// They can be redone from right after the step `parent` (from the start if None).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Branch<T> {
+    id: usize,
+    parent: Option<u64>,
+    redo_stack: Vec<Entry<T>>,
+}
+
+/// A branch that can be switched to from the current line of steps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchInfo {
+    pub id: usize,
+    pub fork: usize,   // number of steps before the branch starts
+    pub steps: usize,
+    pub label: String, // of the branch's first step
+}
+
+type Observer = Box<dyn FnMut(&str)>;
+type MementoSize<T> = fn(&T) -> usize;
+
+/// Functions called with the label of every step undone, redone or recorded
+#[derive(Default)]
+struct Observers {
+    undo: Vec<Observer>,
+    redo: Vec<Observer>,
+    record: Vec<Observer>,
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Observers({} undo, {} redo, {} record)",
+            self.undo.len(),
+            self.redo.len(),
+            self.record.len()
+        )
+    }
+}
+
+fn notify(observers: &mut [Observer], label: &str) {
+    for observer in observers {
+        observer(label);
+    }
+}
+
+/// Serializable when the mementos are, so that the undo steps outlive a session.
+/// Open transactions are not saved.
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct History<T: Clone> {
-    undo_stack: Vec<T>,
-    redo_stack: Vec<T>,
+    undo_stack: Vec<Entry<T>>,
+    redo_stack: Vec<Entry<T>>,
+    #[serde(skip)]
+    transactions: Vec<usize>, // undo stack length when each open transaction began
+    limit: Option<usize>,     // most undo steps kept, unbounded when None
+    #[serde(skip)]
+    budget: Option<(usize, MementoSize<T>)>, // most bytes kept, and how to size a memento
+    next_id: u64,
+    branching: bool, // keep undone steps when new ones are recorded, like an undo tree
+    branches: Vec<Branch<T>>,
+    next_branch_id: usize,
+    #[serde(skip)]
+    observers: Observers,
 }
 
 #[allow(unused_assignments)]
@@ -14,12 +102,211 @@ impl<T: Clone> History<T> {
         Self {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            transactions: Vec::new(),
+            limit: None,
+            budget: None,
+            next_id: 0,
+            branching: false,
+            branches: Vec::new(),
+            next_branch_id: 0,
+            observers: Observers::default(),
         }
     }
 
+    /// A history keeping at most `limit` undo steps, forgetting the oldest ones
+    #[allow(dead_code)]
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Self::new()
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Bytes held by the steps that can be undone, redone or switched to
+    pub fn memory_usage(&self) -> usize
+    where
+        T: MementoSizeTrait,
+    {
+        self.entries_usage(T::memory_usage)
+    }
+
+    /// Keep the steps within `budget` bytes (None for no budget), forgetting the
+    /// branches and then the oldest undo steps beyond it, now and whenever recording
+    pub fn set_byte_budget(&mut self, budget: Option<usize>)
+    where
+        T: MementoSizeTrait,
+    {
+        self.budget = budget.map(|bytes| (bytes, T::memory_usage as MementoSize<T>));
+        self.enforce_limit();
+    }
+
+    #[allow(dead_code)]
+    pub fn byte_budget(&self) -> Option<usize> {
+        self.budget.map(|(bytes, _)| bytes)
+    }
+
+    fn entries_usage(&self, size: MementoSize<T>) -> usize {
+        let entry_usage = |entry: &Entry<T>| {
+            mem::size_of::<Entry<T>>() - mem::size_of::<T>()
+                + entry.label.capacity()
+                + entry.key.as_ref().map_or(0, String::capacity)
+                + size(&entry.memento)
+        };
+        let branches = self.branches.iter().flat_map(|branch| branch.redo_stack.iter());
+        self.undo_stack
+            .iter()
+            .chain(self.redo_stack.iter())
+            .chain(branches)
+            .map(entry_usage)
+            .sum()
+    }
+
+    /// Call `observer` with the label of every step undone, `undo_to` and switching
+    /// branches included
+    pub fn on_undo<F: FnMut(&str) + 'static>(&mut self, observer: F) {
+        self.observers.undo.push(Box::new(observer));
+    }
+
+    /// Call `observer` with the label of every step redone
+    pub fn on_redo<F: FnMut(&str) + 'static>(&mut self, observer: F) {
+        self.observers.redo.push(Box::new(observer));
+    }
+
+    /// Call `observer` with the label of every step recorded, merged ones included
+    pub fn on_record<F: FnMut(&str) + 'static>(&mut self, observer: F) {
+        self.observers.record.push(Box::new(observer));
+    }
+
+    /// Take the steps and settings of `other`, such as a history read back from a
+    /// file, keeping this one's observers and byte budget, which are not saved with it
+    pub fn replace(&mut self, other: Self) {
+        let observers = mem::take(&mut self.observers);
+        let budget = self.budget;
+        *self = other;
+        self.observers = observers;
+        self.budget = budget;
+        self.enforce_limit();
+    }
+
+    /// Keep at most `limit` undo steps (None for no limit), dropping the oldest ones now
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+        self.enforce_limit();
+    }
+
     pub fn record(self: &mut Self, memento: T) {
-        self.undo_stack.push(memento);
+        self.record_labeled(memento, "");
+    }
+
+    /// Record a step described by `label`, as listed by `entries`
+    pub fn record_labeled<L: Into<String>>(&mut self, memento: T, label: L) {
+        let entry = self.new_entry(memento, label.into());
+        notify(&mut self.observers.record, &entry.label);
+        if self.branching && !self.redo_stack.is_empty() {
+            let parent = self.undo_stack.last().map(|entry| entry.id);
+            let redo_stack = mem::take(&mut self.redo_stack);
+            self.add_branch(parent, redo_stack);
+        }
+        self.undo_stack.push(entry);
         self.redo_stack.clear();
+        self.enforce_limit();
+    }
+
+    fn new_entry(&mut self, memento: T, label: String) -> Entry<T> {
+        self.next_id += 1;
+        Entry {
+            memento,
+            label,
+            id: self.next_id,
+            time: SystemTime::now(),
+            key: None,
+        }
+    }
+
+    /// Like `record_labeled`, but merged into the last undo step when that was also
+    /// recorded with `key` and nothing happened since, so that a burst of changes to
+    /// one target, such as typing into a cell, is undone at once. Undoing, redoing or
+    /// recording anything else ends the burst.
+    pub fn record_coalesced<L: Into<String>>(&mut self, memento: T, label: L, key: &str)
+    where
+        T: CompositeMementoTrait,
+    {
+        let start = self.transactions.last().copied().unwrap_or(0);
+        let mergeable = self.redo_stack.is_empty()
+            && self.undo_stack.len() > start
+            && self.undo_stack.last().is_some_and(|entry| entry.key.as_deref() == Some(key));
+        if mergeable {
+            let last = self.undo_stack.pop().unwrap();
+            let label = label.into();
+            notify(&mut self.observers.record, &label);
+            self.undo_stack.push(Entry {
+                memento: T::composite(vec![last.memento, memento]),
+                label,
+                time: SystemTime::now(),
+                ..last
+            });
+        } else {
+            self.record_labeled(memento, label);
+            if let Some(entry) = self.undo_stack.last_mut() {
+                entry.key = Some(key.to_string());
+            }
+        }
+    }
+
+    /// Labels of the undo steps, oldest first; `undo_to(i)` undoes those from i on
+    pub fn entries(&self) -> Vec<&str> {
+        self.undo_stack.iter().map(|entry| entry.label.as_str()).collect()
+    }
+
+    /// Labels of the redo steps, the next one to redo first
+    pub fn redo_entries(&self) -> Vec<&str> {
+        self.redo_stack.iter().rev().map(|entry| entry.label.as_str()).collect()
+    }
+
+    /// When and what the undo steps recorded at or after `time` were, oldest first
+    pub fn entries_since(&self, time: SystemTime) -> Vec<(SystemTime, &str)> {
+        self.undo_stack
+            .iter()
+            .filter(|entry| entry.time >= time)
+            .map(|entry| (entry.time, entry.label.as_str()))
+            .collect()
+    }
+
+    /// Steps of an open transaction are kept until it is committed as one
+    fn enforce_limit(&mut self) {
+        if !self.transactions.is_empty() {
+            return;
+        }
+        if let Some(limit) = self.limit {
+            let excess = self.undo_stack.len().saturating_sub(limit);
+            if excess > 0 {
+                self.forget_oldest(excess);
+            }
+        }
+        if let Some((budget, size)) = self.budget {
+            // Branches go first, the steps that cannot be redone from here
+            while self.entries_usage(size) > budget {
+                if !self.branches.is_empty() {
+                    self.branches.remove(0);
+                } else if !self.undo_stack.is_empty() {
+                    self.forget_oldest(1);
+                } else {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn forget_oldest(&mut self, count: usize) {
+        self.undo_stack.drain(..count);
+        // The start of the history cannot be reached anymore
+        self.branches.retain(|branch| branch.parent.is_some());
+        self.prune_branches();
     }
 
     pub fn undoable(self: &mut Self) -> bool {
@@ -31,21 +318,487 @@ impl<T: Clone> History<T> {
     }
 
     pub fn undo<U: TargetMementoTrait<T>>(self: &mut Self, target: &mut U) {
-        if let Some(memento) = self.undo_stack.pop() {
-            let inverse = target.apply_memento(&memento);
-            self.redo_stack.push(inverse);
+        if let Some(entry) = self.undo_stack.pop() {
+            let inverse = target.apply_memento(&entry.memento);
+            notify(&mut self.observers.undo, &entry.label);
+            self.redo_stack.push(Entry {
+                memento: inverse,
+                key: None,
+                ..entry
+            });
         }
     }
 
     pub fn redo<U: TargetMementoTrait<T>>(self: &mut Self, target: &mut U) {
-        if let Some(memento) = self.redo_stack.pop() {
-            let inverse = target.apply_memento(&memento);
-            self.undo_stack.push(inverse);
+        if let Some(entry) = self.redo_stack.pop() {
+            let inverse = target.apply_memento(&entry.memento);
+            notify(&mut self.observers.redo, &entry.label);
+            self.undo_stack.push(Entry {
+                memento: inverse,
+                key: None,
+                ..entry
+            });
+        }
+    }
+
+    /// Undo steps until only the first `index` entries are left, so they can all be redone
+    pub fn undo_to<U: TargetMementoTrait<T>>(&mut self, index: usize, target: &mut U) {
+        while self.undo_stack.len() > index {
+            self.undo(target);
+        }
+    }
+
+    /// Undo the steps recorded in the last `duration`, returning how many there were.
+    /// Steps are undone newest first, stopping at the first older one.
+    pub fn undo_since<U: TargetMementoTrait<T>>(&mut self, duration: Duration, target: &mut U) -> usize {
+        let since = SystemTime::now().checked_sub(duration).unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut undone = 0;
+        while self.undo_stack.last().is_some_and(|entry| entry.time >= since) {
+            self.undo(target);
+            undone += 1;
+        }
+        undone
+    }
+
+    /// Start grouping the mementos recorded from now on, until `commit` or `rollback`.
+    /// Transactions nest: committing an inner one leaves a single step in the outer one.
+    pub fn begin_transaction(&mut self) {
+        self.transactions.push(self.undo_stack.len());
+    }
+
+    /// Whether a transaction has begun and is not committed or rolled back yet
+    pub fn in_transaction(&self) -> bool {
+        !self.transactions.is_empty()
+    }
+
+    /// Squash the mementos recorded since `begin_transaction` into one undo step,
+    /// labeled with their different labels
+    pub fn commit(&mut self)
+    where
+        T: CompositeMementoTrait,
+    {
+        self.commit_labeled(None);
+    }
+
+    /// Like `commit`, with `label` for the step when there is one
+    pub fn commit_labeled(&mut self, label: Option<&str>)
+    where
+        T: CompositeMementoTrait,
+    {
+        let Some(start) = self.transactions.pop() else {
+            panic!("commit called without a transaction");
+        };
+        let start = start.min(self.undo_stack.len());
+        let entries = self.undo_stack.split_off(start);
+        if !entries.is_empty() {
+            let label = label.map(String::from).unwrap_or_else(|| {
+                let mut labels: Vec<&str> = Vec::new();
+                for entry in &entries {
+                    if !entry.label.is_empty() && !labels.contains(&entry.label.as_str()) {
+                        labels.push(&entry.label);
+                    }
+                }
+                labels.join(", ")
+            });
+            let memento = T::composite(entries.into_iter().map(|entry| entry.memento).collect());
+            let entry = self.new_entry(memento, label);
+            self.undo_stack.push(entry);
+            self.prune_branches();
+        }
+        self.enforce_limit();
+    }
+
+    /// Undo and forget the mementos recorded since `begin_transaction`
+    pub fn rollback<U: TargetMementoTrait<T>>(&mut self, target: &mut U) {
+        let Some(start) = self.transactions.pop() else {
+            panic!("rollback called without a transaction");
+        };
+        while self.undo_stack.len() > start {
+            let entry = self.undo_stack.pop().unwrap();
+            target.apply_memento(&entry.memento);
+        }
+        self.prune_branches();
+    }
+
+    /// In branching mode, steps undone before recording new ones are kept as a branch
+    /// instead of being forgotten. Turning it off forgets the branches.
+    pub fn set_branching(&mut self, branching: bool) {
+        self.branching = branching;
+        if !branching {
+            self.branches.clear();
+        }
+    }
+
+    /// The branches starting from a step of the current line (the undo steps and the
+    /// redo steps), by where they start
+    pub fn branches(&self) -> Vec<BranchInfo> {
+        let mut branches: Vec<BranchInfo> = self
+            .branches
+            .iter()
+            .filter_map(|branch| {
+                Some(BranchInfo {
+                    id: branch.id,
+                    fork: self.fork_depth(branch.parent)?,
+                    steps: branch.redo_stack.len(),
+                    label: branch.redo_stack.last()?.label.clone(),
+                })
+            })
+            .collect();
+        branches.sort_by_key(|branch| (branch.fork, branch.id));
+        branches
+    }
+
+    /// Go to the end of branch `id`: undo or redo to where it starts, keep the steps
+    /// after that as a new branch, and redo the branch's steps. Returns false, changing
+    /// nothing, if `id` is not one of `branches`.
+    pub fn switch_branch<U: TargetMementoTrait<T>>(&mut self, id: usize, target: &mut U) -> bool {
+        let Some(index) = self.branches.iter().position(|branch| branch.id == id) else {
+            return false;
+        };
+        let Some(fork) = self.fork_depth(self.branches[index].parent) else {
+            return false;
+        };
+        let branch = self.branches.remove(index);
+        while self.undo_stack.len() > fork {
+            self.undo(target);
+        }
+        while self.undo_stack.len() < fork {
+            self.redo(target);
+        }
+        let current = mem::replace(&mut self.redo_stack, branch.redo_stack);
+        if !current.is_empty() {
+            self.add_branch(branch.parent, current);
+        }
+        while !self.redo_stack.is_empty() {
+            self.redo(target);
+        }
+        true
+    }
+
+    /// Number of steps of the current line up to and including step `parent`
+    fn fork_depth(&self, parent: Option<u64>) -> Option<usize> {
+        let Some(parent) = parent else {
+            return Some(0);
+        };
+        let mut line = self.undo_stack.iter().chain(self.redo_stack.iter().rev());
+        line.position(|entry| entry.id == parent).map(|position| position + 1)
+    }
+
+    fn add_branch(&mut self, parent: Option<u64>, redo_stack: Vec<Entry<T>>) {
+        self.next_branch_id += 1;
+        self.branches.push(Branch {
+            id: self.next_branch_id,
+            parent,
+            redo_stack,
+        });
+    }
+
+    /// Forget branches starting from steps that no longer exist anywhere
+    fn prune_branches(&mut self) {
+        loop {
+            let mut ids = std::collections::HashSet::new();
+            ids.extend(self.undo_stack.iter().map(|entry| entry.id));
+            ids.extend(self.redo_stack.iter().map(|entry| entry.id));
+            for branch in &self.branches {
+                ids.extend(branch.redo_stack.iter().map(|entry| entry.id));
+            }
+            let before = self.branches.len();
+            self.branches.retain(|branch| branch.parent.is_none_or(|parent| ids.contains(&parent)));
+            if self.branches.len() == before {
+                break;
+            }
         }
     }
 
     pub fn clear(self: &mut Self) {
         self.undo_stack.clear();
         self.redo_stack.clear();
+        self.transactions.clear();
+        self.branches.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cells to set, as (index, value); applying it gives back the old values
+    #[derive(Debug, Clone)]
+    struct Sets(Vec<(usize, i32)>);
+
+    impl TargetMementoTrait<Sets> for Vec<i32> {
+        fn apply_memento(&mut self, memento: &Sets) -> Sets {
+            // Newest first, so the inverse, applied the same way, starts with the oldest
+            let mut inverse = Vec::with_capacity(memento.0.len());
+            for &(index, value) in memento.0.iter().rev() {
+                inverse.push((index, mem::replace(&mut self[index], value)));
+            }
+            Sets(inverse)
+        }
+    }
+
+    impl CompositeMementoTrait for Sets {
+        fn composite(mementos: Vec<Self>) -> Self {
+            Sets(mementos.into_iter().flat_map(|memento| memento.0).collect())
+        }
+    }
+
+    impl MementoSizeTrait for Sets {
+        fn memory_usage(&self) -> usize {
+            mem::size_of::<Self>() + self.0.capacity() * mem::size_of::<(usize, i32)>()
+        }
+    }
+
+    /// Set `cells[index]` to `value` as a step labeled `label`
+    fn set(history: &mut History<Sets>, cells: &mut [i32], index: usize, value: i32, label: &str) {
+        let old = mem::replace(&mut cells[index], value);
+        history.record_labeled(Sets(vec![(index, old)]), label);
+    }
+
+    #[test]
+    fn replace_keeps_the_byte_budget_and_observers() {
+        let mut cells = vec![0; 4];
+        let mut saved = History::new();
+        for value in 1..=50 {
+            set(&mut saved, &mut cells, 0, value, "set");
+        }
+        let full = saved.memory_usage();
+
+        let mut history = History::new();
+        let recorded = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = std::rc::Rc::clone(&recorded);
+        history.on_record(move |_| counter.set(counter.get() + 1));
+        history.set_byte_budget(Some(full / 2));
+        history.replace(saved);
+        assert_eq!(history.byte_budget(), Some(full / 2));
+        assert!(history.memory_usage() <= full / 2);
+        set(&mut history, &mut cells, 1, 1, "after");
+        assert_eq!(recorded.get(), 1);
+    }
+
+    /// Labels given to the observers, in order
+    fn observed() -> (std::rc::Rc<std::cell::RefCell<Vec<String>>>, impl FnMut(&str) + 'static) {
+        let labels = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = std::rc::Rc::clone(&labels);
+        (labels, move |label: &str| sink.borrow_mut().push(label.to_string()))
+    }
+
+    #[test]
+    fn undoes_and_redoes_in_order() {
+        let mut cells = vec![0; 2];
+        let mut history = History::new();
+        set(&mut history, &mut cells, 0, 1, "first");
+        set(&mut history, &mut cells, 0, 2, "second");
+        history.undo(&mut cells);
+        assert_eq!(cells, [1, 0]);
+        assert_eq!((history.entries(), history.redo_entries()), (vec!["first"], vec!["second"]));
+        history.redo(&mut cells);
+        assert_eq!(cells, [2, 0]);
+        history.undo_to(0, &mut cells);
+        assert_eq!(cells, [0, 0]);
+        assert_eq!(history.redo_entries(), ["first", "second"]);
+
+        // Recording drops the steps that could be redone
+        set(&mut history, &mut cells, 1, 5, "third");
+        assert!(!history.redoable());
+    }
+
+    #[test]
+    fn nested_transactions_commit_as_one_step() {
+        let mut cells = vec![0; 3];
+        let mut history = History::new();
+        history.begin_transaction();
+        set(&mut history, &mut cells, 0, 1, "a");
+        history.begin_transaction();
+        set(&mut history, &mut cells, 1, 1, "b");
+        set(&mut history, &mut cells, 1, 2, "b");
+        history.commit();
+        assert_eq!(history.entries(), ["a", "b"]);
+        set(&mut history, &mut cells, 2, 1, "");
+        history.commit();
+        assert!(!history.in_transaction());
+        assert_eq!(history.entries(), ["a, b"]);
+
+        history.undo(&mut cells);
+        assert_eq!(cells, [0, 0, 0]);
+        history.redo(&mut cells);
+        assert_eq!(cells, [1, 2, 1]);
+
+        history.begin_transaction();
+        set(&mut history, &mut cells, 0, 9, "c");
+        history.commit_labeled(Some("Fill"));
+        assert_eq!(history.entries(), ["a, b", "Fill"]);
+    }
+
+    #[test]
+    fn rollback_undoes_only_its_own_transaction() {
+        let mut cells = vec![0; 2];
+        let mut history = History::new();
+        history.begin_transaction();
+        set(&mut history, &mut cells, 0, 1, "kept");
+        history.begin_transaction();
+        set(&mut history, &mut cells, 1, 1, "dropped");
+        set(&mut history, &mut cells, 0, 2, "dropped");
+        history.rollback(&mut cells);
+        assert_eq!(cells, [1, 0]);
+        assert!(history.in_transaction());
+        history.commit();
+        assert_eq!(history.entries(), ["kept"]);
+
+        history.begin_transaction();
+        set(&mut history, &mut cells, 1, 3, "dropped");
+        history.rollback(&mut cells);
+        assert_eq!(cells, [1, 0]);
+        assert_eq!(history.entries(), ["kept"]);
+        assert!(!history.redoable());
+    }
+
+    #[test]
+    fn limit_waits_for_open_transactions() {
+        let mut cells = vec![0; 1];
+        let mut history = History::with_limit(2);
+        for value in 1..=3 {
+            set(&mut history, &mut cells, 0, value, &value.to_string());
+        }
+        assert_eq!(history.entries(), ["2", "3"]);
+
+        history.begin_transaction();
+        set(&mut history, &mut cells, 0, 4, "4");
+        set(&mut history, &mut cells, 0, 5, "5");
+        assert_eq!(history.entries().len(), 4);
+        history.commit();
+        assert_eq!(history.entries(), ["3", "4, 5"]);
+
+        history.set_limit(Some(1));
+        assert_eq!(history.entries(), ["4, 5"]);
+        history.undo(&mut cells);
+        assert_eq!(cells, [3]);
+    }
+
+    #[test]
+    fn coalesces_steps_to_the_same_target() {
+        let mut cells = vec![0; 2];
+        let mut history = History::new();
+        for value in 1..=3 {
+            let old = mem::replace(&mut cells[0], value);
+            history.record_coalesced(Sets(vec![(0, old)]), format!("Type {}", value), "cell 0");
+        }
+        assert_eq!(history.entries(), ["Type 3"]);
+
+        // Another key, or an undo in between, ends the burst
+        let old = mem::replace(&mut cells[1], 1);
+        history.record_coalesced(Sets(vec![(1, old)]), "Type", "cell 1");
+        history.undo(&mut cells);
+        history.redo(&mut cells);
+        let old = mem::replace(&mut cells[1], 2);
+        history.record_coalesced(Sets(vec![(1, old)]), "Type", "cell 1");
+        assert_eq!(history.entries(), ["Type 3", "Type", "Type"]);
+
+        history.undo_to(0, &mut cells);
+        assert_eq!(cells, [0, 0]);
+    }
+
+    #[test]
+    fn coalescing_stays_inside_a_transaction() {
+        let mut cells = vec![0; 1];
+        let mut history = History::new();
+        history.record_coalesced(Sets(vec![(0, 0)]), "before", "cell");
+        cells[0] = 1;
+        history.begin_transaction();
+        for value in 2..=3 {
+            let old = mem::replace(&mut cells[0], value);
+            history.record_coalesced(Sets(vec![(0, old)]), "inside", "cell");
+        }
+        assert_eq!(history.entries(), ["before", "inside"]);
+        history.rollback(&mut cells);
+        assert_eq!(cells, [1]);
+        assert_eq!(history.entries(), ["before"]);
+    }
+
+    #[test]
+    fn switches_between_branches() {
+        let mut cells = vec![0; 1];
+        let mut history = History::new();
+        history.set_branching(true);
+        set(&mut history, &mut cells, 0, 1, "one");
+        set(&mut history, &mut cells, 0, 2, "two");
+        history.undo(&mut cells);
+        set(&mut history, &mut cells, 0, 3, "three");
+
+        let branches = history.branches();
+        assert_eq!(branches.len(), 1);
+        let BranchInfo { id, fork, steps, ref label } = branches[0];
+        assert_eq!((fork, steps, label.as_str()), (1, 1, "two"));
+
+        assert!(history.switch_branch(id, &mut cells));
+        assert_eq!(cells, [2]);
+        assert_eq!(history.entries(), ["one", "two"]);
+        let branches = history.branches();
+        assert_eq!((branches.len(), branches[0].label.as_str()), (1, "three"));
+        assert!(!history.switch_branch(id, &mut cells));
+
+        // Without branching the undone steps are gone
+        history.set_branching(false);
+        assert!(history.branches().is_empty());
+    }
+
+    #[test]
+    fn observers_see_every_label() {
+        let mut cells = vec![0; 1];
+        let mut history = History::new();
+        let (undone, on_undo) = observed();
+        let (redone, on_redo) = observed();
+        let (recorded, on_record) = observed();
+        history.on_undo(on_undo);
+        history.on_redo(on_redo);
+        history.on_record(on_record);
+        set(&mut history, &mut cells, 0, 1, "a");
+        set(&mut history, &mut cells, 0, 2, "b");
+        history.undo_to(0, &mut cells);
+        history.redo(&mut cells);
+        assert_eq!(*recorded.borrow(), ["a", "b"]);
+        assert_eq!(*undone.borrow(), ["b", "a"]);
+        assert_eq!(*redone.borrow(), ["a"]);
+    }
+
+    #[test]
+    fn undoes_the_steps_since_a_time() {
+        let mut cells = vec![0; 2];
+        let mut history = History::new();
+        let start = SystemTime::now();
+        set(&mut history, &mut cells, 0, 1, "old");
+        set(&mut history, &mut cells, 1, 1, "new");
+        assert_eq!(history.entries_since(start).len(), 2);
+
+        history.undo_stack[0].time = start - Duration::from_secs(3600);
+        let since: Vec<&str> = history.entries_since(start).into_iter().map(|(_, label)| label).collect();
+        assert_eq!(since, ["new"]);
+        assert_eq!(history.undo_since(Duration::from_secs(60), &mut cells), 1);
+        assert_eq!(cells, [1, 0]);
+        assert_eq!(history.undo_since(Duration::from_secs(60), &mut cells), 0);
+    }
+
+    #[test]
+    fn byte_budget_drops_branches_first() {
+        let mut cells = vec![0; 1];
+        let mut history = History::new();
+        history.set_branching(true);
+        set(&mut history, &mut cells, 0, 1, "one");
+        set(&mut history, &mut cells, 0, 2, "two");
+        history.undo(&mut cells);
+        set(&mut history, &mut cells, 0, 3, "three");
+        assert_eq!(history.branches().len(), 1);
+
+        // Short of room by a byte, which the branch makes up for
+        let steps = history.memory_usage() - 1;
+        history.set_byte_budget(Some(steps));
+        assert!(history.branches().is_empty());
+        assert_eq!(history.entries(), ["one", "three"]);
+
+        history.set_byte_budget(Some(steps / 2));
+        assert_eq!(history.entries(), ["three"]);
+        assert!(history.memory_usage() <= steps / 2);
+        history.set_byte_budget(Some(0));
+        assert!(!history.undoable());
     }
 }
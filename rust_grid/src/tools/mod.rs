@@ -1,5 +1,13 @@
 pub mod csv_read;
-pub use csv_read::{CsvReader, CsvWriter};
+pub use csv_read::{CsvDialect, CsvReader, CsvWriter, FlexPolicy, QuoteStyle, Terminator};
+#[cfg(feature = "serde")]
+pub mod csv_serde;
+
+#[cfg(feature = "async")]
+pub mod csv_async;
+
+#[cfg(feature = "encoding_rs")]
+pub mod decode;
 
 pub mod history;
 pub use history::{TargetMementoTrait, History};
@@ -0,0 +1,185 @@
+use crate::csv_table::render::{column_letters, display_cell};
+use crate::csv_table::CSVTable;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Cell, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+
+const MAX_COL_WIDTH: usize = 24; // longer cells are cut, ending them with '…'
+
+/// The grid's place in the table. Rows are by position among those passing the
+/// filter, as `print` numbers them.
+#[derive(Debug, Default)]
+struct Grid {
+    row: usize, // selected cell
+    col: usize,
+    top: usize, // first row and column shown
+    left: usize,
+    editing: Option<String>, // the new value of the selected cell, while it is edited
+    message: Option<String>, // shown in the status line until the next key
+    changed: bool,           // a cell was written
+    page: usize,             // rows shown at once, as of the last draw
+}
+
+/// Show `csv` as a grid to move around and edit in, until Esc or 'q' is pressed.
+/// Edits are undoable steps like `write`. Returns whether any cell was changed.
+pub fn run(csv: &mut CSVTable) -> io::Result<bool> {
+    let mut terminal = ratatui::init();
+    let result = run_grid(&mut terminal, csv);
+    ratatui::restore();
+    result
+}
+
+fn run_grid(terminal: &mut DefaultTerminal, csv: &mut CSVTable) -> io::Result<bool> {
+    let mut grid = Grid::default();
+    loop {
+        let rows = csv.visible_rows();
+        terminal.draw(|frame| grid.draw(frame, csv, &rows))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        grid.message = None;
+        let quit = if grid.editing.is_some() {
+            grid.edit(key, csv, &rows);
+            false
+        } else {
+            grid.navigate(key, csv, &rows)
+        };
+        if quit {
+            return Ok(grid.changed);
+        }
+    }
+}
+
+impl Grid {
+    /// Handle a key while moving around; true to leave the grid
+    fn navigate(&mut self, key: KeyEvent, csv: &mut CSVTable, rows: &[usize]) -> bool {
+        let last_row = rows.len().saturating_sub(1);
+        let last_col = csv.col_size().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return true,
+            KeyCode::Up | KeyCode::Char('k') => self.row = self.row.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.row = (self.row + 1).min(last_row),
+            KeyCode::Left | KeyCode::Char('h') => self.col = self.col.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => self.col = (self.col + 1).min(last_col),
+            KeyCode::PageUp => self.row = self.row.saturating_sub(self.page.max(1)),
+            KeyCode::PageDown => self.row = (self.row + self.page.max(1)).min(last_row),
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = last_col,
+            KeyCode::Enter | KeyCode::F(2) if !rows.is_empty() && csv.col_size() > 0 => {
+                self.editing = Some(csv.read_cell(rows[self.row], self.col).to_string());
+            }
+            KeyCode::Delete if !rows.is_empty() && csv.col_size() > 0 => self.write(csv, rows, String::new()),
+            _ => {}
+        }
+        false
+    }
+
+    /// Handle a key while editing the selected cell
+    fn edit(&mut self, key: KeyEvent, csv: &mut CSVTable, rows: &[usize]) {
+        let Some(value) = &mut self.editing else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.editing = None,
+            KeyCode::Enter => {
+                let value = std::mem::take(value);
+                self.editing = None;
+                self.write(csv, rows, value);
+            }
+            KeyCode::Backspace => {
+                value.pop();
+            }
+            KeyCode::Char(c) => value.push(c),
+            _ => {}
+        }
+    }
+
+    fn write(&mut self, csv: &mut CSVTable, rows: &[usize], value: String) {
+        let row_index = rows[self.row];
+        if csv.read_cell(row_index, self.col) == value {
+            return;
+        }
+        match csv.write_cell(row_index, self.col, &value) {
+            Ok(()) => self.changed = true,
+            Err(e) => self.message = Some(e.to_string()),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame, csv: &mut CSVTable, rows: &[usize]) {
+        let [table_area, status_area] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        self.row = self.row.min(rows.len().saturating_sub(1));
+        self.col = self.col.min(csv.col_size().saturating_sub(1));
+
+        // Scroll so that the selected cell is shown, below the column letters
+        self.page = (table_area.height as usize).saturating_sub(1).max(1);
+        if self.row < self.top {
+            self.top = self.row;
+        } else if self.row >= self.top + self.page {
+            self.top = self.row + 1 - self.page;
+        }
+        let shown = &rows[self.top.min(rows.len())..(self.top + self.page).min(rows.len())];
+        let number_width = rows.last().map_or(1, |row_index| row_index.to_string().len());
+        let widths: Vec<usize> = (0..csv.col_size())
+            .map(|col_index| {
+                let values = shown.iter().map(|&row_index| display_cell(csv.read_cell(row_index, col_index), None).chars().count());
+                values.max().unwrap_or(0).max(column_letters(col_index).len()).min(MAX_COL_WIDTH)
+            })
+            .collect();
+        self.left = self.left.min(self.col);
+        while self.left < self.col && !fits(&widths[self.left..=self.col], number_width, table_area) {
+            self.left += 1;
+        }
+
+        let selected = Style::default().add_modifier(Modifier::REVERSED);
+        let label = Style::default().add_modifier(Modifier::BOLD);
+        let columns: Vec<usize> = (self.left..widths.len()).collect();
+        let header = Row::new(std::iter::once(Cell::from("")).chain(columns.iter().map(|&col_index| {
+            let style = if col_index == self.col { selected } else { label };
+            Cell::from(column_letters(col_index)).style(style)
+        })));
+        let body: Vec<Row> = shown
+            .iter()
+            .enumerate()
+            .map(|(offset, &row_index)| {
+                let position = self.top + offset;
+                let number_style = if position == self.row { selected } else { label };
+                let number = Cell::from(row_index.to_string()).style(number_style);
+                let cells = columns.iter().map(|&col_index| {
+                    let value = display_cell(csv.read_cell(row_index, col_index), Some(widths[col_index]));
+                    let cell = Cell::from(value);
+                    if position == self.row && col_index == self.col { cell.style(selected) } else { cell }
+                });
+                Row::new(std::iter::once(number).chain(cells))
+            })
+            .collect();
+        let constraints = std::iter::once(number_width)
+            .chain(columns.iter().map(|&col_index| widths[col_index]))
+            .map(|width| Constraint::Length(width as u16));
+        frame.render_widget(Table::new(body, constraints).header(header), table_area);
+
+        let status = if rows.is_empty() || csv.col_size() == 0 {
+            "The table is empty. q: leave".to_string()
+        } else if let Some(value) = &self.editing {
+            format!("{}{}: {}█  Enter: write, Esc: cancel", column_letters(self.col), rows[self.row], value)
+        } else if let Some(message) = &self.message {
+            message.clone()
+        } else {
+            let value = display_cell(csv.read_cell(rows[self.row], self.col), None);
+            format!("{}{}: {}  arrows: move, Enter: edit, Del: clear, q: leave", column_letters(self.col), rows[self.row], value)
+        };
+        frame.render_widget(Paragraph::new(Line::from(status)), status_area);
+    }
+}
+
+/// Whether the row numbers and columns of `widths` fit across `area`, a space apart
+fn fits(widths: &[usize], number_width: usize, area: Rect) -> bool {
+    let total: usize = widths.iter().map(|width| width + 1).sum();
+    number_width + total <= area.width as usize
+}